@@ -0,0 +1,55 @@
+// The agent of `check_postgresql agent --socket <path>`: it stays resident, keeps its sessions to the servers open and
+// runs the checks of plugins started with `--agent <path>`, so those neither connect nor shake hands for TLS. A request
+// is the plugin's arguments, each terminated by a NUL byte, until the plugin shuts down its side of the connection.
+// The answer is the exit code on the first line, followed by the output.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::time::Duration;
+
+// Answers requests until the program is stopped, each on a thread of its own. Only failing to listen returns.
+pub fn serve(path: &str, handle: &(dyn Fn(Vec<String>) -> (String, i32) + Sync)) -> Result<(), String> {
+    // a socket left behind by an agent that is gone is replaced, one in use is not
+    if Path::new(path).exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(format!("Could not listen on {}: an agent is already running", path));
+        }
+        std::fs::remove_file(path).map_err(|err| format!("Could not remove stale socket {}: {}", path, err))?;
+    }
+    let listener = UnixListener::bind(path).map_err(|err| format!("Could not listen on {}: {}", path, err))?;
+    std::thread::scope(|scope| {
+        for stream in listener.incoming().flatten() {
+            // a plugin that does not send its request must not keep a thread
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+            scope.spawn(move || answer(stream, handle));
+        }
+    });
+    Ok(())
+}
+
+fn answer(stream: UnixStream, handle: &(dyn Fn(Vec<String>) -> (String, i32) + Sync)) -> std::io::Result<()> {
+    let mut arguments = vec![];
+    for argument in BufReader::new(&stream).split(0) {
+        arguments.push(String::from_utf8_lossy(&argument?).into_owned());
+    }
+    let (output, code) = handle(arguments);
+    write!(&stream, "{}\n{}", code, output)?;
+    (&stream).flush()
+}
+
+// Sends the arguments to the agent, returns its output and exit code
+pub fn request(path: &str, arguments: &[String]) -> Result<(String, i32), String> {
+    let error = |err: std::io::Error| format!("Could not reach the agent on {}: {}", path, err);
+    let mut stream = UnixStream::connect(path).map_err(error)?;
+    for argument in arguments {
+        stream.write_all(argument.as_bytes()).and_then(|_| stream.write_all(b"\0")).map_err(error)?;
+    }
+    stream.shutdown(std::net::Shutdown::Write).map_err(error)?;
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).map_err(error)?;
+    match answer.split_once('\n').map(|(code, output)| (code.parse::<i32>(), output)) {
+        Some((Ok(code), output)) => Ok((output.to_string(), code)),
+        _ => Err(format!("Invalid answer from the agent on {}", path)),
+    }
+}
@@ -0,0 +1,681 @@
+// The command line arguments. They are also the options of the checks, so a table of the configuration file or an
+// embedding program passes its options as arguments, too.
+
+// The command line interface, also used to validate the tables of the configuration file
+pub fn app() -> clap::App<'static, 'static> {
+    clap::App::new("check_postgresql")
+        .version("0.1.0")
+        .author("Jens Heyens")
+        .arg(clap::Arg::with_name("verbose")
+            .short("v")
+            .long("verbose")
+            .multiple(true)
+            .help("logs the connection and run times (-v), queries and threshold evaluation (-vv) and rows (-vvv) to stderr")
+            .required(false))
+        .arg(clap::Arg::with_name("config")
+            .long("config")
+            .value_name("FILE")
+            .help("reads connection defaults and named checks from a TOML file")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("check")
+            .long("check")
+            .value_name("NAME")
+            .help("runs the check NAME defined in the configuration file or a built-in one, may be given several times")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false))
+        .arg(clap::Arg::with_name("db-connection-string")
+            .short("d")
+            .long("db-connection-string")
+            .value_name("CONNINFO")
+            .help("The connection string, a postgresql:// URI, key=value pairs or user[:password]@host[:port][/database]")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("host")
+            .short("H")
+            .long("host")
+            .value_name("HOST")
+            .help("host to connect to instead of the connection string's, may be given several times to try them in order")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false))
+        .arg(clap::Arg::with_name("target")
+            .long("target")
+            .value_name("KIND")
+            .help("kind of server to connect to, the first host that is one is used (default: any)")
+            .takes_value(true)
+            .possible_values(&["any", "read-write", "read-only", "primary", "standby"])
+            .required(false))
+        .arg(clap::Arg::with_name("password-file")
+            .long("password-file")
+            .value_name("FILE")
+            .help("reads the password from FILE instead of the connection string or ~/.pgpass")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("vault-path")
+            .long("vault-path")
+            .value_name("PATH")
+            .help("reads the user and password from this Vault secret, e.g. secret/data/monitoring/pg or database/creds/monitoring")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("vault-addr")
+            .long("vault-addr")
+            .value_name("URL")
+            .help("address of the Vault server (default: $VAULT_ADDR)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("vault-cacert")
+            .long("vault-cacert")
+            .value_name("FILE")
+            .help("CA certificates to verify the Vault server with (default: $VAULT_CACERT)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("vault-role-id")
+            .long("vault-role-id")
+            .value_name("ID")
+            .help("logs in to Vault with this AppRole role ID instead of using $VAULT_TOKEN or ~/.vault-token")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("vault-secret-id-file")
+            .long("vault-secret-id-file")
+            .value_name("FILE")
+            .help("reads the AppRole secret ID from FILE")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("aws-iam-auth")
+            .long("aws-iam-auth")
+            .help("logs in to RDS with an IAM authentication token instead of a password")
+            .required(false))
+        .arg(clap::Arg::with_name("aws-region")
+            .long("aws-region")
+            .value_name("REGION")
+            .help("AWS region of the RDS instance (default: $AWS_REGION or the one of the host name)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("azure-ad-auth")
+            .long("azure-ad-auth")
+            .help("logs in to Azure Database for PostgreSQL with an Entra ID (Azure AD) access token instead of a password")
+            .conflicts_with("aws-iam-auth")
+            .required(false))
+        .arg(clap::Arg::with_name("azure-client-id")
+            .long("azure-client-id")
+            .value_name("ID")
+            .help("client ID of the service principal or user-assigned managed identity (default: $AZURE_CLIENT_ID)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("azure-tenant-id")
+            .long("azure-tenant-id")
+            .value_name("ID")
+            .help("tenant of the service principal (default: $AZURE_TENANT_ID)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("azure-client-secret-file")
+            .long("azure-client-secret-file")
+            .value_name("FILE")
+            .help("reads the service principal's client secret from FILE (default: $AZURE_CLIENT_SECRET)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("sslmode")
+            .long("sslmode")
+            .value_name("MODE")
+            .help("whether to use TLS and how to verify the server's certificate (default: prefer)")
+            .takes_value(true)
+            .possible_values(&["disable", "prefer", "require", "verify-ca", "verify-full"])
+            .required(false))
+        .arg(clap::Arg::with_name("sslcert")
+            .long("sslcert")
+            .value_name("FILE")
+            .help("client certificate to authenticate with (default: ~/.postgresql/postgresql.crt)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("sslkey")
+            .long("sslkey")
+            .value_name("FILE")
+            .help("private key of the client certificate (default: ~/.postgresql/postgresql.key)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("sslpassword")
+            .long("sslpassword")
+            .value_name("PASSPHRASE")
+            .help("passphrase of an encrypted private key")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("sslrootcert")
+            .long("sslrootcert")
+            .value_name("FILE")
+            .help("CA certificates to verify the server's certificate with, 'system' for only the system's ones (default: the system's CAs and ~/.postgresql/root.crt)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("sslcrl")
+            .long("sslcrl")
+            .value_name("FILE")
+            .help("certificate revocation lists the server's certificate chain is checked against")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("ssl-hostname")
+            .long("ssl-hostname")
+            .value_name("NAME")
+            .help("name the server's certificate needs to match with sslmode verify-full (default: the host connected to)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("krb-srvname")
+            .long("krb-srvname")
+            .value_name("NAME")
+            .help("Kerberos service name of the server for GSSAPI authentication (default: $PGKRBSRVNAME or postgres)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("connect-timeout")
+            .long("connect-timeout")
+            .value_name("SECONDS")
+            .help("maximum time to wait for the connection to be established")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("client-encoding")
+            .long("client-encoding")
+            .value_name("ENCODING")
+            .help("client_encoding of the session, one of UTF8, LATIN1, LATIN9 or WIN1252 (default: UTF8)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("on-connection-error")
+            .long("on-connection-error")
+            .value_name("STATUS")
+            .help("status if the connection fails, e.g. because the server is down (default: unknown)")
+            .takes_value(true)
+            .possible_values(&["unknown", "warning", "critical"])
+            .required(false))
+        .arg(clap::Arg::with_name("retries")
+            .long("retries")
+            .value_name("N")
+            .help("retries a connection that could not be established N times (default: 0)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("retry-delay")
+            .long("retry-delay")
+            .value_name("MS")
+            .help("milliseconds to wait before the first retry, doubled for every further one (default: 1000)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("on-connect-timeout")
+            .long("on-connect-timeout")
+            .value_name("STATUS")
+            .help("status if the connect timeout elapses (default: like --on-connection-error)")
+            .takes_value(true)
+            .possible_values(&["unknown", "critical"])
+            .required(false))
+        .arg(clap::Arg::with_name("statement-timeout")
+            .long("statement-timeout")
+            .value_name("SECONDS")
+            .help("sets the session's statement_timeout, so the server cancels a query running longer")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("on-statement-timeout")
+            .long("on-statement-timeout")
+            .value_name("STATUS")
+            .help("status if the statement timeout elapses (default: unknown)")
+            .takes_value(true)
+            .possible_values(&["unknown", "critical"])
+            .required(false))
+        .arg(clap::Arg::with_name("timeout")
+            .short("t")
+            .long("timeout")
+            .value_name("SECONDS")
+            .help("bounds the whole run, connecting and all checks, and exits with a result when it elapses")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("on-timeout")
+            .long("on-timeout")
+            .value_name("STATUS")
+            .help("status if the timeout elapses (default: unknown)")
+            .takes_value(true)
+            .possible_values(&["unknown", "warning", "critical"])
+            .required(false))
+        .arg(clap::Arg::with_name("map")
+            .long("map")
+            .value_name("FROM=TO[,...]")
+            .help("replaces statuses before they become the exit code, e.g. warning=ok or unknown=critical")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("state-dir")
+            .long("state-dir")
+            .value_name("DIR")
+            .help("keeps values between runs in DIR, e.g. counters (default: /var/tmp/check_postgresql)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("output")
+            .long("output")
+            .value_name("FORMAT")
+            .help("prints the results as nagios plugin output, a json document, checkmk local checks, zabbix_sender input, influx line protocol or graphite plaintext (default: nagios)")
+            .takes_value(true)
+            .possible_values(&["nagios", "json", "checkmk", "zabbix", "influx", "graphite"])
+            .required(false))
+        .arg(clap::Arg::with_name("dry-run")
+            .long("dry-run")
+            .help("prints the connection, queries, thresholds and output format that would be used and exits UNKNOWN without connecting")
+            .required(false))
+        .arg(clap::Arg::with_name("metric-prefix")
+            .long("metric-prefix")
+            .value_name("PREFIX")
+            .help("prefix of the metric paths of --output graphite and --statsd-host (default: check_postgresql)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("statsd-host")
+            .long("statsd-host")
+            .value_name("HOST[:PORT]")
+            .help("also send the perfdata as gauges to this statsd over UDP (default port: 8125)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("otlp-endpoint")
+            .long("otlp-endpoint")
+            .value_name("URL")
+            .help("also export each run as a trace and its perfdata as metrics to this OTLP/HTTP collector, e.g. http://localhost:4318")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("otlp-cacert")
+            .long("otlp-cacert")
+            .value_name("FILE")
+            .help("CA certificates to verify the OTLP collector with")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("zabbix-host")
+            .long("zabbix-host")
+            .value_name("HOST")
+            .help("host of the items of --output zabbix (default: -, the host zabbix_sender is configured with)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("listen")
+            .long("listen")
+            .value_name("ADDRESS")
+            .help("stays resident and serves the results as Prometheus metrics on ADDRESS, e.g. 0.0.0.0:9187")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("nrpe-listen")
+            .long("nrpe-listen")
+            .value_name("ADDRESS")
+            .help("stays resident and answers NRPE requests on ADDRESS, e.g. 0.0.0.0:5666, the command names the check")
+            .takes_value(true)
+            .conflicts_with_all(&["listen", "agent"])
+            .required(false))
+        .arg(clap::Arg::with_name("nrpe-no-ssl")
+            .long("nrpe-no-ssl")
+            .help("answers NRPE requests without TLS, for check_nrpe -n")
+            .required(false))
+        .arg(clap::Arg::with_name("nrpe-ssl-cert")
+            .long("nrpe-ssl-cert")
+            .value_name("FILE")
+            .help("certificate of the NRPE listener instead of anonymous Diffie-Hellman")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("nrpe-ssl-key")
+            .long("nrpe-ssl-key")
+            .value_name("FILE")
+            .help("private key of the --nrpe-ssl-cert")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("nrpe-allowed-hosts")
+            .long("nrpe-allowed-hosts")
+            .value_name("ADDRESSES")
+            .help("comma separated IP addresses NRPE requests are answered for (default: any)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("agent")
+            .long("agent")
+            .value_name("SOCKET")
+            .help("runs the checks in the agent listening on SOCKET, which keeps its connections open")
+            .takes_value(true)
+            .conflicts_with("listen")
+            .required(false))
+        .arg(clap::Arg::with_name("rate")
+            .long("rate")
+            .help("numeric columns of --query are counters, evaluated by their increase per second since the previous run")
+            .conflicts_with("delta")
+            .required(false))
+        .arg(clap::Arg::with_name("delta")
+            .long("delta")
+            .help("numeric columns of --query are counters, evaluated by their increase since the previous run")
+            .required(false))
+        .arg(clap::Arg::with_name("query")
+            .short("q")
+            .long("query")
+            .value_name("QUERY")
+            .help("The PG query to execute, may be given several times")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false))
+        .arg(clap::Arg::with_name("param")
+            .long("param")
+            .value_name("VALUE")
+            .help("binds VALUE to the next parameter $1, $2, ... of the query, may be given several times")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .conflicts_with("simple-protocol")
+            .required(false))
+        .arg(clap::Arg::with_name("param-type")
+            .long("param-type")
+            .value_name("TYPE")
+            .help("type of the next parameter, e.g. integer or text (default: inferred by the server)")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false))
+        .arg(clap::Arg::with_name("query-file")
+            .long("query-file")
+            .value_name("FILE")
+            .help("reads a query to execute from FILE, may be given several times")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .required(false))
+        .arg(clap::Arg::with_name("backup-query")
+            .long("backup-query")
+            .value_name("QUERY")
+            .help("query returning the time of the last base backup for the backup-age check")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("simple-protocol")
+            .long("simple-protocol")
+            .help("runs --query with the simple query protocol, e.g. on PgBouncer's admin console")
+            .required(false))
+        .arg(clap::Arg::with_name("database")
+            .long("database")
+            .value_name("db1[,db2...]")
+            .help("databases checked by built-in checks (default: all)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("table")
+            .long("table")
+            .value_name("table1[,table2...]")
+            .help("tables checked by the rowcount check, e.g. schema.table, the history table of the migration check, the views of the matview-age check or the tables of the partitions check")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("refresh-log")
+            .long("refresh-log")
+            .value_name("TABLE")
+            .help("table with the matview and refreshed_at of every refresh, for the matview-age check")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("ahead")
+            .long("ahead")
+            .value_name("DURATION")
+            .help("time from now the partitions check needs partitions for (default: 7d)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("exact")
+            .long("exact")
+            .help("counts rows instead of using the planner's estimate")
+            .required(false))
+        .arg(clap::Arg::with_name("compare-with")
+            .long("compare-with")
+            .value_name("CONNINFO")
+            .help("connection string of the server the consistency check compares with, e.g. a replica")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("compare-query")
+            .long("compare-query")
+            .value_name("QUERY")
+            .help("query the consistency check runs on both servers, with an ORDER BY")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("relative")
+            .long("relative")
+            .help("checks the differences of the consistency check in percent of the server's values")
+            .required(false))
+        .arg(clap::Arg::with_name("include-table")
+            .long("include-table")
+            .value_name("regex1[,regex2...]")
+            .help("only matching tables are checked by built-in checks, matched against schema.table")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("all-databases")
+            .long("all-databases")
+            .help("runs the checks in every database that accepts connections but templates, each with a connection of its own")
+            .required(false))
+        .arg(clap::Arg::with_name("exclude-database")
+            .long("exclude-database")
+            .value_name("regex1[,regex2...]")
+            .help("databases skipped by --all-databases")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("exclude-table")
+            .long("exclude-table")
+            .value_name("regex1[,regex2...]")
+            .help("tables skipped by built-in checks, matched against schema.table")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("exclude-user")
+            .long("exclude-user")
+            .value_name("regex1[,regex2...]")
+            .help("sessions of matching users are skipped by built-in checks")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("exclude-application")
+            .long("exclude-application")
+            .value_name("regex1[,regex2...]")
+            .help("sessions of matching application names are skipped by built-in checks")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("exclude-maintenance")
+            .long("exclude-maintenance")
+            .help("pg_dump, VACUUM, ANALYZE and REINDEX are skipped by built-in checks")
+            .required(false))
+        .arg(clap::Arg::with_name("top")
+            .long("top")
+            .value_name("N")
+            .help("number of worst offenders listed by built-in checks (default: 5)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("expect")
+            .long("expect")
+            .value_name("VALUE")
+            .help("expected value of built-in checks, e.g. primary or standby for the role check, the version of the migration check, or the number every column of a query needs to equal to not be CRITICAL")
+            .takes_value(true)
+            .conflicts_with("compare")
+            .required(false))
+        .arg(clap::Arg::with_name("expect-warning")
+            .long("expect-warning")
+            .value_name("n1[,n2...]")
+            .help("the number every column of a query needs to equal to not be WARNING")
+            .takes_value(true)
+            .conflicts_with("compare")
+            .required(false))
+        .arg(clap::Arg::with_name("settings-file")
+            .long("settings-file")
+            .value_name("FILE")
+            .help("expected settings for the settings check, in postgresql.conf syntax")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("require")
+            .long("require")
+            .value_name("name1[>=version][,name2...]")
+            .help("extensions required by the extensions check")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("min")
+            .long("min")
+            .value_name("VERSION")
+            .help("oldest server version the version check accepts, e.g. 14.5")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("max")
+            .long("max")
+            .value_name("VERSION")
+            .help("newest server version the version check accepts, e.g. 16 for every 16.x")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("warn")
+            .short("w")
+            .long("warn")
+            .value_name("range1[,range2...]")
+            .help("defines warning result ranges (default: ~:0)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("critical")
+            .short("c")
+            .long("critical")
+            .value_name("range1[,range2...]")
+            .help("defines critical result ranges (default: ~:1)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("strict-thresholds")
+            .long("strict-thresholds")
+            .help("requires a warning and critical range for every column instead of applying a single one to all")
+            .required(false))
+        .arg(clap::Arg::with_name("compare")
+            .long("compare")
+            .value_name("op1[,op2...]")
+            .help("compares the result with plain warning and critical numbers instead of ranges, one of ge, le, gt, lt, eq or ne per column")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("labels")
+            .long("labels")
+            .value_name("l1[,l2...]")
+            .help("labels of the columns in the output and performance data (default: col1, col2, ...)")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("column-labels")
+            .long("column-labels")
+            .help("labels the columns by their names in the query, unless --labels gives one")
+            .required(false))
+        .arg(clap::Arg::with_name("uom")
+            .long("uom")
+            .value_name("u1[,u2...]")
+            .help("units of measurement reported in the performance data, e.g. s, %, B or c")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("perf-min")
+            .long("perf-min")
+            .value_name("n1[,n2...]")
+            .help("minimum values reported in the performance data, empty entries are omitted")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("perf-max")
+            .long("perf-max")
+            .value_name("n1[,n2...]")
+            .help("maximum values reported in the performance data, empty entries are omitted")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("expect-string")
+            .long("expect-string")
+            .value_name("STRING")
+            .help("text columns are expected to equal STRING")
+            .takes_value(true)
+            .conflicts_with("expect-regex")
+            .required(false))
+        .arg(clap::Arg::with_name("expect-regex")
+            .long("expect-regex")
+            .value_name("REGEX")
+            .help("text columns are expected to match REGEX")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("warn-regex")
+            .long("warn-regex")
+            .value_name("REGEX")
+            .help("text columns matching REGEX are WARNING")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("critical-regex")
+            .long("critical-regex")
+            .value_name("REGEX")
+            .help("text columns matching REGEX are CRITICAL")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("mismatch-status")
+            .long("mismatch-status")
+            .value_name("STATUS")
+            .help("status if a text column does not meet the expectation (default: critical)")
+            .takes_value(true)
+            .possible_values(&["warning", "critical"])
+            .required(false))
+        .arg(clap::Arg::with_name("invert-bool")
+            .long("invert-bool")
+            .help("boolean columns are OK if false and CRITICAL if true")
+            .required(false))
+        .arg(clap::Arg::with_name("null-is")
+            .long("null-is")
+            .value_name("POLICY")
+            .help("status for NULL values, zero compares them as 0 (default: unknown)")
+            .takes_value(true)
+            .possible_values(&["ok", "warning", "critical", "unknown", "zero"])
+            .required(false))
+        .arg(clap::Arg::with_name("rows-warning")
+            .long("rows-warning")
+            .value_name("RANGE")
+            .help("warning range of the number of rows the query returns, columns are only evaluated if they have thresholds too")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("rows-critical")
+            .long("rows-critical")
+            .value_name("RANGE")
+            .help("critical range of the number of rows the query returns")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("time-warning")
+            .long("time-warning")
+            .value_name("RANGE")
+            .help("warning range of the query's run time in seconds, e.g. 500ms or 2s")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("time-critical")
+            .long("time-critical")
+            .value_name("RANGE")
+            .help("critical range of the query's run time in seconds")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("on-empty")
+            .long("on-empty")
+            .value_name("STATUS")
+            .help("status if the query returns no rows, e.g. ok for a query returning the rows that violate an invariant (default: unknown)")
+            .takes_value(true)
+            .possible_values(&["ok", "warning", "critical", "unknown"])
+            .required(false))
+        .arg(clap::Arg::with_name("aggregate")
+            .long("aggregate")
+            .value_name("a1[,a2...]")
+            .help("reduces the columns of all rows by max, min, sum, avg or count, instead of evaluating the first row")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("row-mode")
+            .long("row-mode")
+            .value_name("MODE")
+            .help("evaluates the first row, every row reporting the worst or every row reporting all (default: first)")
+            .takes_value(true)
+            .possible_values(&["first", "worst", "all"])
+            .conflicts_with("aggregate")
+            .required(false))
+        .arg(clap::Arg::with_name("output-format")
+            .long("output-format")
+            .value_name("TEMPLATE")
+            .help("status line of --query with placeholders like {col1}, {warn1}, {status}, {host}, {db} and {duration}")
+            .takes_value(true)
+            .required(false))
+        .arg(clap::Arg::with_name("details")
+            .long("details")
+            .help("summarizes the alerting columns of --query in the status line, followed by a line per column")
+            .required(false))
+        .arg(clap::Arg::with_name("precision")
+            .short("p")
+            .long("precision")
+            .value_name("DIGITS")
+            .help("rounds floating point results to the given number of decimal places")
+            .takes_value(true)
+            .required(false))
+        .subcommand(clap::SubCommand::with_name("agent")
+            .about("stays resident and runs the checks of plugins started with --agent, keeping the connections open")
+            .arg(clap::Arg::with_name("socket")
+                .long("socket")
+                .value_name("PATH")
+                .help("listens on the unix socket PATH, e.g. /run/check_pg.sock")
+                .takes_value(true)
+                .required(true)))
+}
+
+// Parses arguments like the command line, without the program name. Errors are a single line without clap's help.
+pub fn parse(arguments : Vec<String>) -> Result<clap::ArgMatches<'static>, String> {
+    let argv = std::iter::once("check_postgresql".to_string()).chain(arguments);
+    app().setting(clap::AppSettings::ColorNever).get_matches_from_safe(argv)
+        .map_err(|err| err.message.lines().next().unwrap_or("").trim_start_matches("error: ").to_string())
+}
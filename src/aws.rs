@@ -0,0 +1,156 @@
+// RDS IAM authentication: with `--aws-iam-auth`, the password is an authentication token, a request to connect as
+// the user signed with AWS Signature Version 4 that RDS accepts for 15 minutes. The AWS credentials are those of
+// `$AWS_ACCESS_KEY_ID` and `$AWS_SECRET_ACCESS_KEY` (with `$AWS_SESSION_TOKEN`), of the ECS task role or of the EC2
+// instance's IAM role, in this order. The region is `--aws-region`, `$AWS_REGION`, `$AWS_DEFAULT_REGION` or the one of
+// the RDS endpoint's host name.
+
+use crate::http;
+use crate::json;
+use crate::verbose;
+use openssl::hash::{hash, MessageDigest};
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// RDS accepts at most 15 minutes, a token is reused for 10 of them
+const EXPIRES: u64 = 900;
+const REUSE: Duration = Duration::from_secs(600);
+
+const METADATA: &str = "http://169.254.169.254";
+const CONTAINER_METADATA: &str = "http://169.254.170.2";
+
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+// Tokens by the endpoint, user and region they are for
+static TOKENS: Mutex<Vec<(String, String, Instant)>> = Mutex::new(Vec::new());
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha256(data: &[u8]) -> Result<Vec<u8>, String> {
+    hash(MessageDigest::sha256(), data).map(|digest| digest.to_vec()).map_err(|err| format!("Could not hash: {}", err))
+}
+
+fn hmac(key: &[u8], data: &str) -> Result<Vec<u8>, String> {
+    let error = |err: openssl::error::ErrorStack| format!("Could not sign: {}", err);
+    let key = PKey::hmac(key).map_err(error)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(error)?;
+    signer.update(data.as_bytes()).map_err(error)?;
+    signer.sign_to_vec().map_err(error)
+}
+
+// The UTC date and time of a Unix timestamp, as `YYYYMMDDTHHMMSSZ`
+fn timestamp(seconds: u64) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+    // Howard Hinnant's civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}{:02}{:02}T{:02}{:02}{:02}Z", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+// The credentials of a metadata service's JSON document
+fn parse_credentials(body: &str, source: &str) -> Result<Credentials, String> {
+    let document = json::parse(body).map_err(|err| format!("Invalid credentials from {}: {}", source, err))?;
+    let field = |name: &str| document.get(name).and_then(|value| value.as_str()).map(|value| value.to_string());
+    match (field("AccessKeyId"), field("SecretAccessKey")) {
+        (Some(access_key), Some(secret_key)) => Ok(Credentials { access_key, secret_key, session_token: field("Token") }),
+        _ => Err(format!("No credentials from {}", source)),
+    }
+}
+
+fn credentials() -> Result<Credentials, String> {
+    let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    if let (Some(access_key), Some(secret_key)) = (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+        return Ok(Credentials { access_key, secret_key, session_token: var("AWS_SESSION_TOKEN") });
+    }
+    if let Some(uri) = var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI") {
+        verbose::log(1, || "Fetching the credentials of the ECS task role".to_string());
+        let response = http::request("GET", &format!("{}{}", CONTAINER_METADATA, uri), &[], None, None)?;
+        return parse_credentials(&response.body, "the ECS task role");
+    }
+    // IMDSv2 needs a session token first
+    verbose::log(1, || "Fetching the credentials of the instance's IAM role".to_string());
+    let response = http::request("PUT", &format!("{}/latest/api/token", METADATA), &[("X-aws-ec2-metadata-token-ttl-seconds", "60")], Some(""), None)
+        .map_err(|err| format!("No AWS credentials in the environment and no instance metadata: {}", err))?;
+    if response.status != 200 {
+        return Err(format!("The instance metadata service answered with HTTP status {}", response.status));
+    }
+    let headers = [("X-aws-ec2-metadata-token", response.body.trim())];
+    let url = format!("{}/latest/meta-data/iam/security-credentials/", METADATA);
+    let roles = http::request("GET", &url, &headers, None, None)?;
+    let role = roles.body.lines().next().filter(|_| roles.status == 200).ok_or("The instance has no IAM role")?.trim().to_string();
+    let response = http::request("GET", &format!("{}{}", url, role), &headers, None, None)?;
+    parse_credentials(&response.body, &format!("the IAM role {}", role))
+}
+
+// The region of `--aws-region`, the environment or an endpoint like `db1.abc123.eu-central-1.rds.amazonaws.com`
+pub fn region(region: Option<&str>, host: &str) -> Result<String, String> {
+    region.map(|region| region.to_string())
+        .or_else(|| env::var("AWS_REGION").ok().filter(|region| !region.is_empty()))
+        .or_else(|| env::var("AWS_DEFAULT_REGION").ok().filter(|region| !region.is_empty()))
+        .or_else(|| {
+            let labels: Vec<&str> = host.split('.').collect();
+            let rds = labels.iter().position(|&label| label == "rds")?;
+            labels.get(rds.checked_sub(1)?).map(|region| region.to_string())
+        })
+        .ok_or_else(|| format!("No AWS region for {}, use --aws-region", host))
+}
+
+// The authentication token to log in as `user` at the endpoint
+pub fn auth_token(host: &str, port: &str, user: &str, region: &str) -> Result<String, String> {
+    let key = format!("{}:{}\0{}\0{}", host, port, user, region);
+    {
+        let mut tokens = TOKENS.lock().unwrap_or_else(|err| err.into_inner());
+        tokens.retain(|(_, _, created)| created.elapsed() < REUSE);
+        if let Some((_, token, _)) = tokens.iter().find(|(k, _, _)| *k == key) {
+            return Ok(token.clone());
+        }
+    }
+
+    let credentials = credentials()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let amz_date = timestamp(now);
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/rds-db/aws4_request", date, region);
+    let mut query = vec![
+        ("Action", "connect".to_string()),
+        ("DBUser", user.to_string()),
+        ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+        ("X-Amz-Credential", format!("{}/{}", credentials.access_key, scope)),
+        ("X-Amz-Date", amz_date.clone()),
+        ("X-Amz-Expires", EXPIRES.to_string()),
+        ("X-Amz-SignedHeaders", "host".to_string()),
+    ];
+    if let Some(ref session_token) = credentials.session_token {
+        query.push(("X-Amz-Security-Token", session_token.clone()));
+    }
+    query.sort();
+    let query = query.iter().map(|(name, value)| format!("{}={}", name, http::encode(value))).collect::<Vec<_>>().join("&");
+
+    let endpoint = format!("{}:{}", host, port);
+    let canonical = format!("GET\n/\n{}\nhost:{}\n\nhost\n{}", query, endpoint, hex(&sha256(b"")?));
+    let to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex(&sha256(canonical.as_bytes())?));
+    let mut signing_key = hmac(format!("AWS4{}", credentials.secret_key).as_bytes(), date)?;
+    for part in [region, "rds-db", "aws4_request"] {
+        signing_key = hmac(&signing_key, part)?;
+    }
+    let token = format!("{}/?{}&X-Amz-Signature={}", endpoint, query, hex(&hmac(&signing_key, &to_sign)?));
+    verbose::log(1, || format!("Generated an RDS IAM authentication token for {} at {}", user, endpoint));
+    TOKENS.lock().unwrap_or_else(|err| err.into_inner()).push((key, token.clone(), Instant::now()));
+    Ok(token)
+}
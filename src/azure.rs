@@ -0,0 +1,109 @@
+// Microsoft Entra ID (Azure AD) authentication for Azure Database for PostgreSQL: with `--azure-ad-auth`, the password
+// is an access token for the database service. With a client secret (`--azure-client-secret-file` or
+// `$AZURE_CLIENT_SECRET`), the token is that of a service principal of `--azure-tenant-id`, otherwise the one of the
+// managed identity of the VM or App Service the plugin runs on. `--azure-client-id` selects the service principal or a
+// user-assigned identity.
+
+use crate::http;
+use crate::json;
+use crate::options::Options;
+use crate::verbose;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const RESOURCE: &str = "https://ossrdbms-aad.database.windows.net";
+const METADATA: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+// Tokens by the identity they are for, until two thirds of their lifetime have passed
+static TOKENS: Mutex<Vec<(String, String, Instant)>> = Mutex::new(Vec::new());
+
+// The access token and its lifetime in seconds of a token endpoint's answer
+fn parse_token(status: u16, body: &str, source: &str) -> Result<(String, f64), String> {
+    let document = json::parse(body).map_err(|err| format!("Invalid answer from {}: {}", source, err))?;
+    if status != 200 {
+        let description = document.get("error_description").or_else(|| document.get("error")).and_then(|error| error.as_str());
+        return Err(match description {
+            Some(description) => format!("HTTP status {} from {}: {}", status, source, description.lines().next().unwrap_or("")),
+            None => format!("HTTP status {} from {}", status, source),
+        });
+    }
+    let token = document.get("access_token").and_then(|token| token.as_str()).ok_or_else(|| format!("No access token from {}", source))?;
+    // the managed identity endpoints send the lifetime as a string
+    let expires_in = document.get("expires_in")
+        .and_then(|expires| expires.as_f64().or_else(|| expires.as_str().and_then(|expires| expires.parse().ok())))
+        .unwrap_or(0.0);
+    Ok((token.to_string(), expires_in))
+}
+
+fn service_principal(tenant: &str, client_id: &str, secret: &str) -> Result<(String, f64), String> {
+    verbose::log(1, || format!("Requesting an access token for the service principal {}", client_id));
+    let body = format!("grant_type=client_credentials&client_id={}&client_secret={}&scope={}", http::encode(client_id),
+                       http::encode(secret), http::encode(&format!("{}/.default", RESOURCE)));
+    let url = format!("https://login.microsoftonline.com/{}/oauth2/v2.0/token", http::encode(tenant));
+    let response = http::request("POST", &url, &[("Content-Type", "application/x-www-form-urlencoded")], Some(&body), None)?;
+    parse_token(response.status, &response.body, "Entra ID")
+}
+
+fn managed_identity(client_id: Option<&str>) -> Result<(String, f64), String> {
+    verbose::log(1, || "Requesting an access token for the managed identity".to_string());
+    let client_id = client_id.map(|id| format!("&client_id={}", http::encode(id))).unwrap_or_default();
+    // App Service and Functions have an endpoint of their own, VMs the instance metadata service
+    if let (Ok(endpoint), Ok(header)) = (env::var("IDENTITY_ENDPOINT"), env::var("IDENTITY_HEADER")) {
+        let url = format!("{}?api-version=2019-08-01&resource={}{}", endpoint, http::encode(RESOURCE), client_id);
+        let response = http::request("GET", &url, &[("X-IDENTITY-HEADER", &header)], None, None)?;
+        return parse_token(response.status, &response.body, "the managed identity endpoint");
+    }
+    let url = format!("{}?api-version=2018-02-01&resource={}{}", METADATA, http::encode(RESOURCE), client_id);
+    let response = http::request("GET", &url, &[("Metadata", "true")], None, None)
+        .map_err(|err| format!("No client secret and no managed identity: {}", err))?;
+    parse_token(response.status, &response.body, "the instance metadata service")
+}
+
+// The identity tokens are requested for, with the client secret of a service principal
+#[derive(Clone, Debug)]
+pub struct Identity {
+    tenant: Option<String>,
+    client_id: Option<String>,
+    secret: Option<String>,
+}
+
+// The identity of the options and the environment
+pub fn identity(options: &Options) -> Result<Identity, String> {
+    let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+    let client_id = options.value_of("azure-client-id").map(|id| id.to_string()).or_else(|| var("AZURE_CLIENT_ID"));
+    let secret = match options.value_of("azure-client-secret-file") {
+        Some(path) => Some(std::fs::read_to_string(path).map_err(|err| format!("Could not read client secret file '{}': {}", path, err))?
+            .trim().to_string()),
+        None => var("AZURE_CLIENT_SECRET"),
+    };
+    let tenant = options.value_of("azure-tenant-id").map(|id| id.to_string()).or_else(|| var("AZURE_TENANT_ID"));
+    match (&secret, &client_id, &tenant) {
+        (Some(_), Some(_), None) => Err("A service principal needs --azure-tenant-id or $AZURE_TENANT_ID".to_string()),
+        (Some(_), None, _) => Err("A service principal needs --azure-client-id or $AZURE_CLIENT_ID".to_string()),
+        _ => Ok(Identity { tenant, client_id, secret }),
+    }
+}
+
+impl Identity {
+    // The access token to log in with
+    pub fn access_token(&self) -> Result<String, String> {
+        let key = format!("{:?}\0{:?}\0{:?}", self.tenant, self.client_id, self.secret);
+        {
+            let mut tokens = TOKENS.lock().unwrap_or_else(|err| err.into_inner());
+            tokens.retain(|(_, _, renew)| *renew > Instant::now());
+            if let Some((_, token, _)) = tokens.iter().find(|(k, _, _)| *k == key) {
+                return Ok(token.clone());
+            }
+        }
+        let (token, expires_in) = match (&self.secret, &self.tenant, &self.client_id) {
+            (Some(secret), Some(tenant), Some(client_id)) => service_principal(tenant, client_id, secret)?,
+            (_, _, client_id) => managed_identity(client_id.as_deref())?,
+        };
+        if expires_in >= 3.0 {
+            let renew = Instant::now() + Duration::from_secs_f64(expires_in * 2.0 / 3.0);
+            TOKENS.lock().unwrap_or_else(|err| err.into_inner()).push((key, token.clone(), renew));
+        }
+        Ok(token)
+    }
+}
@@ -0,0 +1,51 @@
+// Client sessions by state, by `pg_stat_activity`: active, idle, idle in transaction (including aborted transactions)
+// and waiting for a lock. A waiting session is active too. Every state has a threshold of its own, e.g. `-w ,,5,1`
+// for sessions idle in transaction and waiting.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT count(*) FILTER (WHERE state = 'active'), \
+                            count(*) FILTER (WHERE state = 'idle'), \
+                            count(*) FILTER (WHERE state IN ('idle in transaction', 'idle in transaction (aborted)')), \
+                            count(*) FILTER (WHERE wait_event_type = 'Lock') \
+                     FROM pg_stat_activity WHERE backend_type = 'client backend'";
+
+const STATES: [(&str, &str); 4] = [("active", "active"), ("idle", "idle"), ("idle-in-transaction", "idle in transaction"), ("waiting", "waiting")];
+
+struct Activity {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let metrics: Vec<&str> = STATES.iter().map(|&(metric, _)| metric).collect();
+    Ok(Box::new(Activity { thresholds: Thresholds::new(options, &metrics, None, None)? }))
+}
+
+impl Check for Activity {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let row = session.query_one(QUERY, &[]).await?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut descriptions = vec![];
+            for (i, &(metric, state)) in STATES.iter().enumerate() {
+                let count: i64 = column(&row, i)?;
+                let t = self.thresholds.status(i, count as f64);
+                status.t = status.t.worst(t);
+                descriptions.push(match t {
+                    StatusType::OK => format!("{} {}", count, state),
+                    t => format!("{} {} ({})", count, state, t),
+                });
+                status.perfdata.push(self.thresholds.perfdata(i, metric, count as f64).min(Some(0.0)));
+            }
+            status.description = format!("Sessions: {}", descriptions.join(", "));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,73 @@
+// WAL archiving health from `pg_stat_archiver`: the archive failures since the previous run, kept in the state
+// directory, and the time since the last segment was archived. On the first run, failures are not compared yet.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT archived_count, failed_count, extract(epoch FROM now() - last_archived_time)::float8, \
+                            coalesce(last_failed_wal, ''), \
+                            coalesce(last_failed_time > coalesce(last_archived_time, '-infinity'), false), \
+                            current_setting('archive_mode') \
+                     FROM pg_stat_archiver";
+
+struct Archiver {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Archiver { thresholds: Thresholds::new(options, &["failures", "seconds"], Some("0,"), Some("10,"))? }))
+}
+
+impl Check for Archiver {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let row = session.query_one(QUERY, &[]).await?;
+            let archived = column::<i64>(&row, 0)? as f64;
+            let failed = column::<i64>(&row, 1)? as f64;
+            let age: Option<f64> = column(&row, 2)?;
+            let last_failed_wal: String = column(&row, 3)?;
+            let failing: bool = column(&row, 4)?;
+            let mode: String = column(&row, 5)?;
+            if mode == "off" {
+                return Err(Status::new(StatusType::UNKNOWN, "WAL archiving is disabled (archive_mode is off)".to_string()));
+            }
+
+            let mut state = session.state("archiver")?;
+            let failures = state.delta("failed_count", failed);
+            state.save()?;
+
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut parts = vec![match failures {
+                Some(failures) => format!("{} failed archive attempts since the previous run", failures),
+                None => format!("{} failed archive attempts since the statistics reset (first run)", failed),
+            }];
+            parts.push(match age {
+                Some(age) => format!("last archived {}s ago", age.round()),
+                None => "nothing archived yet".to_string(),
+            });
+            if failing {
+                parts.push(format!("currently failing on {}", last_failed_wal));
+            }
+            status.description = parts.join(", ");
+
+            if let Some(failures) = failures {
+                status.t = status.t.worst(self.thresholds.status(0, failures));
+                status.perfdata.push(self.thresholds.perfdata(0, "failures", failures).min(Some(0.0)));
+            }
+            if let Some(age) = age {
+                status.t = status.t.worst(self.thresholds.status(1, age));
+                status.perfdata.push(self.thresholds.perfdata(1, "last_archived", (age * 1000.0).round() / 1000.0).uom("s").min(Some(0.0)));
+            }
+            status.perfdata.push(PerfData::new("archived_count", archived).uom("c"));
+            status.perfdata.push(PerfData::new("failed_count", failed).uom("c"));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,68 @@
+// Time since the last successful base backup. By default, that is the newest backup history file in `pg_wal`, which
+// `pg_basebackup` and other tools calling `pg_backup_stop()` leave when a backup completes; older ones are removed.
+// Backups taken elsewhere, e.g. of a standby, are recorded by the backup tool instead: `--backup-query` gives a query
+// returning the time of the last backup, e.g. from a catalog table the tool maintains.
+//
+// A base backup in progress, from `pg_stat_progress_basebackup` (PostgreSQL 13+), is reported in the long output.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const HISTORY: &str = "SELECT max(modification) FROM pg_ls_waldir() WHERE name ~ '^[0-9A-F]{24}\\.[0-9A-F]{8}\\.backup$'";
+
+const PROGRESS: &str = "SELECT phase, backup_streamed::float8, backup_total::float8 FROM pg_stat_progress_basebackup";
+
+struct BackupAge {
+    thresholds: Thresholds,
+    query: String,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(BackupAge {
+        thresholds: Thresholds::new(options, &["seconds"], Some("26h"), Some("50h"))?,
+        query: options.value_of("backup-query").unwrap_or(HISTORY).to_string(),
+    }))
+}
+
+impl Check for BackupAge {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let query = format!("SELECT extract(epoch FROM now() - finished)::float8 FROM ({}) AS backup(finished)", self.query);
+            let rows = session.query(&query, &[]).await?;
+            let age = match rows.first() {
+                Some(row) => column::<Option<f64>>(row, 0)?,
+                None => None,
+            };
+
+            let mut status = match age {
+                Some(age) => {
+                    let mut status = Status::new(self.thresholds.status(0, age), format!("Last base backup finished {}s ago", age.round()));
+                    status.perfdata.push(self.thresholds.perfdata(0, "age", age.round()).uom("s").min(Some(0.0)));
+                    status
+                }
+                None => Status::new(StatusType::CRITICAL, "No base backup found".to_string()),
+            };
+
+            let version: i32 = column(&session.query_one("SELECT current_setting('server_version_num')::int", &[]).await?, 0)?;
+            if version >= 130000 {
+                for row in &session.query(PROGRESS, &[]).await? {
+                    let phase: String = column(row, 0)?;
+                    let streamed: f64 = column(row, 1)?;
+                    let total = match column::<Option<f64>>(row, 2)? {
+                        Some(total) => format!(" of {}", format_bytes(total)),
+                        None => String::new(),
+                    };
+                    status.long_output.push(format!("Base backup in progress: {}, {}{} streamed", phase, format_bytes(streamed), total));
+                }
+            }
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,78 @@
+// Buffer cache hit ratio of every database, `blks_hit / (blks_hit + blks_read)` from `pg_stat_database`. The counters
+// are kept in the state directory, so the ratio covers the blocks read since the previous run. On the first run, it
+// covers everything since the statistics were reset. A database that read no blocks in between is not compared.
+//
+// Blocks read by PostgreSQL may still come from the operating system's page cache, so the ratio is a lower bound.
+
+use super::{databases, require_databases, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT datname::text, blks_hit::float8, blks_read::float8 FROM pg_stat_database \
+                     WHERE datname IS NOT NULL AND (cardinality($1::text[]) = 0 OR datname = ANY($1)) ORDER BY 1";
+
+struct CacheHitRatio {
+    databases: Vec<String>,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(CacheHitRatio {
+        databases: databases(options),
+        thresholds: Thresholds::new(options, &["percent"], Some("90:"), Some("80:"))?,
+    }))
+}
+
+impl Check for CacheHitRatio {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(QUERY, &[&self.databases]).await?;
+            let mut state = session.state("cache-hit-ratio")?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut names = vec![];
+            let mut first_run = false;
+            let mut lowest: Option<(String, f64)> = None;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let mut hit: f64 = column(row, 1)?;
+                let mut read: f64 = column(row, 2)?;
+                match (state.delta(&format!("{}.hit", name), hit), state.delta(&format!("{}.read", name), read)) {
+                    (Some(hit_delta), Some(read_delta)) => {
+                        hit = hit_delta;
+                        read = read_delta;
+                    }
+                    _ => first_run = true,
+                }
+                names.push(name.clone());
+                if hit + read == 0.0 {
+                    status.long_output.push(format!("{} read no blocks", name));
+                    continue;
+                }
+
+                let percent = hit * 100.0 / (hit + read);
+                status.t = status.t.worst(self.thresholds.status(0, percent));
+                status.perfdata.push(self.thresholds.perfdata(0, &name, (percent * 100.0).round() / 100.0).uom("%").min(Some(0.0)).max(Some(100.0)));
+                status.long_output.push(format!("{} {}% of {} blocks", name, (percent * 10.0).round() / 10.0, hit + read));
+                if lowest.as_ref().is_none_or(|lowest| percent < lowest.1) {
+                    lowest = Some((name, percent));
+                }
+            }
+            state.save()?;
+            require_databases(&self.databases, &names)?;
+
+            let since = if first_run { "since the statistics reset (first run)" } else { "since the previous run" };
+            status.description = match lowest {
+                Some((name, percent)) => format!("Lowest cache hit ratio {}% in {} {}", (percent * 10.0).round() / 10.0, name, since),
+                None => format!("No blocks read {}", since),
+            };
+            status.perfdata.insert(0, PerfData::new("databases", names.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,71 @@
+// Checkpoint behavior since the previous run: the percentage of checkpoints that were requested instead of timed, and
+// the percentage of the time spent writing and syncing checkpoints. Checkpoints are requested when `max_wal_size` is
+// reached before `checkpoint_timeout`, a high share of them means `max_wal_size` is too small for the write load.
+// The counters come from `pg_stat_checkpointer` (PostgreSQL 17+) or `pg_stat_bgwriter` and are kept in the state
+// directory. On the first run, the share covers everything since the statistics were reset and the times are unknown.
+//
+// Writes are spread over `checkpoint_completion_target` of the interval, so a high write time is not a problem by
+// itself.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const CHECKPOINTER: &str = "SELECT num_timed::float8, num_requested::float8, write_time, sync_time FROM pg_stat_checkpointer";
+
+const BGWRITER: &str = "SELECT checkpoints_timed::float8, checkpoints_req::float8, checkpoint_write_time, checkpoint_sync_time \
+                        FROM pg_stat_bgwriter";
+
+struct Checkpoints {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Checkpoints { thresholds: Thresholds::new(options, &["requested", "write", "sync"], Some("20,,"), Some("50,,"))? }))
+}
+
+impl Check for Checkpoints {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let version: i32 = column(&session.query_one("SELECT current_setting('server_version_num')::int", &[]).await?, 0)?;
+            let row = session.query_one(if version >= 170000 { CHECKPOINTER } else { BGWRITER }, &[]).await?;
+            let timed_count: f64 = column(&row, 0)?;
+            let requested_count: f64 = column(&row, 1)?;
+            let write_time: f64 = column(&row, 2)?;
+            let sync_time: f64 = column(&row, 3)?;
+
+            let mut state = session.state("checkpoints")?;
+            let deltas = (state.delta("timed", timed_count), state.delta("requested", requested_count));
+            // the times are in milliseconds, so a rate of 10 per second is 1% of the time
+            let times = (state.rate("write_time", write_time).map(|rate| rate / 10.0), state.rate("sync_time", sync_time).map(|rate| rate / 10.0));
+            state.save()?;
+
+            let (timed, requested, since) = match deltas {
+                (Some(timed), Some(requested)) => (timed, requested, "since the previous run"),
+                _ => (timed_count, requested_count, "since the statistics reset (first run)"),
+            };
+            let mut status = Status::new(StatusType::OK, format!("{} checkpoints {}", timed + requested, since));
+            if timed + requested > 0.0 {
+                let percent = requested * 100.0 / (timed + requested);
+                status.t = status.t.worst(self.thresholds.status(0, percent));
+                status.description += &format!(", {}% requested", percent.round());
+                status.perfdata.push(self.thresholds.perfdata(0, "requested", (percent * 10.0).round() / 10.0).uom("%").min(Some(0.0)).max(Some(100.0)));
+            }
+            if let (Some(write), Some(sync)) = times {
+                status.t = status.t.worst(self.thresholds.status(1, write)).worst(self.thresholds.status(2, sync));
+                status.description += &format!(", writing {}% and syncing {}% of the time", (write * 10.0).round() / 10.0, (sync * 10.0).round() / 10.0);
+                status.perfdata.push(self.thresholds.perfdata(1, "write_time", (write * 100.0).round() / 100.0).uom("%").min(Some(0.0)));
+                status.perfdata.push(self.thresholds.perfdata(2, "sync_time", (sync * 100.0).round() / 100.0).uom("%").min(Some(0.0)));
+            }
+            status.perfdata.push(PerfData::new("checkpoints_timed", timed_count).uom("c"));
+            status.perfdata.push(PerfData::new("checkpoints_requested", requested_count).uom("c"));
+            Ok(status)
+        })
+    }
+}
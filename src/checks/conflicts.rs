@@ -0,0 +1,85 @@
+// Queries on a hot standby cancelled because they conflicted with the replay of WAL, since the previous run. Every
+// database's counters of `pg_stat_database_conflicts` are kept in the state directory, the first run only records
+// them. The counters stay 0 on a primary.
+
+use super::{databases, require_databases, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+// the kinds of conflicts, by the columns of `pg_stat_database_conflicts`
+const KINDS: &[&str] = &["tablespace", "lock", "snapshot", "bufferpin", "deadlock"];
+
+const QUERY: &str = "SELECT datname::text, confl_tablespace::float8, confl_lock::float8, confl_snapshot::float8, \
+                            confl_bufferpin::float8, confl_deadlock::float8 \
+                     FROM pg_stat_database_conflicts \
+                     WHERE cardinality($1::text[]) = 0 OR datname = ANY($1) ORDER BY 1";
+
+struct Conflicts {
+    databases: Vec<String>,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Conflicts { databases: databases(options), thresholds: Thresholds::new(options, &["conflicts"], Some("0"), Some("10"))? }))
+}
+
+impl Check for Conflicts {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(QUERY, &[&self.databases]).await?;
+            let mut state = session.state("conflicts")?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut names = vec![];
+            let mut kinds: Option<Vec<f64>> = None;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let mut increases = vec![];
+                for (i, kind) in KINDS.iter().enumerate() {
+                    increases.push(state.delta(&format!("{}.{}", name, kind), column(row, i + 1)?));
+                }
+                let increases: Option<Vec<f64>> = increases.into_iter().collect();
+                if let Some(increases) = increases {
+                    let conflicts: f64 = increases.iter().sum();
+                    status.t = status.t.worst(self.thresholds.status(0, conflicts));
+                    status.perfdata.push(self.thresholds.perfdata(0, &name, conflicts).min(Some(0.0)));
+                    let kinds = kinds.get_or_insert_with(|| vec![0.0; KINDS.len()]);
+                    let mut details = vec![];
+                    for (i, increase) in increases.into_iter().enumerate() {
+                        kinds[i] += increase;
+                        if increase > 0.0 {
+                            details.push(format!("{} {}", KINDS[i], increase));
+                        }
+                    }
+                    if conflicts > 0.0 {
+                        status.long_output.push(format!("{} {} conflicts: {}", name, conflicts, details.join(", ")));
+                    }
+                }
+                names.push(name);
+            }
+            state.save()?;
+            require_databases(&self.databases, &names)?;
+
+            status.description = match kinds {
+                Some(kinds) => {
+                    let mut description = format!("{} queries cancelled by recovery conflicts in {} databases since the previous run", kinds.iter().sum::<f64>(), names.len());
+                    let details: Vec<String> = kinds.iter().enumerate().filter(|&(_, &n)| n > 0.0).map(|(i, n)| format!("{} {}", KINDS[i], n)).collect();
+                    if !details.is_empty() {
+                        description += &format!(" ({})", details.join(", "));
+                    }
+                    for (kind, n) in KINDS.iter().zip(kinds) {
+                        status.perfdata.push(PerfData::new(kind, n).min(Some(0.0)));
+                    }
+                    description
+                }
+                None => "Recovery conflict counters recorded (first run)".to_string(),
+            };
+            Ok(status)
+        })
+    }
+}
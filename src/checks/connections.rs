@@ -0,0 +1,44 @@
+// Client connections compared to the slots available to ordinary users, which are `max_connections` without the
+// `superuser_reserved_connections`. Thresholds are percentages of the available slots.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::Status;
+
+const QUERY: &str = "SELECT (SELECT count(*) FROM pg_stat_activity WHERE backend_type = 'client backend'), \
+                            current_setting('max_connections')::int8, \
+                            current_setting('superuser_reserved_connections')::int8";
+
+struct Connections {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let thresholds = Thresholds::new(options, &["percent"], Some("80"), Some("90"))?;
+    Ok(Box::new(Connections { thresholds }))
+}
+
+impl Check for Connections {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let row = session.query_one(QUERY, &[]).await?;
+            let used: i64 = column(&row, 0)?;
+            let max: i64 = column(&row, 1)?;
+            let reserved: i64 = column(&row, 2)?;
+            let available = std::cmp::max(max - reserved, 1);
+            let percent = (used as f64 * 1000.0 / available as f64).round() / 10.0;
+
+            let mut status = Status::new(self.thresholds.status(0, percent),
+                format!("{} of {} connections used ({}%), {} reserved for superusers", used, available, percent, reserved));
+            status.perfdata.push(PerfData::new("connections", used as f64).min(Some(0.0)).max(Some(max as f64)));
+            status.perfdata.push(self.thresholds.perfdata(0, "used", percent).uom("%").min(Some(0.0)).max(Some(100.0)));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,103 @@
+// The same query on the server and on the one of `--compare-with`, e.g. a logical replica, with the results compared
+// cell by cell. Rows are compared in order, so the query needs an ORDER BY. Non-numeric cells identify a row and
+// need to be equal; numbers, and timestamps in seconds, may differ within the thresholds, which every difference is
+// checked against: by default any difference is CRITICAL, `--relative` takes them in percent of the server's values.
+
+use super::{Check, Run, Thresholds};
+use crate::connection::Connection;
+use crate::encoding;
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::value::Value;
+use tokio_postgres::Row;
+
+struct Consistency {
+    query: String,
+    other: Connection,
+    relative: bool,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let query = options.value_of("compare-query").ok_or("--check consistency needs --compare-query")?;
+    let other = options.value_of("compare-with").ok_or("--check consistency needs --compare-with")?;
+    // --host would replace the hosts of both connection strings
+    if options.is_present("host") {
+        return Err("--check consistency needs the hosts in the connection strings, not --host".to_string());
+    }
+    Ok(Box::new(Consistency {
+        query: query.to_string(),
+        other: Connection::new(&options.with("db-connection-string", other)?)?,
+        relative: options.is_present("relative"),
+        thresholds: Thresholds::new(options, &["difference"], None, Some("0"))?,
+    }))
+}
+
+// The cells of every row, decoded in the encoding of the session they are from
+fn cells(rows: &[Row]) -> Result<Vec<Vec<Value>>, Status> {
+    rows.iter().map(|row| (0..row.len()).map(|i| column::<Value>(row, i)).collect()).collect()
+}
+
+// The number a cell is compared by, timestamps in seconds
+fn number(value: &Value) -> Option<f64> {
+    match *value {
+        Value::Timestamp(micros) | Value::TimestampTz(micros) => Some(micros as f64 / 1_000_000f64),
+        ref value => value.as_f64(),
+    }
+}
+
+impl Check for Consistency {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(&self.query, &[]).await?;
+            let names: Vec<String> = rows.first().map(|row| row.columns().iter().map(|c| c.name().to_string()).collect()).unwrap_or_default();
+            let mine = cells(&rows)?;
+            let (mut other, _) = self.other.session().await
+                .map_err(|status| Status::new(status.t, format!("--compare-with: {}", status.description)))?;
+            let theirs = encoding::scope(other.encoding(), async {
+                cells(&other.query(&self.query, &[]).await?)
+            }).await?;
+            let (host, other_host) = (session.host(), other.host());
+
+            if mine.len() != theirs.len() {
+                return Ok(Status::new(StatusType::CRITICAL,
+                    format!("{} rows on {}, {} on {}", mine.len(), host, theirs.len(), other_host)));
+            }
+            let mut status = Status::new(StatusType::OK, String::new());
+            // the largest difference and where it is
+            let mut largest: Option<(f64, usize, usize)> = None;
+            for (i, (a, b)) in mine.iter().zip(&theirs).enumerate() {
+                if a.len() != b.len() {
+                    return Err(Status::new(StatusType::UNKNOWN, format!("The query returns {} columns on {}, {} on {}", a.len(), host, b.len(), other_host)));
+                }
+                for (j, (x, y)) in a.iter().zip(b).enumerate() {
+                    let difference = match (number(x), number(y)) {
+                        (Some(x), Some(y)) if self.relative && x != 0.0 => (y - x).abs() / x.abs() * 100.0,
+                        (Some(_), Some(y)) if self.relative => if y == 0.0 { 0.0 } else { 100.0 },
+                        (Some(x), Some(y)) => (y - x).abs(),
+                        _ if x.to_string() == y.to_string() => continue,
+                        _ => return Ok(Status::new(StatusType::CRITICAL,
+                            format!("Row {} differs in {}: '{}' on {}, '{}' on {}", i + 1, names[j], x, host, y, other_host))),
+                    };
+                    status.t = status.t.worst(self.thresholds.status(0, difference));
+                    if largest.is_none_or(|(largest, _, _)| difference > largest) {
+                        largest = Some((difference, i, j));
+                    }
+                }
+            }
+            let uom = if self.relative { "%" } else { "" };
+            status.description = match largest {
+                Some((difference, i, j)) if difference > 0.0 => format!("Largest difference {}{} in row {}, {}: {} on {}, {} on {}",
+                    difference, uom, i + 1, names[j], mine[i][j], host, theirs[i][j], other_host),
+                _ => format!("Results of {} and {} match ({} rows)", host, other_host, mine.len()),
+            };
+            status.perfdata.push(self.thresholds.perfdata(0, "difference", largest.map_or(0.0, |(difference, _, _)| difference)).uom(uom).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
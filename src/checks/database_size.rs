@@ -0,0 +1,46 @@
+// Size of every database, or of the databases given by `--database`, as reported by `pg_database_size()`. Databases
+// not accepting connections, like template0, are skipped.
+
+use super::{databases, require_databases, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const QUERY: &str = "SELECT datname::text, pg_database_size(oid)::float8 FROM pg_database \
+                     WHERE datallowconn AND (cardinality($1::text[]) = 0 OR datname = ANY($1)) ORDER BY 1";
+
+struct DatabaseSize {
+    databases: Vec<String>,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let thresholds = Thresholds::new(options, &["bytes"], None, None)?;
+    Ok(Box::new(DatabaseSize { databases: databases(options), thresholds }))
+}
+
+impl Check for DatabaseSize {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut names = vec![];
+            let mut sizes = vec![];
+            for row in &session.query(QUERY, &[&self.databases]).await? {
+                let name: String = column(row, 0)?;
+                let size: f64 = column(row, 1)?;
+                status.t = status.t.worst(self.thresholds.status(0, size));
+                status.perfdata.push(self.thresholds.perfdata(0, &name, size).uom("B").min(Some(0.0)));
+                sizes.push(format!("{} {}", name, format_bytes(size)));
+                names.push(name);
+            }
+            require_databases(&self.databases, &names)?;
+            status.description = sizes.join(", ");
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,58 @@
+// Deadlocks detected in every database since the previous run, by the `deadlocks` counter of `pg_stat_database` kept in
+// the state directory. The first run only records the counters.
+
+use super::{databases, require_databases, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT datname::text, deadlocks::float8 FROM pg_stat_database \
+                     WHERE datname IS NOT NULL AND (cardinality($1::text[]) = 0 OR datname = ANY($1)) ORDER BY 1";
+
+struct Deadlocks {
+    databases: Vec<String>,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Deadlocks { databases: databases(options), thresholds: Thresholds::new(options, &["deadlocks"], Some("0"), Some("5"))? }))
+}
+
+impl Check for Deadlocks {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(QUERY, &[&self.databases]).await?;
+            let mut state = session.state("deadlocks")?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut names = vec![];
+            let mut total: Option<f64> = None;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let deadlocks: f64 = column(row, 1)?;
+                if let Some(increase) = state.delta(&name, deadlocks) {
+                    status.t = status.t.worst(self.thresholds.status(0, increase));
+                    status.perfdata.push(self.thresholds.perfdata(0, &name, increase).min(Some(0.0)));
+                    if increase > 0.0 {
+                        status.long_output.push(format!("{} {} deadlocks", name, increase));
+                    }
+                    total = Some(total.unwrap_or(0.0) + increase);
+                }
+                status.perfdata.push(PerfData::new(&format!("{}_total", name), deadlocks).uom("c"));
+                names.push(name);
+            }
+            state.save()?;
+            require_databases(&self.databases, &names)?;
+
+            status.description = match total {
+                Some(total) => format!("{} deadlocks in {} databases since the previous run", total, names.len()),
+                None => "Deadlock counters recorded (first run)".to_string(),
+            };
+            Ok(status)
+        })
+    }
+}
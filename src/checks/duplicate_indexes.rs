@@ -0,0 +1,76 @@
+// Indexes of the current database with the same definition as another index of the table: the same access method,
+// columns or expressions, operator classes, collations and predicate. Every one but the index that is kept, a primary
+// key or unique one if there is, is wasted space that every write still updates. The wasted bytes are WARNING from
+// any by default, the duplicates are listed as long output.
+
+use super::{patterns, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::Status;
+use crate::units::format_bytes;
+
+const QUERY: &str = "
+SELECT n.nspname || '.' || t.relname,
+       array_agg(n.nspname || '.' || c.relname ORDER BY i.indisprimary DESC, i.indisunique DESC, c.relname),
+       array_agg(pg_relation_size(c.oid)::float8 ORDER BY i.indisprimary DESC, i.indisunique DESC, c.relname)
+FROM pg_index i
+  JOIN pg_class c ON c.oid = i.indexrelid
+  JOIN pg_class t ON t.oid = i.indrelid
+  JOIN pg_namespace n ON n.oid = t.relnamespace
+WHERE n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname !~ '^pg_toast'
+GROUP BY t.oid, n.nspname, t.relname, c.relam, i.indkey::text, i.indclass::text, i.indcollation::text,
+         coalesce(pg_get_expr(i.indexprs, i.indrelid), ''), coalesce(pg_get_expr(i.indpred, i.indrelid), '')
+HAVING count(*) > 1
+ORDER BY 1";
+
+struct DuplicateIndexes {
+    thresholds: Thresholds,
+    exclude: Option<RegexSet>,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(DuplicateIndexes {
+        thresholds: Thresholds::new(options, &["bytes"], Some("0"), None)?,
+        exclude: patterns(options, "exclude-table")?,
+        top: top(options)?,
+    }))
+}
+
+impl Check for DuplicateIndexes {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut duplicates = vec![];
+            let mut wasted = 0.0;
+            for row in &session.query(QUERY, &[]).await? {
+                let table: String = column(row, 0)?;
+                if self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&table)) {
+                    continue;
+                }
+                let indexes: Vec<String> = column(row, 1)?;
+                let sizes: Vec<f64> = column(row, 2)?;
+                // the first index is kept
+                for (index, size) in indexes.iter().zip(&sizes).skip(1) {
+                    wasted += size;
+                    duplicates.push((*size, format!("{} duplicates {} on {}, {}", index, indexes[0], table, format_bytes(*size))));
+                }
+            }
+            let mut status = Status::new(self.thresholds.status(0, wasted), match duplicates.len() {
+                0 => "No duplicate indexes".to_string(),
+                n => format!("{} duplicate indexes wasting {}", n, format_bytes(wasted)),
+            });
+            status.perfdata.push(self.thresholds.perfdata(0, "wasted", wasted).uom("B").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("duplicates", duplicates.len() as f64).min(Some(0.0)));
+            // the largest first
+            duplicates.sort_by(|a, b| b.0.total_cmp(&a.0));
+            status.long_output = duplicates.into_iter().take(self.top).map(|(_, line)| line).collect();
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,125 @@
+// Extensions installed in the current database. `--require` lists the extensions expected there, optionally with a
+// minimum or exact version, e.g. `pg_stat_statements>=1.9,postgis`. A required extension that is missing or too old
+// is CRITICAL. Versions are compared by their dot-separated parts, numerically where both are numbers.
+
+use super::{Check, Run};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use std::cmp::Ordering;
+
+const QUERY: &str = "SELECT a.name::text, a.installed_version, a.default_version FROM pg_available_extensions a ORDER BY 1";
+
+struct Requirement {
+    name: String,
+    // the comparison operator and the version, `None` for any version
+    version: Option<(&'static str, String)>,
+}
+
+struct Extensions {
+    required: Vec<Requirement>,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let mut required = vec![];
+    for requirement in options.value_of("require").unwrap_or("").split(',').map(|r| r.trim()).filter(|r| !r.is_empty()) {
+        let version = ["<=", ">=", "=", "<", ">"].iter().find_map(|&op| requirement.find(op).map(|idx| (op, idx)));
+        required.push(match version {
+            Some((op, idx)) if idx > 0 && requirement.len() > idx + op.len() => Requirement {
+                name: requirement[..idx].trim().to_string(),
+                version: Some((op, requirement[idx + op.len()..].trim().to_string())),
+            },
+            Some(_) => return Err(format!("Invalid --require '{}', expected NAME[>=VERSION]", requirement)),
+            None => Requirement { name: requirement.to_string(), version: None },
+        });
+    }
+    Ok(Box::new(Extensions { required }))
+}
+
+// Compares versions like 1.10 and 1.9 part by part, numerically if both parts are numbers
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => {
+                let ordering = match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+        }
+    }
+}
+
+impl Check for Extensions {
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut installed = vec![];
+            let mut available = vec![];
+            for row in &session.query(QUERY, &[]).await? {
+                let name: String = column(row, 0)?;
+                let default_version: Option<String> = column(row, 2)?;
+                match column::<Option<String>>(row, 1)? {
+                    Some(version) => installed.push((name, version, default_version)),
+                    None => available.push(name),
+                }
+            }
+
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut problems = vec![];
+            for requirement in &self.required {
+                let version = match installed.iter().find(|extension| extension.0 == requirement.name) {
+                    Some(extension) => &extension.1,
+                    None if available.contains(&requirement.name) => {
+                        problems.push(format!("{} is not installed", requirement.name));
+                        continue;
+                    }
+                    None => {
+                        problems.push(format!("{} is not available", requirement.name));
+                        continue;
+                    }
+                };
+                if let Some((op, ref required)) = requirement.version {
+                    let ordering = compare_versions(version, required);
+                    let met = match op {
+                        "<=" => ordering != Ordering::Greater,
+                        ">=" => ordering != Ordering::Less,
+                        "<" => ordering == Ordering::Less,
+                        ">" => ordering == Ordering::Greater,
+                        _ => ordering == Ordering::Equal,
+                    };
+                    if !met {
+                        problems.push(format!("{} {} does not match {}{}", requirement.name, version, op, required));
+                    }
+                }
+            }
+            if !problems.is_empty() {
+                status.t = StatusType::CRITICAL;
+            }
+
+            for (name, version, default_version) in &installed {
+                status.long_output.push(match default_version {
+                    Some(default_version) if default_version != version =>
+                        format!("{} {} (version {} available)", name, version, default_version),
+                    _ => format!("{} {}", name, version),
+                });
+            }
+            let mut parts = vec![format!("{} extensions installed", installed.len())];
+            if !self.required.is_empty() {
+                parts.push(format!("{} of {} requirements met", self.required.len() - problems.len(), self.required.len()));
+            }
+            parts.extend(problems);
+            status.description = parts.join(", ");
+            status.perfdata.push(PerfData::new("installed", installed.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
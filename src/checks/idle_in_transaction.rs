@@ -0,0 +1,71 @@
+// Sessions idle in a transaction by the time since their last statement. They hold their snapshot and locks, and so
+// block vacuum and DDL until they commit, roll back or are terminated by `idle_in_transaction_session_timeout`.
+
+use super::{patterns, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT pid, coalesce(usename::text, ''), application_name, \
+                            extract(epoch FROM now() - state_change)::float8 \
+                     FROM pg_stat_activity \
+                     WHERE state IN ('idle in transaction', 'idle in transaction (aborted)') \
+                     ORDER BY 4 DESC";
+
+struct IdleInTransaction {
+    thresholds: Thresholds,
+    exclude_user: Option<RegexSet>,
+    exclude_application: Option<RegexSet>,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(IdleInTransaction {
+        thresholds: Thresholds::new(options, &["seconds"], Some("1m"), Some("10m"))?,
+        exclude_user: patterns(options, "exclude-user")?,
+        exclude_application: patterns(options, "exclude-application")?,
+    }))
+}
+
+impl Check for IdleInTransaction {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut idle = 0;
+            let mut oldest: Option<(i32, String, f64)> = None;
+            for row in &session.query(QUERY, &[]).await? {
+                let user: String = column(row, 1)?;
+                let application: String = column(row, 2)?;
+                if self.exclude_user.as_ref().is_some_and(|exclude| exclude.is_match(&user))
+                    || self.exclude_application.as_ref().is_some_and(|exclude| exclude.is_match(&application)) {
+                    continue;
+                }
+                let seconds: f64 = column(row, 3)?;
+                let session_status = self.thresholds.status(0, seconds);
+                if session_status != StatusType::OK {
+                    status.t = status.t.worst(session_status);
+                    idle += 1;
+                }
+                // rows are ordered by age, so the first one is the oldest
+                if oldest.is_none() {
+                    oldest = Some((column(row, 0)?, user, seconds.round()));
+                }
+            }
+
+            status.description = match (idle, oldest.as_ref()) {
+                (_, None) => "No sessions idle in transaction".to_string(),
+                (0, Some(&(pid, ref user, seconds))) => format!("No sessions idle in transaction too long, oldest {}s (pid {}, user {})", seconds, pid, user),
+                (n, Some(&(pid, ref user, seconds))) => format!("{} sessions idle in transaction, oldest {}s (pid {}, user {})", n, seconds, pid, user),
+            };
+            let oldest = oldest.map(|oldest| oldest.2).unwrap_or(0.0);
+            status.perfdata.push(self.thresholds.perfdata(0, "oldest", oldest).uom("s").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("idle_in_transaction", idle as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
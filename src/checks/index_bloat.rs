@@ -0,0 +1,119 @@
+// B-tree index bloat, estimated from the statistics of the indexed columns like the well-known query of
+// ioguix/pgsql-bloat-estimation: the expected number of leaf pages follows from the row count, the average key width
+// and the index' fillfactor, everything above it is considered wasted. Indexes on columns of type `name` cannot be
+// estimated and are skipped.
+
+use super::{patterns, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const QUERY: &str = "
+SELECT nspname || '.' || idxname, nspname || '.' || tblname,
+       (CASE WHEN relpages > est_pages_ff THEN bs * (relpages - est_pages_ff) ELSE 0 END)::float8,
+       (CASE WHEN relpages > est_pages_ff THEN 100 * (relpages - est_pages_ff) / relpages ELSE 0 END)::float8
+FROM (
+  SELECT coalesce(1 + ceil(reltuples / floor((bs - pageopqdata - pagehdr) * fillfactor
+                                             / (100 * (4 + nulldatahdrwidth)::float))), 0) AS est_pages_ff,
+         bs, nspname, tblname, idxname, relpages, is_na
+  FROM (
+    SELECT bs, nspname, tblname, idxname, reltuples, relpages, fillfactor, pagehdr, pageopqdata, is_na,
+           (index_tuple_hdr_bm
+            + maxalign - CASE WHEN index_tuple_hdr_bm % maxalign = 0 THEN maxalign ELSE index_tuple_hdr_bm % maxalign END
+            + nulldatawidth + maxalign - CASE WHEN nulldatawidth = 0 THEN 0
+                                              WHEN nulldatawidth::integer % maxalign = 0 THEN maxalign
+                                              ELSE nulldatawidth::integer % maxalign END
+           )::numeric AS nulldatahdrwidth
+    FROM (
+      SELECT n.nspname, i.tblname, i.idxname, i.reltuples, i.relpages, i.fillfactor,
+             current_setting('block_size')::numeric AS bs,
+             CASE WHEN version() ~ 'mingw32|64-bit|x86_64|ppc64|ia64|amd64' THEN 8 ELSE 4 END AS maxalign,
+             24 AS pagehdr, 16 AS pageopqdata,
+             CASE WHEN max(coalesce(s.null_frac, 0)) = 0 THEN 8 ELSE 8 + ((32 + 8 - 1) / 8) END AS index_tuple_hdr_bm,
+             sum((1 - coalesce(s.null_frac, 0)) * coalesce(s.avg_width, 1024)) AS nulldatawidth,
+             max(CASE WHEN i.atttypid = 'pg_catalog.name'::regtype THEN 1 ELSE 0 END) > 0 AS is_na
+      FROM (
+        SELECT ct.relname AS tblname, ct.relnamespace, ic.idxname, ic.reltuples, ic.relpages, ic.fillfactor,
+               coalesce(a1.attname, a2.attname) AS attname, coalesce(a1.atttypid, a2.atttypid) AS atttypid,
+               CASE WHEN a1.attnum IS NULL THEN ic.idxname ELSE ct.relname END AS attrelname
+        FROM (
+          SELECT idxname, reltuples, relpages, tbloid, idxoid, fillfactor, indkey,
+                 generate_series(1, indnatts) AS attpos
+          FROM (
+            SELECT ci.relname AS idxname, ci.reltuples, ci.relpages, i.indrelid AS tbloid, i.indexrelid AS idxoid,
+                   coalesce(substring(array_to_string(ci.reloptions, ' ') FROM 'fillfactor=([0-9]+)')::smallint, 90)
+                     AS fillfactor,
+                   i.indnatts, string_to_array(textin(int2vectorout(i.indkey)), ' ')::int[] AS indkey
+            FROM pg_index i
+              JOIN pg_class ci ON ci.oid = i.indexrelid
+            WHERE ci.relam = (SELECT oid FROM pg_am WHERE amname = 'btree') AND ci.relpages > 0
+          ) AS idx_data
+        ) AS ic
+          JOIN pg_class ct ON ct.oid = ic.tbloid
+          LEFT JOIN pg_attribute a1 ON ic.indkey[ic.attpos] <> 0 AND a1.attrelid = ic.tbloid
+                                    AND a1.attnum = ic.indkey[ic.attpos]
+          LEFT JOIN pg_attribute a2 ON ic.indkey[ic.attpos] = 0 AND a2.attrelid = ic.idxoid AND a2.attnum = ic.attpos
+      ) i
+        JOIN pg_namespace n ON n.oid = i.relnamespace
+        JOIN pg_stats s ON s.schemaname = n.nspname AND s.tablename = i.attrelname AND s.attname = i.attname
+      WHERE n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname !~ '^pg_toast'
+      GROUP BY 1, 2, 3, 4, 5, 6
+    ) AS rows_data_stats
+  ) AS rows_hdr_pdg_stats
+) AS relation_stats
+WHERE NOT is_na";
+
+struct IndexBloat {
+    thresholds: Thresholds,
+    exclude: Option<RegexSet>,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(IndexBloat {
+        thresholds: Thresholds::new(options, &["percent"], Some("50"), Some("80"))?,
+        exclude: patterns(options, "exclude-table")?,
+        top: top(options)?,
+    }))
+}
+
+impl Check for IndexBloat {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut total = 0.0;
+            let mut bloated = vec![];
+            for row in &session.query(QUERY, &[]).await? {
+                let table: String = column(row, 1)?;
+                if self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&table)) {
+                    continue;
+                }
+                let bytes: f64 = column(row, 2)?;
+                let percent: f64 = column(row, 3)?;
+                total += bytes;
+                let index_status = self.thresholds.status(0, percent);
+                if index_status != StatusType::OK {
+                    status.t = status.t.worst(index_status);
+                    bloated.push((column::<String>(row, 0)?, bytes, percent));
+                }
+            }
+
+            status.description = format!("{} wasted in total, {} bloated indexes", format_bytes(total), bloated.len());
+            // the worst offenders by wasted bytes go to the long output
+            bloated.sort_by(|a, b| b.1.total_cmp(&a.1));
+            status.long_output = bloated.iter().take(self.top)
+                .map(|&(ref name, bytes, percent)| format!("{} {} ({}%)", name, format_bytes(bytes), percent.round()))
+                .collect();
+            status.perfdata.push(PerfData::new("wasted", total).uom("B").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("bloated_indexes", bloated.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,56 @@
+// Foreign keys and check constraints of the current database that were added `NOT VALID` and never validated. New
+// rows are checked, but existing ones may violate them, which the planner and readers of the schema cannot tell. A
+// migration that adds a constraint this way to avoid a long lock is meant to run `ALTER TABLE ... VALIDATE
+// CONSTRAINT` afterwards, so any left is WARNING by default. Every one is listed as long output.
+
+use super::{patterns, Check, Run, Thresholds};
+use crate::options::Options;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::Status;
+
+const QUERY: &str = "SELECT n.nspname || '.' || t.relname, c.conname::text, c.contype = 'f' \
+                     FROM pg_constraint c JOIN pg_class t ON t.oid = c.conrelid JOIN pg_namespace n ON n.oid = t.relnamespace \
+                     WHERE NOT c.convalidated AND c.contype IN ('f', 'c') ORDER BY 1, 2";
+
+struct InvalidConstraints {
+    thresholds: Thresholds,
+    exclude: Option<RegexSet>,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(InvalidConstraints {
+        thresholds: Thresholds::new(options, &["constraints"], Some("0"), None)?,
+        exclude: patterns(options, "exclude-table")?,
+    }))
+}
+
+impl Check for InvalidConstraints {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut invalid = vec![];
+            for row in &session.query(QUERY, &[]).await? {
+                let table: String = column(row, 0)?;
+                if self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&table)) {
+                    continue;
+                }
+                let name: String = column(row, 1)?;
+                let foreign_key: bool = column(row, 2)?;
+                invalid.push(format!("{} {} on {}", if foreign_key { "Foreign key" } else { "Check constraint" }, name, table));
+            }
+            let count = invalid.len() as f64;
+            let mut status = Status::new(self.thresholds.status(0, count), match invalid.len() {
+                0 => "No NOT VALID constraints".to_string(),
+                1 => "1 NOT VALID constraint".to_string(),
+                n => format!("{} NOT VALID constraints", n),
+            });
+            status.long_output = invalid;
+            status.perfdata.push(self.thresholds.perfdata(0, "not_valid", count).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
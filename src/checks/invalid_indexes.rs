@@ -0,0 +1,55 @@
+// Invalid indexes of the current database, `pg_index.indisvalid` false. They are left over by a CREATE INDEX
+// CONCURRENTLY or REINDEX CONCURRENTLY that failed, are not used by queries but still updated by every write, and a
+// unique one still enforces uniqueness. Any is CRITICAL by default, every one is listed as long output to be dropped
+// or rebuilt.
+
+use super::{patterns, Check, Run, Thresholds};
+use crate::options::Options;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::Status;
+
+const QUERY: &str = "SELECT n.nspname || '.' || t.relname, n.nspname || '.' || c.relname \
+                     FROM pg_index i JOIN pg_class c ON c.oid = i.indexrelid JOIN pg_class t ON t.oid = i.indrelid \
+                       JOIN pg_namespace n ON n.oid = c.relnamespace \
+                     WHERE NOT i.indisvalid ORDER BY 1, 2";
+
+struct InvalidIndexes {
+    thresholds: Thresholds,
+    exclude: Option<RegexSet>,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(InvalidIndexes {
+        thresholds: Thresholds::new(options, &["indexes"], None, Some("0"))?,
+        exclude: patterns(options, "exclude-table")?,
+    }))
+}
+
+impl Check for InvalidIndexes {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut invalid = vec![];
+            for row in &session.query(QUERY, &[]).await? {
+                let table: String = column(row, 0)?;
+                let index: String = column(row, 1)?;
+                if !self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&table)) {
+                    invalid.push(format!("{} on {}", index, table));
+                }
+            }
+            let count = invalid.len() as f64;
+            let mut status = Status::new(self.thresholds.status(0, count), match invalid.len() {
+                0 => "No invalid indexes".to_string(),
+                1 => "1 invalid index".to_string(),
+                n => format!("{} invalid indexes", n),
+            });
+            status.long_output = invalid;
+            status.perfdata.push(self.thresholds.perfdata(0, "invalid", count).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,64 @@
+// Sessions waiting for a lock held by another session, by their number and the longest wait. The wait is measured
+// from the start of the waiting query. Blocked sessions and the sessions blocking them are listed in the long output.
+
+use super::{one_line, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::Status;
+
+const QUERY: &str = "SELECT a.pid, coalesce(a.usename::text, ''), extract(epoch FROM now() - a.query_start)::float8, \
+                            a.query, b.pid, coalesce(b.query, '') \
+                     FROM pg_stat_activity a \
+                       LEFT JOIN pg_stat_activity b ON b.pid = (pg_blocking_pids(a.pid))[1] \
+                     WHERE a.pid IN (SELECT pid FROM pg_locks WHERE NOT granted) \
+                     ORDER BY 3 DESC";
+
+struct Locks {
+    thresholds: Thresholds,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Locks {
+        thresholds: Thresholds::new(options, &["sessions", "seconds"], Some("5,30s"), Some("20,5m"))?,
+        top: top(options)?,
+    }))
+}
+
+impl Check for Locks {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(QUERY, &[]).await?;
+            let blocked = rows.len() as f64;
+            // rows are ordered by the wait, so the first one waits the longest
+            let longest: f64 = match rows.first() {
+                Some(row) => column::<Option<f64>>(row, 2)?.unwrap_or(0.0),
+                None => 0.0,
+            };
+            let t = self.thresholds.status(0, blocked).worst(self.thresholds.status(1, longest));
+
+            let mut status = Status::new(t, match rows.len() {
+                0 => "No sessions waiting for locks".to_string(),
+                n => format!("{} sessions waiting for locks, longest {}s", n, longest.round()),
+            });
+            for row in rows.iter().take(self.top) {
+                let pid: i32 = column(row, 0)?;
+                let user: String = column(row, 1)?;
+                let seconds = column::<Option<f64>>(row, 2)?.unwrap_or(0.0);
+                let query: String = column(row, 3)?;
+                let blocker = match column::<Option<i32>>(row, 4)? {
+                    Some(blocker) => format!(", blocked by pid {}: {}", blocker, one_line(&column::<String>(row, 5)?, 100)),
+                    None => String::new(),
+                };
+                status.long_output.push(format!("pid {} user {} waiting {}s: {}{}", pid, user, seconds.round(), one_line(&query, 100), blocker));
+            }
+            status.perfdata.push(self.thresholds.perfdata(0, "blocked", blocked).min(Some(0.0)));
+            status.perfdata.push(self.thresholds.perfdata(1, "longest", (longest * 1000.0).round() / 1000.0).uom("s").min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
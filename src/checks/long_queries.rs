@@ -0,0 +1,88 @@
+// Active queries of client backends by their duration. Queries of users or applications matching `--exclude-user` or
+// `--exclude-application` are skipped, `--exclude-maintenance` also skips pg_dump and VACUUM, ANALYZE and REINDEX.
+// Background workers like autovacuum are never checked.
+
+use super::{one_line, patterns, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::{Regex, RegexSet};
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT pid, coalesce(usename::text, ''), application_name, \
+                            extract(epoch FROM now() - query_start)::float8, query \
+                     FROM pg_stat_activity \
+                     WHERE state = 'active' AND backend_type = 'client backend' AND pid <> pg_backend_pid() \
+                     ORDER BY 4 DESC";
+
+struct LongQueries {
+    thresholds: Thresholds,
+    exclude_user: Option<RegexSet>,
+    exclude_application: Option<RegexSet>,
+    maintenance: Option<Regex>,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let maintenance = if options.is_present("exclude-maintenance") {
+        Some(Regex::new(r"(?i)^\s*(vacuum|analyze|reindex)\b").unwrap())
+    } else {
+        None
+    };
+    Ok(Box::new(LongQueries {
+        thresholds: Thresholds::new(options, &["seconds"], Some("1m"), Some("5m"))?,
+        exclude_user: patterns(options, "exclude-user")?,
+        exclude_application: patterns(options, "exclude-application")?,
+        maintenance,
+        top: top(options)?,
+    }))
+}
+
+impl LongQueries {
+    fn excluded(&self, user: &str, application: &str, query: &str) -> bool {
+        self.exclude_user.as_ref().is_some_and(|exclude| exclude.is_match(user))
+            || self.exclude_application.as_ref().is_some_and(|exclude| exclude.is_match(application))
+            || self.maintenance.as_ref().is_some_and(|maintenance| application == "pg_dump" || maintenance.is_match(query))
+    }
+}
+
+impl Check for LongQueries {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut long = vec![];
+            let mut longest = 0f64;
+            for row in &session.query(QUERY, &[]).await? {
+                let user: String = column(row, 1)?;
+                let application: String = column(row, 2)?;
+                let query: String = column(row, 4)?;
+                if self.excluded(&user, &application, &query) {
+                    continue;
+                }
+                let seconds: f64 = column(row, 3)?;
+                longest = longest.max(seconds);
+                let query_status = self.thresholds.status(0, seconds);
+                if query_status != StatusType::OK {
+                    status.t = status.t.worst(query_status);
+                    long.push((column::<i32>(row, 0)?, user, seconds.round(), query));
+                }
+            }
+
+            // rows are ordered by duration, so the first one is the longest
+            status.description = match long.first() {
+                None => "No long running queries".to_string(),
+                Some(&(pid, ref user, seconds, _)) => format!("{} long running queries, longest {}s (pid {}, user {})", long.len(), seconds, pid, user),
+            };
+            status.long_output = long.iter().take(self.top)
+                .map(|&(pid, ref user, seconds, ref query)| format!("pid {} user {} running {}s: {}", pid, user, seconds, one_line(query, 100)))
+                .collect();
+            status.perfdata.push(self.thresholds.perfdata(0, "longest", (longest * 1000.0).round() / 1000.0).uom("s").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("long_queries", long.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
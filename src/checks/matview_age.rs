@@ -0,0 +1,103 @@
+// Time since the materialized views of the current database, or those given by `--table`, were last refreshed.
+// PostgreSQL does not record refreshes, so the time is the latest `refreshed_at` of the view in the table of
+// `--refresh-log` that the refresh jobs write to, or else the modification time of the view's data file, which
+// `pg_stat_file()` only tells superusers and roles it is granted to. A view never refreshed or not populated has no
+// age and alerts on any threshold. With `--table`, `--warn` and `--critical` may have a range for each view.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_age;
+
+// The columns the age is selected with are put in for `{age}`
+const QUERY: &str = "SELECT c.oid::regclass::text, CASE WHEN c.relispopulated THEN {age} END, \
+                            coalesce(array_position($1::text[]::regclass[], c.oid::regclass), 1) \
+                     FROM pg_class c \
+                     WHERE c.relkind = 'm' AND (cardinality($1::text[]) = 0 OR c.oid = ANY($1::text[]::regclass[])) ORDER BY 1";
+
+const FILE_AGE: &str = "extract(epoch FROM now() - (pg_stat_file(pg_relation_filepath(c.oid), true)).modification)::float8";
+
+struct MatviewAge {
+    views: Vec<String>,
+    refresh_log: Option<String>,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let views: Vec<String> = options.value_of("table").unwrap_or("").split(',').map(|view| view.trim().to_string())
+        .filter(|view| !view.is_empty()).collect();
+    // a single range applies to every view
+    let metrics: Vec<&str> = if views.is_empty() { vec!["seconds"] } else { views.iter().map(|view| view.as_str()).collect() };
+    let each = |range: Option<&str>| range.map(|range| match range.contains(',') {
+        true => range.to_string(),
+        false => vec![range; metrics.len()].join(","),
+    });
+    let thresholds = Thresholds::parse(options, &metrics, each(options.value_of("warn")).as_deref(), each(options.value_of("critical")).as_deref())?;
+    Ok(Box::new(MatviewAge { views, refresh_log: options.value_of("refresh-log").map(|log| log.to_string()), thresholds }))
+}
+
+impl Check for MatviewAge {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let age = match self.refresh_log {
+                Some(ref log) => {
+                    let row = session.query_one("SELECT $1::text::regclass::text", &[log]).await?;
+                    // quoted as needed, the table is known to exist now
+                    let log: String = column(&row, 0)?;
+                    format!("(SELECT extract(epoch FROM now() - max(l.refreshed_at))::float8 FROM {} l WHERE to_regclass(l.matview::text) = c.oid)", log)
+                }
+                None => FILE_AGE.to_string(),
+            };
+            let rows = session.query(&QUERY.replace("{age}", &age), &[&self.views]).await.map_err(|mut status| {
+                if self.refresh_log.is_none() && status.description.contains("permission denied") {
+                    status.description += " (the refresh times need pg_stat_file() or --refresh-log)";
+                }
+                status
+            })?;
+
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut stale = vec![];
+            let mut oldest: Option<(String, f64)> = None;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let age = column::<Option<f64>>(row, 1)?.unwrap_or(f64::INFINITY);
+                let metric = column::<i32>(row, 2)? as usize - 1;
+                let view_status = self.thresholds.status(metric, age);
+                if view_status != StatusType::OK {
+                    status.t = status.t.worst(view_status);
+                    stale.push(match age.is_finite() {
+                        true => format!("{} refreshed {} ago", name, format_age(age)),
+                        false => format!("{} never refreshed", name),
+                    });
+                }
+                if oldest.as_ref().is_none_or(|oldest| age > oldest.1) {
+                    oldest = Some((name.clone(), age));
+                }
+                if age.is_finite() {
+                    status.perfdata.push(self.thresholds.perfdata(metric, &name, age.round()).uom("s").min(Some(0.0)));
+                }
+            }
+            if rows.len() < self.views.len() {
+                return Err(Status::new(StatusType::UNKNOWN, "--table lists a relation that is not a materialized view".to_string()));
+            }
+
+            status.description = match oldest {
+                Some((ref name, age)) if age.is_finite() => format!("{} materialized views, oldest refreshed {} ago: {}", rows.len(), format_age(age), name),
+                Some((ref name, _)) => format!("{} materialized views, {} never refreshed", rows.len(), name),
+                None => "No materialized views".to_string(),
+            };
+            if !stale.is_empty() {
+                status.description += &format!(", {} stale", stale.len());
+            }
+            status.long_output = stale;
+            status.perfdata.push(PerfData::new("matviews", rows.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
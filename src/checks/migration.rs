@@ -0,0 +1,95 @@
+// The latest schema migration applied, from the history table of the migration framework: Flyway's
+// `flyway_schema_history`, sqlx's `_sqlx_migrations`, Diesel's `__diesel_schema_migrations` or the `schema_migrations`
+// of Rails and golang-migrate. `--table` names the table if it is another one or in a schema not on the search path,
+// the framework is told by its columns. With `--expect <VERSION>`, e.g. passed by the deployment, another version
+// results in `--mismatch-status` (default: critical). A migration golang-migrate left dirty is CRITICAL.
+
+use super::{Check, Run};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const TABLES: [&str; 4] = ["flyway_schema_history", "_sqlx_migrations", "__diesel_schema_migrations", "schema_migrations"];
+
+// The table, quoted as needed, and its columns
+const COLUMNS: &str = "SELECT c.oid::regclass::text, array_agg(a.attname::text) FROM pg_class c \
+                         JOIN pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped \
+                       WHERE c.oid = to_regclass($1) GROUP BY c.oid";
+
+struct Migration {
+    table: Option<String>,
+    expect: Option<String>,
+    mismatch_status: StatusType,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Migration {
+        table: options.value_of("table").map(|table| table.to_string()),
+        expect: options.value_of("expect").map(|expect| expect.to_string()),
+        // possible values are restricted by clap
+        mismatch_status: options.value_of("mismatch-status").unwrap_or("critical").parse().unwrap(),
+    }))
+}
+
+// The framework of a history table with `columns` and the query for its latest version and whether it is dirty. Versions
+// of Rails and Diesel are text, the longer one is the later one of numbers like `9` and `10`.
+fn framework(table: &str, columns: &[String]) -> (&'static str, String) {
+    let has = |name: &str| columns.iter().any(|column| column == name);
+    if has("installed_rank") {
+        ("Flyway", format!("SELECT version, false FROM {} WHERE success AND version IS NOT NULL ORDER BY installed_rank DESC LIMIT 1", table))
+    } else if has("installed_on") && has("success") {
+        ("sqlx", format!("SELECT version::text, false FROM {} WHERE success ORDER BY version DESC LIMIT 1", table))
+    } else if has("dirty") {
+        ("golang-migrate", format!("SELECT version::text, dirty FROM {} LIMIT 1", table))
+    } else if has("run_on") {
+        ("Diesel", format!("SELECT version::text, false FROM {} ORDER BY length(version) DESC, version DESC LIMIT 1", table))
+    } else {
+        ("Rails", format!("SELECT version::text, false FROM {} ORDER BY length(version::text) DESC, version DESC LIMIT 1", table))
+    }
+}
+
+impl Check for Migration {
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let candidates: Vec<&str> = match self.table {
+                Some(ref table) => vec![table.as_str()],
+                None => TABLES.to_vec(),
+            };
+            let mut found = None;
+            for candidate in candidates {
+                if let Some(row) = session.query(COLUMNS, &[&candidate]).await?.first() {
+                    found = Some((column::<String>(row, 0)?, column::<Vec<String>>(row, 1)?));
+                    break;
+                }
+            }
+            let (table, columns) = match (found, &self.table) {
+                (Some(found), _) => found,
+                (None, Some(table)) => return Err(Status::new(StatusType::UNKNOWN, format!("Table {} does not exist", table))),
+                (None, None) => return Err(Status::new(StatusType::UNKNOWN,
+                    format!("No migration history table, none of {} exists, use --table", TABLES.join(", ")))),
+            };
+
+            let (name, query) = framework(&table, &columns);
+            let rows = session.query(&query, &[]).await?;
+            let (version, dirty): (Option<String>, bool) = match rows.first() {
+                Some(row) => (column(row, 0)?, column(row, 1)?),
+                None => (None, false),
+            };
+            let applied = match version {
+                Some(ref version) => format!("Migration {} applied", version),
+                None => "No migration applied".to_string(),
+            };
+            let mut status = match self.expect {
+                Some(ref expect) if version.as_ref() != Some(expect) =>
+                    Status::new(self.mismatch_status, format!("{}, expected {}", applied, expect)),
+                _ => Status::new(StatusType::OK, applied),
+            };
+            if dirty {
+                status.t = StatusType::CRITICAL;
+                status.description += ", dirty after it failed";
+            }
+            status.description += &format!(" ({} {})", name, table);
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,60 @@
+// Tables of the current database without a primary key or another replica identity. Logical replication cannot
+// replicate their updates and deletes, and rows can not be told apart reliably. A table with `REPLICA IDENTITY FULL`
+// or `USING INDEX` is fine, one with `REPLICA IDENTITY NOTHING` is not even if it has a primary key. Tables of
+// extensions are skipped. Such a table results in `--mismatch-status` (default: critical).
+
+use super::{patterns, top, Check, Run};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT n.nspname || '.' || c.relname, c.relreplident = 'n' \
+                     FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+                     WHERE c.relkind = 'r' AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname !~ '^pg_toast' \
+                       AND (c.relreplident = 'n' OR c.relreplident = 'd' \
+                            AND NOT EXISTS (SELECT FROM pg_constraint p WHERE p.conrelid = c.oid AND p.contype = 'p')) \
+                       AND NOT EXISTS (SELECT FROM pg_depend d WHERE d.classid = 'pg_class'::regclass AND d.objid = c.oid AND d.deptype = 'e') \
+                     ORDER BY 1";
+
+struct MissingPk {
+    include: Option<RegexSet>,
+    exclude: Option<RegexSet>,
+    mismatch_status: StatusType,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(MissingPk {
+        include: patterns(options, "include-table")?,
+        exclude: patterns(options, "exclude-table")?,
+        // possible values are restricted by clap
+        mismatch_status: options.value_of("mismatch-status").unwrap_or("critical").parse().unwrap(),
+        top: top(options)?,
+    }))
+}
+
+impl Check for MissingPk {
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut tables = vec![];
+            for row in &session.query(QUERY, &[]).await? {
+                let name: String = column(row, 0)?;
+                if self.include.as_ref().is_some_and(|include| !include.is_match(&name))
+                    || self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&name)) {
+                    continue;
+                }
+                let nothing: bool = column(row, 1)?;
+                tables.push(if nothing { format!("{} has REPLICA IDENTITY NOTHING", name) } else { format!("{} has no primary key", name) });
+            }
+            let mut status = match tables.len() {
+                0 => Status::new(StatusType::OK, "Every table has a primary key or replica identity".to_string()),
+                n => Status::new(self.mismatch_status, format!("{} tables without a primary key or replica identity", n)),
+            };
+            status.perfdata.push(PerfData::new("tables", tables.len() as f64).min(Some(0.0)));
+            status.long_output = tables.into_iter().take(self.top).collect();
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,242 @@
+// Checks run on a session: the custom `--query` and the built-in checks selected by name with `--check`.
+
+mod activity;
+mod archiver;
+mod backup_age;
+mod cache_hit_ratio;
+mod checkpoints;
+mod conflicts;
+mod connections;
+mod consistency;
+mod database_size;
+mod deadlocks;
+mod duplicate_indexes;
+mod extensions;
+mod idle_in_transaction;
+mod invalid_constraints;
+mod invalid_indexes;
+mod index_bloat;
+mod locks;
+mod long_queries;
+mod matview_age;
+mod migration;
+mod missing_pk;
+mod partitions;
+mod pgbouncer_pools;
+mod query;
+mod replication_lag;
+mod replication_slots;
+mod role;
+mod rowcount;
+mod sequences;
+mod settings;
+mod slow_statements;
+mod subscription_lag;
+mod table_bloat;
+mod tablespaces;
+mod temp_files;
+mod unused_indexes;
+mod uptime;
+mod vacuum_age;
+mod version;
+mod wal;
+mod xid_age;
+
+pub use self::query::{read_query, Query};
+
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::Session;
+use crate::status::{Status, StatusType};
+use std::future::Future;
+use std::pin::Pin;
+use crate::threshold::{self, Comparison, Range};
+
+// A check's run, which borrows the check and the session until it completes
+pub type Run<'a> = Pin<Box<dyn Future<Output = Result<Status, Status>> + Send + 'a>>;
+
+pub trait Check: Send + Sync {
+    // The status of the check, `Err` if it could not be evaluated, e.g. because a query failed
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a>;
+
+    // What the check would run, for `--dry-run`. Built-in checks only tell their thresholds, they pick their queries by
+    // the server's version.
+    fn describe(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+type Builtin = fn(&Options) -> Result<Box<dyn Check>, String>;
+
+const BUILTINS: &[(&str, Builtin)] = &[
+    ("replication-lag", replication_lag::new),
+    ("connections", connections::new),
+    ("database-size", database_size::new),
+    ("table-bloat", table_bloat::new),
+    ("index-bloat", index_bloat::new),
+    ("xid-age", xid_age::new),
+    ("long-queries", long_queries::new),
+    ("idle-in-transaction", idle_in_transaction::new),
+    ("locks", locks::new),
+    ("wal", wal::new),
+    ("archiver", archiver::new),
+    ("replication-slots", replication_slots::new),
+    ("subscription-lag", subscription_lag::new),
+    ("vacuum-age", vacuum_age::new),
+    ("cache-hit-ratio", cache_hit_ratio::new),
+    ("checkpoints", checkpoints::new),
+    ("temp-files", temp_files::new),
+    ("sequences", sequences::new),
+    ("deadlocks", deadlocks::new),
+    ("slow-statements", slow_statements::new),
+    ("role", role::new),
+    ("backup-age", backup_age::new),
+    ("conflicts", conflicts::new),
+    ("pgbouncer-pools", pgbouncer_pools::new),
+    ("extensions", extensions::new),
+    ("rowcount", rowcount::new),
+    ("uptime", uptime::new),
+    ("settings", settings::new),
+    ("consistency", consistency::new),
+    ("version", version::new),
+    ("activity", activity::new),
+    ("tablespaces", tablespaces::new),
+    ("invalid-indexes", invalid_indexes::new),
+    ("unused-indexes", unused_indexes::new),
+    ("missing-pk", missing_pk::new),
+    ("migration", migration::new),
+    ("matview-age", matview_age::new),
+    ("invalid-constraints", invalid_constraints::new),
+    ("duplicate-indexes", duplicate_indexes::new),
+    ("partitions", partitions::new),
+];
+
+pub fn builtin(name: &str) -> Option<Builtin> {
+    BUILTINS.iter().find(|&&(builtin, _)| builtin == name).map(|&(_, new)| new)
+}
+
+// With `--compare`, thresholds are plain numbers instead of ranges
+fn comparisons(options: &Options) -> Result<Option<Vec<Comparison>>, String> {
+    let comparisons: Option<Vec<Comparison>> = match options.value_of("compare") {
+        Some(c) => Some(c.split(',').map(|c| c.parse()).collect::<Result<_, _>>()?),
+        None => None,
+    };
+    if comparisons.is_some() && !(options.is_present("warn") && options.is_present("critical")) {
+        return Err("--compare needs --warn and --critical".to_string());
+    }
+    Ok(comparisons)
+}
+
+// Parses the ranges given by `--warn` or `--critical`
+pub fn ranges(options: &Options, s: &str) -> Result<Vec<Range>, String> {
+    match comparisons(options)? {
+        Some(ref comparisons) => threshold::parse_compare_list(s, comparisons),
+        None => threshold::parse_list(s),
+    }
+}
+
+// Compiles the comma separated regular expressions of an option like `--exclude-table`, `None` if it is not given
+pub fn patterns(options: &Options, name: &str) -> Result<Option<RegexSet>, String> {
+    match options.value_of(name) {
+        None => Ok(None),
+        Some(patterns) => RegexSet::new(patterns.split(',')).map(Some)
+            // regex errors span multiple lines, but the status line must not
+            .map_err(|err| format!("Invalid --{} '{}': {}", name, patterns, err.to_string().split_whitespace().collect::<Vec<_>>().join(" "))),
+    }
+}
+
+// The databases given by `--database`, empty for all
+pub fn databases(options: &Options) -> Vec<String> {
+    match options.value_of("database") {
+        Some(databases) => databases.split(',').map(|database| database.to_string()).collect(),
+        None => vec![],
+    }
+}
+
+// Fails for the first of the requested `databases` that was not `found`
+pub fn require_databases(databases: &[String], found: &[String]) -> Result<(), Status> {
+    match databases.iter().find(|&database| !found.contains(database)) {
+        Some(missing) => Err(Status::new(StatusType::UNKNOWN, format!("Database '{}' does not exist", missing))),
+        None => Ok(()),
+    }
+}
+
+// Shortens a query text to a single line of at most `max` characters for the output
+pub fn one_line(text: &str, max: usize) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line,
+    }
+}
+
+// The number of worst offenders a built-in check lists, `--top`
+pub fn top(options: &Options) -> Result<usize, String> {
+    options.value_of("top").unwrap_or("5").parse().map_err(|_| "--top needs to be a non-negative integer".to_string())
+}
+
+// Warning and critical ranges of a built-in check, one of each for every metric it evaluates. A check without
+// default thresholds only alerts if they are given.
+pub struct Thresholds {
+    metrics: Vec<String>,
+    warn: Vec<Option<Range>>,
+    crit: Vec<Option<Range>>,
+}
+
+impl Thresholds {
+    // Reads `--warn` and `--critical` with a comma separated range for each of `metrics`
+    pub fn new(options: &Options, metrics: &[&str], warn: Option<&str>, crit: Option<&str>) -> Result<Thresholds, String> {
+        Thresholds::parse(options, metrics, options.value_of("warn").or(warn), options.value_of("critical").or(crit))
+    }
+
+    // Like `new` for thresholds a check has read from the options itself. An empty entry means the metric has no
+    // threshold, e.g. `0,` for thresholds on the first of two metrics only.
+    pub fn parse(options: &Options, metrics: &[&str], warn: Option<&str>, crit: Option<&str>) -> Result<Thresholds, String> {
+        let comparisons = comparisons(options)?;
+        let parse = |s: Option<&str>| -> Result<Vec<Option<Range>>, String> {
+            let s = match s {
+                None => return Ok(vec![None; metrics.len()]),
+                Some(s) => s,
+            };
+            let parts: Vec<&str> = s.split(',').collect();
+            parts.iter().enumerate().map(|(i, part)| match comparisons {
+                _ if part.is_empty() => Ok(None),
+                Some(ref comparisons) if comparisons.len() == 1 => comparisons[0].range(part).map(Some),
+                Some(ref comparisons) if comparisons.len() == parts.len() => comparisons[i].range(part).map(Some),
+                Some(_) => Err("Size of comparison operators and thresholds need to match".to_string()),
+                None => part.parse::<Range>().map(Some),
+            }).collect()
+        };
+        let warn = parse(warn)?;
+        let crit = parse(crit)?;
+        if warn.len() != metrics.len() || crit.len() != metrics.len() {
+            return Err(format!("--warn and --critical need a range for each of {}", metrics.join(",")));
+        }
+        Ok(Thresholds { metrics: metrics.iter().map(|metric| metric.to_string()).collect(), warn, crit })
+    }
+
+    pub fn status(&self, metric: usize, value: f64) -> StatusType {
+        let alerts = |range: &Option<Range>| range.as_ref().is_some_and(|range| range.alerts(value));
+        if alerts(&self.crit[metric]) {
+            StatusType::CRITICAL
+        } else if alerts(&self.warn[metric]) {
+            StatusType::WARNING
+        } else {
+            StatusType::OK
+        }
+    }
+
+    // The thresholds of every metric as resolved from the options and the check's defaults
+    pub fn describe(&self) -> Vec<String> {
+        let range = |range: &Option<Range>| range.as_ref().map_or("none".to_string(), |range| range.to_string());
+        self.metrics.iter().enumerate()
+            .map(|(i, metric)| format!("Thresholds of {}: warning {}, critical {}", metric, range(&self.warn[i]), range(&self.crit[i])))
+            .collect()
+    }
+
+    // Performance data of `metric` including its thresholds
+    pub fn perfdata(&self, metric: usize, label: &str, value: f64) -> PerfData {
+        PerfData::new(label, value).warn(self.warn[metric].as_ref()).crit(self.crit[metric].as_ref())
+    }
+}
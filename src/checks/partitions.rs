@@ -0,0 +1,123 @@
+// Coverage of the tables of `--table` that are partitioned by range on a date or timestamp column: from now on, the
+// partitions need to cover `--ahead` (default: 7d) without a gap, otherwise inserts fail or end up in the default
+// partition once the last one is full. Too little coverage is CRITICAL. The rows of a default partition are counted
+// and only alert if `--warn` or `--critical` are given, e.g. `-w 0` for rows that missed their partition. Counting is
+// cancelled after 10 seconds.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::threshold::Range;
+use crate::units::{format_age, parse_number};
+use std::time::Duration;
+
+// The table, quoted as needed, with its partitioning strategy, number of key columns and the key's type
+const TABLE: &str = "SELECT c.oid::regclass::text, p.partstrat::text, p.partnatts::int, \
+                            (SELECT format_type(a.atttypid, NULL) FROM pg_attribute a WHERE a.attrelid = c.oid AND a.attnum = p.partattrs[0]) \
+                     FROM pg_class c LEFT JOIN pg_partitioned_table p ON p.partrelid = c.oid WHERE c.oid = $1::text::regclass";
+
+// Every partition, whether it is the default one and its bounds as seconds since the epoch and the upper one as given
+const PARTITIONS: &str = "
+SELECT k.oid::regclass::text, pg_get_expr(k.relpartbound, k.oid) = 'DEFAULT',
+       extract(epoch FROM CASE WHEN m[1] = 'MINVALUE' THEN '-infinity' ELSE btrim(m[1], '''') END::timestamptz)::float8,
+       extract(epoch FROM CASE WHEN m[2] = 'MAXVALUE' THEN 'infinity' ELSE btrim(m[2], '''') END::timestamptz)::float8,
+       btrim(m[2], ''''), extract(epoch FROM now())::float8
+FROM pg_inherits i JOIN pg_class k ON k.oid = i.inhrelid,
+  LATERAL regexp_match(pg_get_expr(k.relpartbound, k.oid), 'FROM \\((.*)\\) TO \\((.*)\\)') AS m
+WHERE i.inhparent = $1::text::regclass";
+
+const TIME_TYPES: [&str; 3] = ["date", "timestamp without time zone", "timestamp with time zone"];
+
+const COUNT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Partitions {
+    tables: Vec<String>,
+    ahead: f64,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let tables: Vec<String> = match options.value_of("table") {
+        Some(tables) => tables.split(',').map(|table| table.trim().to_string()).collect(),
+        None => return Err("--check partitions needs --table".to_string()),
+    };
+    let ahead = options.value_of("ahead").unwrap_or("7d");
+    let ahead = parse_number(ahead).filter(|ahead| *ahead >= 0.0)
+        .ok_or_else(|| format!("Invalid --ahead '{}', expected a duration like 7d", ahead))?;
+    Ok(Box::new(Partitions { tables, ahead, thresholds: Thresholds::new(options, &["rows"], None, None)? }))
+}
+
+impl Check for Partitions {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut descriptions = vec![];
+            // coverage below `--ahead` is critical
+            let required: Range = format!("{}:", self.ahead).parse()?;
+            for table in &self.tables {
+                let row = session.query_one(TABLE, &[table]).await?;
+                // quoted as needed, the table is known to exist now
+                let name: String = column(&row, 0)?;
+                let key_type: Option<String> = column(&row, 3)?;
+                if column::<Option<String>>(&row, 1)?.as_deref() != Some("r") || column::<Option<i32>>(&row, 2)? != Some(1)
+                    || !key_type.as_deref().is_some_and(|key_type| TIME_TYPES.contains(&key_type)) {
+                    return Err(Status::new(StatusType::UNKNOWN, format!("{} is not partitioned by range on a date or timestamp column", name)));
+                }
+
+                let mut ranges: Vec<(f64, f64, String)> = vec![];
+                let mut default = None;
+                let mut now = 0.0;
+                for row in &session.query(PARTITIONS, &[table]).await? {
+                    now = column(row, 5)?;
+                    if column::<Option<bool>>(row, 1)? == Some(true) {
+                        default = Some(column::<String>(row, 0)?);
+                    } else if let (Some(from), Some(to), Some(upper)) = (column(row, 2)?, column(row, 3)?, column(row, 4)?) {
+                        ranges.push((from, to, upper));
+                    }
+                }
+                // the end of the partitions following each other from now
+                ranges.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let mut covered: Option<(f64, &str)> = None;
+                for (from, to, upper) in &ranges {
+                    let end = covered.map(|(end, _)| end).unwrap_or(now);
+                    if *from <= end && *to > end {
+                        covered = Some((*to, upper));
+                    }
+                }
+                let remaining = covered.map(|(end, _)| end - now).unwrap_or(0.0);
+                let t = if required.alerts(remaining) { StatusType::CRITICAL } else { StatusType::OK };
+                status.t = status.t.worst(t);
+                descriptions.push(match covered {
+                    None => format!("{} has no partition for now", name),
+                    Some((end, _)) if end.is_infinite() => format!("{} is covered without end", name),
+                    Some((_, upper)) => format!("{} is covered for {} until {}", name, format_age(remaining), upper),
+                });
+                if remaining.is_finite() {
+                    status.perfdata.push(PerfData::new(&name, remaining.round()).uom("s").crit(Some(&required)).min(Some(0.0)));
+                }
+
+                if let Some(default) = default {
+                    let count = session.query_timeout(&format!("SELECT count(*)::float8 FROM {}", default), &[], COUNT_TIMEOUT).await?;
+                    let rows: f64 = column(&count[0], 0)?;
+                    let t = self.thresholds.status(0, rows);
+                    status.t = status.t.worst(t);
+                    if t != StatusType::OK || rows > 0.0 {
+                        descriptions.push(format!("{} rows in {}", rows, default));
+                    }
+                    status.perfdata.push(self.thresholds.perfdata(0, &format!("{}_default", name), rows).min(Some(0.0)));
+                }
+            }
+            status.description = descriptions.join(", ");
+            if status.t != StatusType::OK {
+                status.description += &format!(", {} ahead needed", format_age(self.ahead));
+            }
+            Ok(status)
+        })
+    }
+}
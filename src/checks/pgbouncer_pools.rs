@@ -0,0 +1,109 @@
+// Pools of a PgBouncer, connected to its admin console (`dbname=pgbouncer`), which only understands the simple query
+// protocol. For every pool in `SHOW POOLS`, the clients waiting for a server connection and the saturation, the
+// server connections in use as a percentage of the database's `pool_size` from `SHOW DATABASES`, are compared. The
+// average wait of `SHOW STATS` is only reported. Columns are read by name, since they differ between versions.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use tokio_postgres::SimpleQueryRow;
+use crate::session::{simple_column, Session};
+use crate::status::{Status, StatusType};
+use std::collections::{BTreeMap, HashMap};
+
+// the states of server connections that belong to a pool
+const SERVER_STATES: &[&str] = &["sv_active", "sv_idle", "sv_used", "sv_tested", "sv_login"];
+
+struct PgbouncerPools {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(PgbouncerPools { thresholds: Thresholds::new(options, &["waiting", "percent"], Some("5,80"), Some("20,95"))? }))
+}
+
+// The text of the column `name`, which has to exist
+fn text<'a>(row: &'a SimpleQueryRow, name: &str) -> Result<&'a str, Status> {
+    match row.columns().iter().position(|column| column.name() == name) {
+        Some(idx) => Ok(simple_column(row, idx)?.unwrap_or("")),
+        None => Err(Status::new(StatusType::UNKNOWN, format!("Column '{}' is missing", name))),
+    }
+}
+
+// The number in the column `name`, 0 if the column does not exist in this version
+fn number(row: &SimpleQueryRow, name: &str) -> Result<f64, Status> {
+    match row.columns().iter().position(|column| column.name() == name) {
+        Some(idx) => simple_column(row, idx)?.unwrap_or("0").parse()
+            .map_err(|_| Status::new(StatusType::UNKNOWN, format!("Column '{}' is not a number", name))),
+        None => Ok(0.0),
+    }
+}
+
+impl Check for PgbouncerPools {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut pool_sizes = HashMap::new();
+            for row in &session.simple_query("SHOW DATABASES").await? {
+                pool_sizes.insert(text(row, "name")?.to_string(), number(row, "pool_size")?);
+            }
+            let mut waits = BTreeMap::new();
+            for row in &session.simple_query("SHOW STATS").await? {
+                // microseconds
+                waits.insert(text(row, "database")?.to_string(), number(row, "avg_wait_time")? / 1_000_000.0);
+            }
+
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut pools = 0;
+            let mut total_waiting = 0.0;
+            let mut alerting = vec![];
+            for row in &session.simple_query("SHOW POOLS").await? {
+                let database = text(row, "database")?;
+                // the admin console itself
+                if database == "pgbouncer" {
+                    continue;
+                }
+                let name = format!("{}/{}", database, text(row, "user")?);
+                pools += 1;
+
+                let waiting = number(row, "cl_waiting")?;
+                total_waiting += waiting;
+                let mut pool_status = self.thresholds.status(0, waiting);
+                status.perfdata.push(self.thresholds.perfdata(0, &format!("{}_waiting", name), waiting).min(Some(0.0)));
+                let mut servers = 0.0;
+                for state in SERVER_STATES {
+                    servers += number(row, state)?;
+                }
+                let mut details = vec![format!("{} clients active, {} waiting, {} servers", number(row, "cl_active")?, waiting, servers)];
+                if let Some(&pool_size) = pool_sizes.get(database).filter(|&&pool_size| pool_size > 0.0) {
+                    let percent = servers * 100.0 / pool_size;
+                    pool_status = pool_status.worst(self.thresholds.status(1, percent));
+                    status.perfdata.push(self.thresholds.perfdata(1, &format!("{}_saturation", name), percent.round()).uom("%").min(Some(0.0)));
+                    details.push(format!("{}% of pool_size {}", percent.round(), pool_size));
+                }
+                if let Some(&wait) = waits.get(database) {
+                    details.push(format!("average wait {}s", wait));
+                }
+                status.long_output.push(format!("{}: {}", name, details.join(", ")));
+                if pool_status != StatusType::OK {
+                    status.t = status.t.worst(pool_status);
+                    alerting.push(name);
+                }
+            }
+
+            for (database, &wait) in &waits {
+                if database != "pgbouncer" {
+                    status.perfdata.push(PerfData::new(&format!("{}_avg_wait", database), wait).uom("s").min(Some(0.0)));
+                }
+            }
+            status.description = format!("{} pools, {} clients waiting", pools, total_waiting);
+            if !alerting.is_empty() {
+                status.description += &format!(", {} alerting: {}", alerting.len(), alerting.join(", "));
+            }
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,737 @@
+// A custom query given by `--query`. Only the first row of the result is evaluated, every column against its own
+// warning and critical range. With `--rate` or `--delta`, numeric columns are counters and evaluated by their change
+// since the previous run, kept in the state directory by the query's text.
+
+use super::{ranges, Check, Run};
+use crate::expect::TextExpectation;
+use crate::options::Options;
+use crate::perfdata::{self, PerfData};
+use tokio_postgres::types::Type;
+use crate::session::{column, simple_column, Session};
+use crate::status::{Status, StatusType};
+use std::str::FromStr;
+use std::time::Instant;
+use crate::threshold::{Comparison, Range};
+use crate::value::Value;
+use crate::verbose;
+
+// How NULL values in the result set are evaluated
+#[derive(Clone, Copy)]
+enum NullPolicy {
+    Status(StatusType),
+    Zero,
+}
+
+impl FromStr for NullPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<NullPolicy, String> {
+        match s {
+            "zero" => Ok(NullPolicy::Zero),
+            s => s.parse().map(NullPolicy::Status),
+        }
+    }
+}
+
+// Counters are evaluated by their increase per second (`--rate`) or since the previous run (`--delta`)
+#[derive(Clone, Copy, PartialEq)]
+enum Counters {
+    Plain,
+    Rate,
+    Delta,
+}
+
+// How `--aggregate` reduces a column of all rows to one value. NULL values are skipped like SQL's aggregates do.
+#[derive(Clone, Copy, PartialEq)]
+enum Aggregate {
+    Max,
+    Min,
+    Sum,
+    Avg,
+    Count,
+}
+
+impl FromStr for Aggregate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Aggregate, String> {
+        match s {
+            "max" => Ok(Aggregate::Max),
+            "min" => Ok(Aggregate::Min),
+            "sum" => Ok(Aggregate::Sum),
+            "avg" => Ok(Aggregate::Avg),
+            "count" => Ok(Aggregate::Count),
+            _ => Err(format!("Invalid aggregate '{}', expected max, min, sum, avg or count", s)),
+        }
+    }
+}
+
+// Which rows are evaluated: only the first, every row reporting the worst one, or every row reporting all of them
+#[derive(Clone, Copy, PartialEq)]
+enum RowMode {
+    First,
+    Worst,
+    All,
+}
+
+impl FromStr for RowMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RowMode, String> {
+        match s {
+            "first" => Ok(RowMode::First),
+            "worst" => Ok(RowMode::Worst),
+            "all" => Ok(RowMode::All),
+            _ => Err(format!("Invalid row mode '{}'", s)),
+        }
+    }
+}
+
+// How the columns of a result are labelled and their thresholds
+struct Columns {
+    labels: Vec<String>,
+    warn: Vec<Range>,
+    crit: Vec<Range>,
+}
+
+// The column names and the values of every row
+type Rows = (Vec<String>, Vec<Vec<Value>>);
+
+pub struct Query {
+    query: String,
+    vec_warn: Vec<Range>,
+    vec_crit: Vec<Range>,
+    strict_thresholds: bool,
+    vec_labels: Vec<String>,
+    column_labels: bool,
+    vec_uom: Vec<String>,
+    vec_min: Vec<Option<f64>>,
+    vec_max: Vec<Option<f64>>,
+    precision: Option<usize>,
+    expectation: Option<TextExpectation>,
+    mismatch_status: StatusType,
+    // text matching these alerts before the expectation is considered
+    warn_regex: Option<TextExpectation>,
+    crit_regex: Option<TextExpectation>,
+    invert_bool: bool,
+    null_policy: NullPolicy,
+    // the status of an empty result, `None` makes it an error
+    on_empty: Option<StatusType>,
+    // the warning and critical range of the number of rows, the columns are only evaluated if they have thresholds then
+    row_thresholds: Option<(Option<Range>, Option<Range>)>,
+    evaluate_columns: bool,
+    // the warning and critical range of the query's run time in seconds
+    time_thresholds: Option<(Option<Range>, Option<Range>)>,
+    simple_protocol: bool,
+    // bind parameters as text and their types, missing types are inferred by the server
+    params: Vec<String>,
+    param_types: Vec<Type>,
+    counters: Counters,
+    // by column, `None` takes the value of the first row
+    aggregates: Vec<Option<Aggregate>>,
+    row_mode: RowMode,
+    details: bool,
+    template: Option<String>,
+}
+
+// FNV-1a, a hash that stays the same across builds, so the state file of a query can be found by its text
+fn fnv(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+// The length of the comment at the start of `sql`, `--` up to the end of the line or `/* */`, which nests
+fn comment_length(sql: &str) -> Option<usize> {
+    if sql.starts_with("--") {
+        return Some(sql.find('\n').unwrap_or(sql.len()));
+    }
+    if !sql.starts_with("/*") {
+        return None;
+    }
+    let mut depth = 0;
+    let mut i = 0;
+    while i < sql.len() {
+        if sql[i..].starts_with("/*") {
+            depth += 1;
+            i += 2;
+        } else if sql[i..].starts_with("*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += sql[i..].chars().next().map_or(1, |c| c.len_utf8());
+        }
+    }
+    Some(sql.len())
+}
+
+// The length of the statement at the start of `sql` up to its terminating semicolon, which is not part of a string,
+// quoted identifier, comment or dollar quote
+fn statement_length(sql: &str) -> usize {
+    let bytes = sql.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(length) = comment_length(&sql[i..]) {
+            i += length;
+            continue;
+        }
+        match bytes[i] {
+            b';' => return i,
+            b'\'' | b'"' => {
+                let quote = bytes[i];
+                // E'...' strings have backslash escapes
+                let escapes = quote == b'\'' && i > 0 && (bytes[i - 1] == b'E' || bytes[i - 1] == b'e');
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += if escapes && bytes[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b'$' => {
+                // `$tag$`, but not a parameter like `$1`
+                let tag_length = sql[i + 1..].find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(sql.len() - i - 1);
+                let tag = &sql[i + 1..i + 1 + tag_length];
+                if sql[i + 1 + tag_length..].starts_with('$') && !tag.starts_with(|c: char| c.is_ascii_digit()) {
+                    let delimiter = format!("${}$", tag);
+                    i += delimiter.len();
+                    i = sql[i..].find(&delimiter).map_or(bytes.len(), |end| i + end + delimiter.len());
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+        // the comments are matched at the start of a character, not inside one of several bytes
+        while i < bytes.len() && !sql.is_char_boundary(i) {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+// Whether `sql` has nothing but whitespace and comments
+fn is_blank(mut sql: &str) -> bool {
+    loop {
+        sql = sql.trim_start();
+        match comment_length(sql) {
+            Some(length) => sql = &sql[length..],
+            None => return sql.is_empty(),
+        }
+    }
+}
+
+// Reads the query of `--query-file`. Only one statement is supported, the terminating semicolon is optional.
+pub fn read_query(path: &str) -> Result<String, String> {
+    let sql = std::fs::read_to_string(path).map_err(|err| format!("Could not read query file '{}': {}", path, err))?;
+    let length = statement_length(&sql);
+    if length < sql.len() && !is_blank(&sql[length + 1..]) {
+        return Err(format!("Query file '{}' contains more than one statement, only a single query is supported", path));
+    }
+    if is_blank(&sql[..length]) {
+        return Err(format!("Query file '{}' contains no query", path));
+    }
+    Ok(sql[..length].trim().to_string())
+}
+
+// The type of `--param-type`, by the names postgres knows them
+fn param_type(name: &str) -> Result<Type, String> {
+    Ok(match name.trim().to_lowercase().as_str() {
+        "bool" | "boolean" => Type::BOOL,
+        "int2" | "smallint" => Type::INT2,
+        "int" | "int4" | "integer" => Type::INT4,
+        "int8" | "bigint" => Type::INT8,
+        "oid" => Type::OID,
+        "float4" | "real" => Type::FLOAT4,
+        "float8" | "float" | "double precision" => Type::FLOAT8,
+        "text" => Type::TEXT,
+        "varchar" => Type::VARCHAR,
+        "name" => Type::NAME,
+        _ => return Err(format!("Unsupported parameter type '{}', cast a text parameter in the query instead, e.g. $1::text::{}", name, name)),
+    })
+}
+
+// Splits a comma separated option into owned strings
+fn list(value: Option<&str>) -> Vec<String> {
+    value.unwrap_or("").split(',').map(|s| s.to_string()).collect()
+}
+
+impl Query {
+    // The query is given separately, since the command line may contain several
+    pub fn new(query: &str, options: &Options) -> Result<Query, String> {
+        // `--expect` and `--expect-warning` alert unless a column equals the number, thresholds not given never alert then
+        let exact = |name: &str| options.value_of(name).map(|numbers| numbers.split(',')
+            .map(|n| Comparison::Ne.range(n.trim()).map_err(|_| format!("Expected value '{}' needs to be a number", n)))
+            .collect::<Result<Vec<Range>, String>>()).transpose();
+        let (expect_warn, expect_crit) = (exact("expect-warning")?, exact("expect")?);
+        let never = |exact: &Option<Vec<Range>>| exact.as_ref().map(|ranges| vec!["~:"; ranges.len()].join(","));
+        let (default_warn, default_crit) = match (never(&expect_crit), never(&expect_warn)) {
+            (None, None) => ("~:0".to_string(), "~:1".to_string()),
+            (warn, crit) => (warn.unwrap_or_else(|| "~:".to_string()), crit.unwrap_or_else(|| "~:".to_string())),
+        };
+        let vec_warn = match expect_warn {
+            Some(ranges) => ranges,
+            None => ranges(options, options.value_of("warn").unwrap_or(&default_warn))?,
+        };
+        let vec_crit = match expect_crit {
+            Some(ranges) => ranges,
+            None => ranges(options, options.value_of("critical").unwrap_or(&default_crit))?,
+        };
+        // Make sure we do not have different sized warning and critical vectors
+        if vec_warn.len() != vec_crit.len() {
+            return Err("Size of integer arrays need to match".to_string());
+        }
+
+        let precision = match options.value_of("precision").map(|p| p.parse::<usize>()) {
+            None => None,
+            Some(Ok(p)) => Some(p),
+            Some(Err(_)) => return Err("Precision needs to be a non-negative integer".to_string()),
+        };
+
+        let expectation = match options.one_of(&["expect-string", "expect-regex"]) {
+            Some(("expect-string", literal)) => Some(TextExpectation::Literal(literal.to_string())),
+            Some((_, pattern)) => Some(TextExpectation::regex(pattern)?),
+            None => None,
+        };
+
+        let range = |name: &str| options.value_of(name).map(|range| range.parse::<Range>()).transpose();
+        let row_thresholds = match (range("rows-warning")?, range("rows-critical")?) {
+            (None, None) => None,
+            thresholds => Some(thresholds),
+        };
+        let time_thresholds = match (range("time-warning")?, range("time-critical")?) {
+            (None, None) => None,
+            thresholds => Some(thresholds),
+        };
+        let evaluate_columns = row_thresholds.is_none() || ["warn", "critical", "expect", "expect-warning", "expect-string",
+            "expect-regex", "warn-regex", "critical-regex"].iter().any(|&name| options.is_present(name));
+
+        Ok(Query {
+            query: query.to_string(),
+            vec_warn,
+            vec_crit,
+            strict_thresholds: options.is_present("strict-thresholds"),
+            vec_labels: list(options.value_of("labels")),
+            column_labels: options.is_present("column-labels"),
+            vec_uom: list(options.value_of("uom")),
+            vec_min: perfdata::parse_limits(options.value_of("perf-min").unwrap_or(""))?,
+            vec_max: perfdata::parse_limits(options.value_of("perf-max").unwrap_or(""))?,
+            precision,
+            expectation,
+            // possible values are restricted by clap
+            mismatch_status: options.value_of("mismatch-status").unwrap_or("critical").parse().unwrap(),
+            warn_regex: options.value_of("warn-regex").map(TextExpectation::regex).transpose()?,
+            crit_regex: options.value_of("critical-regex").map(TextExpectation::regex).transpose()?,
+            invert_bool: options.is_present("invert-bool"),
+            null_policy: options.value_of("null-is").unwrap_or("unknown").parse().unwrap(),
+            on_empty: options.value_of("on-empty").map(|t| t.parse().unwrap()),
+            row_thresholds,
+            evaluate_columns,
+            time_thresholds,
+            simple_protocol: options.is_present("simple-protocol"),
+            params: options.values_of("param").into_iter().map(|param| param.to_string()).collect(),
+            param_types: options.values_of("param-type").into_iter().map(param_type).collect::<Result<_, _>>()?,
+            counters: match (options.is_present("rate"), options.is_present("delta")) {
+                (true, _) => Counters::Rate,
+                (_, true) => Counters::Delta,
+                _ => Counters::Plain,
+            },
+            aggregates: options.value_of("aggregate").map(|a| list(Some(a))).unwrap_or_default().iter()
+                .map(|a| if a.trim().is_empty() { Ok(None) } else { a.trim().parse().map(Some) }).collect::<Result<_, _>>()?,
+            // possible values are restricted by clap
+            row_mode: options.value_of("row-mode").unwrap_or("first").parse().unwrap(),
+            details: options.is_present("details"),
+            template: options.value_of("output-format").map(|template| template.to_string()),
+        })
+    }
+
+    // The column names and the values of every row
+    async fn rows(&self, session: &mut Session) -> Result<Rows, Status> {
+        if self.simple_protocol {
+            let rows = session.simple_query(&self.query).await?;
+            let names = rows.first().map(|row| row.columns().iter().map(|c| c.name().to_string()).collect()).unwrap_or_default();
+            let values = rows.iter().map(|row| (0..row.len()).map(|j| simple_column(row, j).map(Value::from_text)).collect())
+                .collect::<Result<_, _>>()?;
+            return Ok((names, values));
+        }
+        // a prepared statement also tells the columns of an empty result, which aggregates need
+        let (names, rows) = if !self.params.is_empty() || !self.aggregates.is_empty() {
+            session.query_params(&self.query, &self.param_types, &self.params).await?
+        } else {
+            let rows = session.query(&self.query, &[]).await?;
+            (rows.first().map(|row| row.columns().iter().map(|c| c.name().to_string()).collect()).unwrap_or_default(), rows)
+        };
+        let values = rows.iter().map(|row| (0..row.len()).map(|j| column::<Value>(row, j)).collect::<Result<_, _>>())
+            .collect::<Result<_, _>>()?;
+        Ok((names, values))
+    }
+
+    // Reduces the rows to one by the column's aggregate, a column without one takes the value of the first row
+    fn aggregate(&self, columns: usize, rows: Vec<Vec<Value>>) -> Result<Vec<Value>, Status> {
+        let mut columns: Vec<Vec<Value>> = vec![vec![]; columns];
+        for row in rows {
+            for (j, value) in row.into_iter().enumerate() {
+                columns[j].push(value);
+            }
+        }
+        columns.into_iter().enumerate().map(|(j, values)| {
+            let aggregate = match self.aggregates.get(j).cloned().unwrap_or(None) {
+                Some(aggregate) => aggregate,
+                None => return Ok(values.into_iter().next().unwrap_or(Value::Null)),
+            };
+            let values: Vec<Value> = values.into_iter().filter(|value| *value != Value::Null).collect();
+            if aggregate == Aggregate::Count {
+                return Ok(Value::Int(values.len() as i64));
+            }
+            if values.is_empty() {
+                return Ok(Value::Null);
+            }
+            let numbers: Option<Vec<f64>> = values.iter().map(|value| value.as_f64()).collect();
+            let numbers = match (numbers, aggregate) {
+                (Some(numbers), _) => numbers,
+                // timestamps and text have an order, but no sum
+                (None, Aggregate::Max) | (None, Aggregate::Min) => {
+                    let value = values.into_iter().reduce(|a, b| match (&a, &b) {
+                        (&Value::Timestamp(x), &Value::Timestamp(y)) | (&Value::TimestampTz(x), &Value::TimestampTz(y))
+                            if (y > x) == (aggregate == Aggregate::Max) => b,
+                        (Value::Text(x), Value::Text(y)) if (y > x) == (aggregate == Aggregate::Max) => b,
+                        _ => a,
+                    });
+                    return Ok(value.unwrap_or(Value::Null));
+                }
+                (None, _) => return Err(Status::new(StatusType::UNKNOWN, format!("Column {} is not numeric and cannot be aggregated", j + 1))),
+            };
+            let number = match aggregate {
+                Aggregate::Max => numbers.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                Aggregate::Min => numbers.iter().cloned().fold(f64::INFINITY, f64::min),
+                Aggregate::Sum => numbers.iter().sum(),
+                _ => numbers.iter().sum::<f64>() / numbers.len() as f64,
+            };
+            // the type of the column is kept, only the average of integers is a float
+            Ok(match (&values[0], aggregate) {
+                (&Value::Int(_), Aggregate::Avg) => Value::Float(number),
+                (&Value::Int(_), _) => Value::Int(number as i64),
+                (&Value::Interval(_), _) => Value::Interval(number),
+                _ => Value::Float(number),
+            })
+        }).collect()
+    }
+
+    // Replaces the numeric values by their change since the previous run, `None` on the first run. The first row keeps
+    // the keys of a single row, so the counters stay when `--row-mode` is changed.
+    async fn changes(&self, session: &mut Session, rows: Vec<Vec<Value>>) -> Result<Option<Vec<Vec<Value>>>, Status> {
+        // the same query with other parameters has counters of its own
+        let key = std::iter::once(self.query.as_str()).chain(self.params.iter().map(|param| param.as_str())).collect::<Vec<&str>>().join("\0");
+        let mut state = session.state(&format!("query-{:016x}", fnv(&key)))?;
+        let mut first_run = false;
+        let mut changes = vec![];
+        for (i, row) in rows.into_iter().enumerate() {
+            let mut row_changes = vec![];
+            for (j, value) in row.into_iter().enumerate() {
+                let key = if i == 0 { format!("col{}", j + 1) } else { format!("row{}.col{}", i + 1, j + 1) };
+                row_changes.push(match (value.as_f64(), self.counters) {
+                    (Some(number), Counters::Rate) => match state.rate(&key, number) {
+                        Some(rate) => Value::Float(rate),
+                        None => {
+                            first_run = true;
+                            value
+                        }
+                    },
+                    (Some(number), _) => match (state.delta(&key, number), value) {
+                        (Some(delta), Value::Int(_)) => Value::Int(delta as i64),
+                        (Some(delta), Value::Interval(_)) => Value::Interval(delta),
+                        (Some(delta), _) => Value::Float(delta),
+                        (None, value) => {
+                            first_run = true;
+                            value
+                        }
+                    },
+                    (None, _) => value,
+                });
+            }
+            changes.push(row_changes);
+        }
+        state.save()?;
+        Ok(if first_run { None } else { Some(changes) })
+    }
+
+    // The labels of the columns, given by `--labels`, with `--column-labels` by the column names or by their position.
+    // Columns postgres could not name, like `SELECT 1`, are `?column?`.
+    fn labels(&self, names: &[String]) -> Vec<String> {
+        names.iter().enumerate().map(|(j, name)| match self.vec_labels.get(j).filter(|label| !label.is_empty()) {
+            Some(label) => label.clone(),
+            None if self.column_labels && name != "?column?" => name.clone(),
+            None => format!("col{}", j + 1),
+        }).collect()
+    }
+
+    // Fills in the placeholders of `--output-format`: `{colN}` or a label for a column's value, `{warnN}` and
+    // `{critN}` for its thresholds, `{status}`, `{host}`, `{db}` and `{duration}` of the query in seconds. `{{` and
+    // `}}` are literal braces.
+    fn format(&self, template: &str, columns: &Columns, formatted: &[String], t: StatusType, session: &Session, duration: f64) -> Result<String, Status> {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(idx) = rest.find(['{', '}']) {
+            out += &rest[..idx];
+            if rest[idx..].starts_with("{{") || rest[idx..].starts_with("}}") {
+                out.push(rest.as_bytes()[idx] as char);
+                rest = &rest[idx + 2..];
+                continue;
+            }
+            let end = match rest[idx..].find('}') {
+                Some(end) if rest.as_bytes()[idx] == b'{' => idx + end,
+                _ => return Err(Status::new(StatusType::UNKNOWN, format!("Unbalanced braces in --output-format '{}'", template))),
+            };
+            let name = &rest[idx + 1..end];
+            let column = |prefix: &str| name.strip_prefix(prefix).and_then(|n| n.parse::<usize>().ok())
+                .filter(|&n| n >= 1 && n <= formatted.len()).map(|n| n - 1);
+            out += &match name {
+                "status" => t.to_string(),
+                "host" => session.host().to_string(),
+                "db" => session.dbname().to_string(),
+                "duration" => format!("{:.3}", duration),
+                _ => match (column("col"), column("warn"), column("crit"), columns.labels.iter().position(|label| label == name)) {
+                    (Some(j), _, _, _) | (_, _, _, Some(j)) => formatted[j].clone(),
+                    (_, Some(j), _, _) => columns.warn[j].to_string(),
+                    (_, _, Some(j), _) => columns.crit[j].to_string(),
+                    _ => return Err(Status::new(StatusType::UNKNOWN, format!("Unknown placeholder '{{{}}}' in --output-format", name))),
+                },
+            };
+            rest = &rest[end + 1..];
+        }
+        Ok(out + rest)
+    }
+
+    // The number thresholds are compared against and reported in the perfdata
+    fn number(&self, value: &Value) -> Option<f64> {
+        match (value, self.null_policy) {
+            (&Value::Null, NullPolicy::Zero) => Some(0.0),
+            (value, _) => value.as_f64(),
+        }
+    }
+}
+
+impl Check for Query {
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let start = Instant::now();
+            let (names, rows) = self.rows(session).await?;
+            let duration = start.elapsed().as_secs_f64();
+            let mut status = match self.row_thresholds {
+                Some((ref warn, ref crit)) => self.row_count(session, warn.as_ref(), crit.as_ref(), names, rows, duration).await?,
+                None => self.result(session, names, rows, duration).await?,
+            };
+            // a slow query alerts even if its result is fine, e.g. a canary query
+            if let Some((ref warn, ref crit)) = self.time_thresholds {
+                let t = range_status(warn.as_ref(), crit.as_ref(), duration);
+                if t != StatusType::OK {
+                    status.description += &format!(", query took {:.3}s", duration);
+                }
+                status.t = status.t.worst(t);
+                status.query_time = Some(Box::new(PerfData::new("query_time", (duration * 1000.0).round() / 1000.0).uom("s").warn(warn.as_ref()).crit(crit.as_ref()).min(Some(0.0))));
+            }
+            Ok(status)
+        })
+    }
+
+    fn describe(&self) -> Vec<String> {
+        let ranges = |ranges: &[Range]| ranges.iter().map(|range| range.to_string()).collect::<Vec<String>>().join(",");
+        let optional = |range: Option<&Range>| range.map(|range| range.to_string()).unwrap_or_else(|| "none".to_string());
+        let mut lines = vec![format!("SQL: {}", self.query)];
+        if !self.params.is_empty() {
+            lines.push(format!("Parameters: {}", self.params.join(", ")));
+        }
+        lines.push(format!("Thresholds: warning {}, critical {}", ranges(&self.vec_warn), ranges(&self.vec_crit)));
+        if let Some((ref warn, ref crit)) = self.row_thresholds {
+            lines.push(format!("Rows: warning {}, critical {}", optional(warn.as_ref()), optional(crit.as_ref())));
+        }
+        if let Some((ref warn, ref crit)) = self.time_thresholds {
+            lines.push(format!("Run time: warning {}, critical {}", optional(warn.as_ref()), optional(crit.as_ref())));
+        }
+        lines
+    }
+}
+
+// The status of a value by optional ranges
+fn range_status(warn: Option<&Range>, crit: Option<&Range>, value: f64) -> StatusType {
+    if crit.is_some_and(|crit| crit.alerts(value)) {
+        StatusType::CRITICAL
+    } else if warn.is_some_and(|warn| warn.alerts(value)) {
+        StatusType::WARNING
+    } else {
+        StatusType::OK
+    }
+}
+
+impl Query {
+    // Evaluates the number of rows, e.g. of a query returning a row per blocked session, and the columns if they have
+    // thresholds too
+    async fn row_count(&self, session: &mut Session, warn: Option<&Range>, crit: Option<&Range>, names: Vec<String>,
+                       rows: Vec<Vec<Value>>, duration: f64) -> Result<Status, Status> {
+        let count = rows.len() as f64;
+        let mut status = Status::new(range_status(warn, crit, count), format!("{} rows", rows.len()));
+        status.perfdata.push(PerfData::new("rows", count).warn(warn).crit(crit).min(Some(0.0)));
+        if !self.evaluate_columns || rows.is_empty() {
+            let labels = self.labels(&names);
+            status.long_output = rows.iter().enumerate().map(|(i, row)| format!("Row {}: {}", i + 1,
+                labels.iter().zip(row).map(|(label, value)| format!("{}={}", label, value)).collect::<Vec<_>>().join(", "))).collect();
+            return Ok(status);
+        }
+        let result = self.result(session, names, rows, duration).await?;
+        status.t = status.t.worst(result.t);
+        status.description += &format!(", {}", result.description);
+        status.perfdata.extend(result.perfdata);
+        status.long_output = result.long_output;
+        Ok(status)
+    }
+
+    // Evaluates the values of a result. Only the first row is evaluated, unless the rows are aggregated or
+    // `--row-mode` evaluates all of them.
+    async fn result(&self, session: &mut Session, names: Vec<String>, rows: Vec<Vec<Value>>, duration: f64) -> Result<Status, Status> {
+        let rows = match self.row_mode {
+            _ if !self.aggregates.is_empty() => vec![self.aggregate(names.len(), rows)?],
+            RowMode::First => rows.into_iter().take(1).collect(),
+            _ => rows,
+        };
+        // the columns of an empty result are unknown with the simple protocol
+        if rows.is_empty() || rows[0].is_empty() {
+            return match self.on_empty {
+                Some(t) => Ok(Status::new(t, "Query did return empty row set".to_string())),
+                None => Err(Status::new(StatusType::UNKNOWN, "Query did return empty row set".to_string())),
+            };
+        }
+        // a single threshold applies to every column, unless `--strict-thresholds` wants one for each
+        let (warn, crit) = match self.vec_warn.len() {
+            n if n == rows[0].len() => (self.vec_warn.clone(), self.vec_crit.clone()),
+            1 if !self.strict_thresholds => (vec![self.vec_warn[0].clone(); rows[0].len()], vec![self.vec_crit[0].clone(); rows[0].len()]),
+            _ => return Err(Status::new(StatusType::UNKNOWN, "Size of result set and integer array need to match".to_string())),
+        };
+        let rows = match self.counters {
+            Counters::Plain => rows,
+            _ => match self.changes(session, rows).await? {
+                Some(rows) => rows,
+                None => return Ok(Status::new(StatusType::OK, "Counters recorded, they are compared from the next run on (first run)".to_string())),
+            },
+        };
+        let mut rows: Vec<Vec<Value>> = rows.into_iter().map(|row| row.into_iter().map(|value| match self.precision {
+            Some(digits) => value.round(digits),
+            None => value,
+        }).collect()).collect();
+
+        // timestamps are compared by their age relative to the server's clock
+        if rows.iter().flatten().any(|v| v.is_timestamp()) {
+            let (now, local_now) = session.clock().await?;
+            rows = rows.into_iter().map(|row| row.into_iter().map(|v| v.age(now, local_now)).collect()).collect();
+        }
+
+        let columns = Columns { labels: self.labels(&names), warn, crit };
+        let mut results = rows.iter().map(|values| self.evaluate(values, &columns, session, duration)).collect::<Result<Vec<_>, _>>()?;
+        if results.len() == 1 {
+            return Ok(results.remove(0));
+        }
+
+        // every row on a line of long output
+        let t = results.iter().fold(StatusType::OK, |t, result| t.worst(result.t));
+        let rows: Vec<String> = results.iter().enumerate().map(|(i, result)| format!("Row {}: {} - {}", i + 1, result.t, result.description)).collect();
+        let mut status = match self.row_mode {
+            RowMode::All => {
+                let alerting: Vec<&String> = results.iter().zip(&rows).filter(|(result, _)| result.t != StatusType::OK).map(|(_, row)| row).collect();
+                let mut description = format!("{} rows, {} alerting", results.len(), alerting.len());
+                if !alerting.is_empty() {
+                    description += &format!(": {}", alerting.iter().map(|row| row.as_str()).collect::<Vec<&str>>().join(", "));
+                }
+                let mut perfdata = vec![];
+                let mut long_output = vec![];
+                for (i, result) in results.into_iter().enumerate() {
+                    perfdata.extend(result.perfdata.into_iter().map(|mut p| {
+                        p.label = format!("{}_{}", p.label, i + 1);
+                        p
+                    }));
+                    long_output.extend(result.long_output.into_iter().map(|line| format!("Row {}: {}", i + 1, line)));
+                }
+                Status { t, description, perfdata, long_output, query_time: None }
+            }
+            _ => {
+                let worst = results.iter().position(|result| result.t == t).unwrap_or(0);
+                let mut status = results.swap_remove(worst);
+                status.description = format!("Row {} of {}: {}", worst + 1, rows.len(), status.description);
+                status
+            }
+        };
+        status.long_output.extend(rows);
+        Ok(status)
+    }
+
+    // Evaluates the values of one row against the thresholds
+    fn evaluate(&self, values: &[Value], columns: &Columns, session: &Session, duration: f64) -> Result<Status, Status> {
+        let mut status = StatusType::OK;
+        let mut column_statuses = vec![];
+        for (j, (value, (warn, crit))) in values.iter().zip(columns.warn.iter().zip(columns.crit.iter())).enumerate() { // They should all have the same length by now.
+            let (column_status, reason) = match (value, self.number(value)) {
+                (_, Some(number)) if crit.alerts(number) => (StatusType::CRITICAL, format!("critical range {} alerts", crit)),
+                (_, Some(number)) if warn.alerts(number) => (StatusType::WARNING, format!("warning range {} alerts", warn)),
+                (_, Some(_)) => (StatusType::OK, format!("within warning {} and critical {}", warn, crit)),
+                (Value::Text(text), _) if self.crit_regex.as_ref().is_some_and(|regex| regex.is_met(text)) =>
+                    (StatusType::CRITICAL, "matches the critical regex".to_string()),
+                (Value::Text(text), _) if self.warn_regex.as_ref().is_some_and(|regex| regex.is_met(text)) =>
+                    (StatusType::WARNING, "matches the warning regex".to_string()),
+                (Value::Text(text), _) => match self.expectation {
+                    Some(ref expectation) if !expectation.is_met(text) => (self.mismatch_status, "does not meet the expectation".to_string()),
+                    Some(_) => (StatusType::OK, "meets the expectation".to_string()),
+                    None => (StatusType::OK, "text without expectation".to_string()),
+                },
+                (&Value::Bool(b), _) if b == self.invert_bool => (StatusType::CRITICAL, "boolean".to_string()),
+                (&Value::Null, _) => match self.null_policy {
+                    NullPolicy::Status(t) => (t, "NULL".to_string()),
+                    // 0 is within the thresholds, otherwise the ranges above had matched
+                    NullPolicy::Zero => (StatusType::OK, "NULL as 0".to_string()),
+                },
+                _ => (StatusType::OK, "not compared".to_string()),
+            };
+            verbose::log(2, || format!("{}: {} is {}, {}", columns.labels[j], value, column_status, reason));
+            status = status.worst(column_status);
+            column_statuses.push(column_status);
+        }
+
+        // print result set as tuple `(s1,..,sn)`, or `label=s1, ..` if the columns are labelled, or with `--details` a
+        // summary followed by a line per column
+        let formatted: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+        let mut description = if self.column_labels || self.vec_labels.iter().any(|label| !label.is_empty()) {
+            let labelled: Vec<String> = columns.labels.iter().zip(&formatted).map(|(label, value)| format!("{}={}", label, value)).collect();
+            format!("Result: {}", labelled.join(", "))
+        } else {
+            format!("Result:({})", formatted.join(","))
+        };
+        let mut long_output = vec![];
+        if self.details {
+            let alerting: Vec<String> = column_statuses.iter().enumerate().filter(|&(_, &t)| t != StatusType::OK)
+                .map(|(j, t)| format!("{} is {} ({})", columns.labels[j], formatted[j], t)).collect();
+            description = match alerting.len() {
+                0 if values.len() == 1 => "The value is OK".to_string(),
+                0 => format!("All {} values OK", values.len()),
+                n => format!("{} of {} values alerting: {}", n, values.len(), alerting.join(", ")),
+            };
+            for (j, t) in column_statuses.iter().enumerate() {
+                let thresholds = match self.number(&values[j]) {
+                    Some(_) => format!(", warning {}, critical {}", columns.warn[j], columns.crit[j]),
+                    None => String::new(),
+                };
+                long_output.push(format!("{}: {} ({}{})", columns.labels[j], formatted[j], t, thresholds));
+            }
+        }
+
+        if let Some(ref template) = self.template {
+            description = self.format(template, columns, &formatted, status, session, duration)?;
+        }
+
+        // one perfdata metric per column, labelled like above
+        let perfdata = values.iter().enumerate().filter_map(|(j, value)| self.number(value).map(|number| (j, number))).map(|(j, number)| {
+            PerfData::new(&columns.labels[j], number)
+                .uom(self.vec_uom.get(j).map(|uom| uom.as_str()).filter(|uom| !uom.is_empty()).unwrap_or(values[j].uom()))
+                .warn(columns.warn.get(j))
+                .crit(columns.crit.get(j))
+                .min(self.vec_min.get(j).cloned().unwrap_or(None))
+                .max(self.vec_max.get(j).cloned().unwrap_or(None))
+        }).collect();
+        Ok(Status { t: status, description, perfdata, long_output, query_time: None })
+    }
+}
@@ -0,0 +1,78 @@
+// Streaming replication lag in bytes and seconds. On a primary, every standby in `pg_stat_replication` is checked
+// by how far its replay position is behind the current WAL position. On a standby, the replay position is compared to
+// the received WAL, and the time lag is the age of the last replayed transaction unless everything received is
+// replayed already.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const PRIMARY: &str = "SELECT coalesce(nullif(application_name, ''), client_addr::text, 'local'), \
+                              pg_wal_lsn_diff(pg_current_wal_lsn(), replay_lsn)::float8, \
+                              coalesce(extract(epoch FROM replay_lag), 0)::float8 \
+                       FROM pg_stat_replication ORDER BY 1";
+
+const STANDBY: &str = "SELECT pg_wal_lsn_diff(pg_last_wal_receive_lsn(), pg_last_wal_replay_lsn())::float8, \
+                              CASE WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 \
+                                   ELSE extract(epoch FROM now() - pg_last_xact_replay_timestamp())::float8 END";
+
+struct ReplicationLag {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let thresholds = Thresholds::new(options, &["bytes", "seconds"], Some("16MB,1m"), Some("256MB,5m"))?;
+    Ok(Box::new(ReplicationLag { thresholds }))
+}
+
+impl ReplicationLag {
+    // Evaluates the lag of one standby, a lag that is not known (yet) is not compared
+    fn evaluate(&self, status: &mut Status, name: &str, bytes: Option<f64>, seconds: Option<f64>) {
+        let mut lags = vec![];
+        for (metric, lag) in [bytes, seconds].iter().enumerate() {
+            if let Some(lag) = *lag {
+                status.t = status.t.worst(self.thresholds.status(metric, lag));
+                let (label, uom, formatted) = match metric {
+                    0 => (format!("{}_bytes", name), "B", format_bytes(lag)),
+                    _ => (format!("{}_seconds", name), "s", format!("{}s", lag)),
+                };
+                status.perfdata.push(self.thresholds.perfdata(metric, &label, lag).uom(uom));
+                lags.push(formatted);
+            }
+        }
+        if lags.is_empty() {
+            lags.push("unknown".to_string());
+        }
+        let lag = format!("{} lag {}", name, lags.join(", "));
+        status.description = if status.description.is_empty() { lag } else { status.description.clone() + "; " + &lag };
+    }
+}
+
+impl Check for ReplicationLag {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let in_recovery: bool = column(&session.query_one("SELECT pg_is_in_recovery()", &[]).await?, 0)?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            if in_recovery {
+                let row = session.query_one(STANDBY, &[]).await?;
+                self.evaluate(&mut status, "standby", column(&row, 0)?, column(&row, 1)?);
+            } else {
+                let rows = session.query(PRIMARY, &[]).await?;
+                if rows.is_empty() {
+                    return Ok(Status::new(StatusType::CRITICAL, "No standby connected".to_string()));
+                }
+                for row in &rows {
+                    let name: String = column(row, 0)?;
+                    self.evaluate(&mut status, &name, column(row, 1)?, column(row, 2)?);
+                }
+            }
+            Ok(status)
+        })
+    }
+}
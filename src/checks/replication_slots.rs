@@ -0,0 +1,85 @@
+// WAL retained by replication slots: the distance from a slot's `restart_lsn` to the current WAL position, which
+// cannot be removed while the slot exists. An abandoned slot keeps WAL until the disk is full, unless
+// `max_slot_wal_keep_size` limits it (PostgreSQL 13+). `safe_wal_size` is then the WAL that can still be written
+// before the slot loses WAL it needs, and a slot whose WAL is removed already is CRITICAL.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+// on a standby, the WAL position is the received or replayed one
+const POSITION: &str = "CASE WHEN pg_is_in_recovery() \
+                             THEN coalesce(pg_last_wal_receive_lsn(), pg_last_wal_replay_lsn()) \
+                             ELSE pg_current_wal_lsn() END";
+
+struct ReplicationSlots {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(ReplicationSlots { thresholds: Thresholds::new(options, &["bytes", "safe-bytes"], Some("1GB,"), Some("10GB,"))? }))
+}
+
+impl Check for ReplicationSlots {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let version: i32 = column(&session.query_one("SELECT current_setting('server_version_num')::int", &[]).await?, 0)?;
+            // `safe_wal_size` and `wal_status` are new in PostgreSQL 13
+            let limits = if version >= 130000 { "safe_wal_size::float8, coalesce(wal_status, '')" } else { "NULL::float8, ''" };
+            let query = format!("SELECT slot_name::text, slot_type::text, active, pg_wal_lsn_diff({}, restart_lsn)::float8, {} \
+                                 FROM pg_replication_slots ORDER BY 1", POSITION, limits);
+
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut largest: Option<(String, f64)> = None;
+            let mut alerting = vec![];
+            let rows = session.query(&query, &[]).await?;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let kind: String = column(row, 1)?;
+                let active: bool = column(row, 2)?;
+                let bytes: Option<f64> = column(row, 3)?;
+                let safe: Option<f64> = column(row, 4)?;
+                let wal_status: String = column(row, 5)?;
+
+                let mut slot_status = if wal_status == "lost" { StatusType::CRITICAL } else { StatusType::OK };
+                let mut details = vec![format!("{}, {}", kind, if active { "active" } else { "inactive" })];
+                if let Some(bytes) = bytes {
+                    slot_status = slot_status.worst(self.thresholds.status(0, bytes));
+                    status.perfdata.push(self.thresholds.perfdata(0, &format!("{}_bytes", name), bytes).uom("B").min(Some(0.0)));
+                    details.push(format!("retains {}", format_bytes(bytes)));
+                    if largest.as_ref().is_none_or(|largest| bytes > largest.1) {
+                        largest = Some((name.clone(), bytes));
+                    }
+                }
+                if let Some(safe) = safe {
+                    slot_status = slot_status.worst(self.thresholds.status(1, safe));
+                    status.perfdata.push(self.thresholds.perfdata(1, &format!("{}_safe", name), safe).uom("B"));
+                    details.push(format!("{} until WAL is removed", format_bytes(safe)));
+                }
+                if !wal_status.is_empty() {
+                    details.push(format!("wal_status {}", wal_status));
+                }
+                status.long_output.push(format!("{} ({})", name, details.join(", ")));
+                if slot_status != StatusType::OK {
+                    status.t = status.t.worst(slot_status);
+                    alerting.push(if wal_status == "lost" { format!("{} (WAL removed)", name) } else { name });
+                }
+            }
+
+            status.description = match largest {
+                Some((name, bytes)) => format!("{} replication slots, {} retains the most WAL ({})", rows.len(), name, format_bytes(bytes)),
+                None => format!("{} replication slots", rows.len()),
+            };
+            if !alerting.is_empty() {
+                status.description += &format!(", {} alerting: {}", alerting.len(), alerting.join(", "));
+            }
+            Ok(status)
+        })
+    }
+}
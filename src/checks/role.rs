@@ -0,0 +1,39 @@
+// The server's role by `pg_is_in_recovery()`. With `--expect primary` or `--expect standby`, the other role is
+// CRITICAL, e.g. a standby that was promoted or a former primary that still accepts writes after a failover.
+
+use super::{Check, Run};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+struct Role {
+    // whether the server is expected to be a standby
+    standby: Option<bool>,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let standby = match options.value_of("expect") {
+        None => None,
+        Some("primary") => Some(false),
+        Some("standby") => Some(true),
+        Some(expect) => return Err(format!("--expect needs to be primary or standby, not '{}'", expect)),
+    };
+    Ok(Box::new(Role { standby }))
+}
+
+impl Check for Role {
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let in_recovery: bool = column(&session.query_one("SELECT pg_is_in_recovery()", &[]).await?, 0)?;
+            let role = if in_recovery { "standby" } else { "primary" };
+            let mut status = match self.standby {
+                Some(standby) if standby != in_recovery =>
+                    Status::new(StatusType::CRITICAL, format!("Server is a {}, expected a {}", role, if standby { "standby" } else { "primary" })),
+                _ => Status::new(StatusType::OK, format!("Server is a {}", role)),
+            };
+            status.perfdata.push(PerfData::new("in_recovery", if in_recovery { 1.0 } else { 0.0 }).min(Some(0.0)).max(Some(1.0)));
+            Ok(status)
+        })
+    }
+}
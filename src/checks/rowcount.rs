@@ -0,0 +1,61 @@
+// The number of rows of the tables given by `--table`, estimated from `reltuples` as of the last VACUUM or ANALYZE,
+// or counted with `--exact`. Counting reads the whole table, so it is cancelled after 10 seconds unless
+// `--statement-timeout` is shorter. Lower bounds like `-c 1:` catch an import that loaded nothing.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use std::time::Duration;
+
+const ESTIMATE: &str = "SELECT c.oid::regclass::text, c.reltuples::float8 FROM pg_class c WHERE c.oid = $1::text::regclass";
+
+const COUNT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Rowcount {
+    tables: Vec<String>,
+    exact: bool,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let tables: Vec<String> = match options.value_of("table") {
+        Some(tables) => tables.split(',').map(|table| table.trim().to_string()).collect(),
+        None => return Err("--check rowcount needs --table".to_string()),
+    };
+    Ok(Box::new(Rowcount { tables, exact: options.is_present("exact"), thresholds: Thresholds::new(options, &["rows"], None, None)? }))
+}
+
+impl Check for Rowcount {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut counts = vec![];
+            for table in &self.tables {
+                let row = session.query_one(ESTIMATE, &[table]).await?;
+                // quoted as needed, the table is known to exist now
+                let name: String = column(&row, 0)?;
+                let rows = if self.exact {
+                    let count = session.query_timeout(&format!("SELECT count(*)::float8 FROM {}", name), &[], COUNT_TIMEOUT).await?;
+                    column::<f64>(&count[0], 0)?
+                } else {
+                    match column::<f64>(&row, 1)? {
+                        // PostgreSQL 14+ reports -1 for tables that were never vacuumed or analyzed
+                        rows if rows < 0.0 => return Err(Status::new(StatusType::UNKNOWN,
+                            format!("No row estimate for {}, it was never analyzed (use --exact to count)", name))),
+                        rows => rows,
+                    }
+                };
+                status.t = status.t.worst(self.thresholds.status(0, rows));
+                status.perfdata.push(self.thresholds.perfdata(0, &name, rows).min(Some(0.0)));
+                counts.push(format!("{} {}", name, rows));
+            }
+            status.description = format!("{} rows: {}", if self.exact { "Counted" } else { "Estimated" }, counts.join(", "));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,78 @@
+// Sequence exhaustion: the percentage of its range every sequence of the current database has used, from
+// `pg_sequences`. A sequence owned by a column, like a serial or identity column, is limited by the column's type
+// too, so a bigint sequence feeding an integer primary key is exhausted at 2^31 - 1. Sequences never used, or not
+// readable by the current user, count as unused.
+
+use super::{top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "
+SELECT name, CASE WHEN increment_by > 0 THEN 100 * (last_value - min_value) / nullif(upper - min_value, 0)
+                  ELSE 100 * (max_value - last_value) / nullif(max_value - lower, 0) END::float8
+FROM (
+  SELECT s.schemaname || '.' || s.sequencename AS name, s.increment_by,
+         coalesce(s.last_value, CASE WHEN s.increment_by > 0 THEN s.min_value ELSE s.max_value END)::numeric AS last_value,
+         s.min_value::numeric, s.max_value::numeric,
+         least(s.max_value, CASE a.atttypid WHEN 'int2'::regtype THEN 32767 WHEN 'int4'::regtype THEN 2147483647 END)::numeric AS upper,
+         greatest(s.min_value, CASE a.atttypid WHEN 'int2'::regtype THEN -32768 WHEN 'int4'::regtype THEN -2147483648 END)::numeric AS lower
+  FROM pg_sequences s
+    JOIN pg_class c ON c.relname = s.sequencename
+    JOIN pg_namespace n ON n.oid = c.relnamespace AND n.nspname = s.schemaname
+    LEFT JOIN pg_depend d ON d.classid = 'pg_class'::regclass AND d.objid = c.oid
+                          AND d.refclassid = 'pg_class'::regclass AND d.deptype IN ('a', 'i')
+    LEFT JOIN pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid
+) AS sequences
+ORDER BY 2 DESC NULLS LAST, 1";
+
+struct Sequences {
+    thresholds: Thresholds,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Sequences { thresholds: Thresholds::new(options, &["percent"], Some("75"), Some("90"))?, top: top(options)? }))
+}
+
+impl Check for Sequences {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(QUERY, &[]).await?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut exhausted = vec![];
+            let mut highest: Option<(String, f64)> = None;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let percent = column::<Option<f64>>(row, 1)?.unwrap_or(0.0);
+                let sequence_status = self.thresholds.status(0, percent);
+                if sequence_status != StatusType::OK {
+                    status.t = status.t.worst(sequence_status);
+                    exhausted.push(format!("{} {}%", name, (percent * 10.0).round() / 10.0));
+                }
+                // rows are ordered by the percentage, so the first one is the highest
+                if highest.is_none() {
+                    highest = Some((name, percent));
+                }
+            }
+
+            status.description = match highest {
+                Some((ref name, percent)) => format!("{} sequences, highest {}% used by {}", rows.len(), (percent * 10.0).round() / 10.0, name),
+                None => "No sequences".to_string(),
+            };
+            if !exhausted.is_empty() {
+                status.description += &format!(", {} above the thresholds", exhausted.len());
+            }
+            status.long_output = exhausted.into_iter().take(self.top).collect();
+            let percent = highest.map(|(_, percent)| (percent * 100.0).round() / 100.0).unwrap_or(0.0);
+            status.perfdata.push(self.thresholds.perfdata(0, "max", percent).uom("%").min(Some(0.0)).max(Some(100.0)));
+            status.perfdata.push(PerfData::new("sequences", rows.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,158 @@
+// Configuration drift: the settings of `pg_settings` compared to the expected values of `--expect`, e.g.
+// `max_connections=500,work_mem=64MB`, and of `--settings-file`, which uses the syntax of postgresql.conf. Values
+// with a memory or time unit are compared in that unit, a value without one is in the setting's unit like in
+// postgresql.conf. Booleans accept every spelling postgres does. A setting differing from its expected value results
+// in `--mismatch-status` (default: critical).
+
+use super::{Check, Run};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use std::fs;
+
+const QUERY: &str = "SELECT lower(name), setting, coalesce(unit, ''), vartype, pending_restart FROM pg_settings \
+                     WHERE lower(name) = ANY($1)";
+
+// postgres' memory units in bytes and time units in milliseconds, largest first for the output
+const MEMORY_UNITS: &[(&str, f64)] = &[("TB", 1099511627776.0), ("GB", 1073741824.0), ("MB", 1048576.0), ("kB", 1024.0), ("B", 1.0)];
+const TIME_UNITS: &[(&str, f64)] = &[("d", 86400000.0), ("h", 3600000.0), ("min", 60000.0), ("s", 1000.0), ("ms", 1.0), ("us", 0.001)];
+
+struct Settings {
+    // setting names in lower case with their expected values
+    expected: Vec<(String, String)>,
+    mismatch_status: StatusType,
+}
+
+// Parses `name = value` lines like postgresql.conf, with `#` comments and optionally quoted values
+fn parse_file(path: &str) -> Result<Vec<(String, String)>, String> {
+    let content = fs::read_to_string(path).map_err(|err| format!("Could not read settings file '{}': {}", path, err))?;
+    let mut settings = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = match line.find(|c: char| c == '=' || c.is_whitespace()) {
+            Some(idx) => (&line[..idx], line[idx..].trim_start().trim_start_matches('=').trim_start()),
+            None => return Err(format!("Invalid line {} in settings file '{}': {}", i + 1, path, line)),
+        };
+        let value = match value.strip_prefix('\'') {
+            Some(quoted) => match quoted.find('\'') {
+                Some(end) => &quoted[..end],
+                None => return Err(format!("Unterminated quote in line {} of settings file '{}'", i + 1, path)),
+            },
+            // the rest of the line is a comment
+            None => value.split('#').next().unwrap_or("").trim(),
+        };
+        settings.push((name.to_lowercase(), value.to_string()));
+    }
+    Ok(settings)
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let mut expected = match options.value_of("settings-file") {
+        Some(path) => parse_file(path)?,
+        None => vec![],
+    };
+    let mut listed = false;
+    for setting in options.value_of("expect").unwrap_or("").split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        match (setting.find('='), expected.last_mut()) {
+            (Some(idx), _) => expected.push((setting[..idx].trim().to_lowercase(), setting[idx + 1..].trim().to_string())),
+            // a list value like `DateStyle=ISO, MDY` continues the previous setting
+            (None, Some(previous)) if listed => previous.1 = format!("{}, {}", previous.1, setting),
+            (None, _) => return Err(format!("Invalid --expect '{}', expected NAME=VALUE", setting)),
+        }
+        listed = true;
+    }
+    if expected.is_empty() {
+        return Err("--check settings needs --expect or --settings-file".to_string());
+    }
+    Ok(Box::new(Settings {
+        expected,
+        // possible values are restricted by clap
+        mismatch_status: options.value_of("mismatch-status").unwrap_or("critical").parse().unwrap(),
+    }))
+}
+
+// The units of a setting's kind and the factor of a unit like `8kB`, `None` for settings without a unit
+fn units(unit: &str) -> Option<(&'static [(&'static str, f64)], f64)> {
+    let digits = unit.find(|c: char| !c.is_ascii_digit()).unwrap_or(unit.len());
+    let count: f64 = if digits == 0 { 1.0 } else { unit[..digits].parse().ok()? };
+    [MEMORY_UNITS, TIME_UNITS].iter()
+        .find_map(|&units| units.iter().find(|&&(name, _)| name == &unit[digits..]).map(|&(_, factor)| (units, count * factor)))
+}
+
+// Parses an expected value with an optional unit of `units`, a value without one is in the setting's unit
+fn parse_value(value: &str, units: &[(&str, f64)], setting_factor: f64) -> Option<f64> {
+    if let Ok(number) = value.parse::<f64>() {
+        return Some(number * setting_factor);
+    }
+    units.iter().find_map(|&(unit, factor)| value.strip_suffix(unit).and_then(|number| number.trim().parse::<f64>().ok()).map(|number| number * factor))
+}
+
+// Formats a value like postgres' SHOW, with the largest unit it is a whole multiple of, e.g. `64MB`
+fn pretty(value: f64, units: &[(&str, f64)]) -> String {
+    match units.iter().find(|&&(_, factor)| (value / factor).fract() == 0.0 && value.abs() >= factor) {
+        Some(&(unit, factor)) => format!("{}{}", value / factor, unit),
+        None => format!("{}{}", value / units[units.len() - 1].1, units[units.len() - 1].0),
+    }
+}
+
+fn boolean(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" | "t" | "y" => Some(true),
+        "off" | "false" | "no" | "0" | "f" | "n" => Some(false),
+        _ => None,
+    }
+}
+
+impl Check for Settings {
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let names: Vec<&str> = self.expected.iter().map(|(name, _)| name.as_str()).collect();
+            let rows = session.query(QUERY, &[&names]).await?;
+
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut differing = vec![];
+            for (name, expected) in &self.expected {
+                let row = match rows.iter().find(|row| column::<String>(row, 0).ok().as_ref() == Some(name)) {
+                    Some(row) => row,
+                    None => return Err(Status::new(StatusType::UNKNOWN, format!("Unknown setting '{}'", name))),
+                };
+                let setting: String = column(row, 1)?;
+                let unit: String = column(row, 2)?;
+                let vartype: String = column(row, 3)?;
+                let pending_restart: bool = column(row, 4)?;
+
+                let (matches, actual) = match (vartype.as_str(), units(&unit)) {
+                    ("bool", _) => (boolean(&setting).is_some() && boolean(&setting) == boolean(expected), setting.clone()),
+                    (_, Some((units, factor))) => {
+                        let actual = setting.parse::<f64>().unwrap_or(f64::NAN) * factor;
+                        let expected = parse_value(expected, units, factor);
+                        // special values like -1 for "disabled" have no unit
+                        let pretty = if actual > 0.0 { pretty(actual, units) } else { setting.clone() };
+                        (expected.is_some_and(|expected| (expected - actual).abs() <= actual.abs() * 1e-9), pretty)
+                    }
+                    ("integer", None) | ("real", None) =>
+                        (expected.parse::<f64>().ok() == setting.parse::<f64>().ok() && setting.parse::<f64>().is_ok(), setting.clone()),
+                    _ => (setting.eq_ignore_ascii_case(expected), setting.clone()),
+                };
+                let pending = if pending_restart { " (changed, pending restart)" } else { "" };
+                status.long_output.push(format!("{} = {}{}", name, actual, pending));
+                if !matches {
+                    differing.push(format!("{} is {}, expected {}", name, actual, expected));
+                }
+            }
+
+            if differing.is_empty() {
+                status.description = format!("All {} settings as expected", self.expected.len());
+            } else {
+                status.t = self.mismatch_status;
+                status.description = format!("{} of {} settings differ: {}", differing.len(), self.expected.len(), differing.join(", "));
+            }
+            status.perfdata.push(PerfData::new("differing", differing.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
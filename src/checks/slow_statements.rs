@@ -0,0 +1,90 @@
+// The slowest statements recorded by the `pg_stat_statements` extension, which has to be installed in the database
+// connected to. The worst mean execution time is compared against the thresholds, the `--top` statements by mean time
+// are listed with their total time and calls. Statements of users matching `--exclude-user` are skipped.
+//
+// The extension's statistics cover the time since they were reset with `pg_stat_statements_reset()`.
+
+use super::{databases, one_line, patterns, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+// the extension's schema and whether it has the column names of version 1.8 (PostgreSQL 13) and later
+const EXTENSION: &str = "SELECT quote_ident(n.nspname), \
+                                EXISTS (SELECT FROM pg_attribute \
+                                        WHERE attrelid = (quote_ident(n.nspname) || '.pg_stat_statements')::regclass \
+                                          AND attname = 'mean_exec_time') \
+                         FROM pg_extension e JOIN pg_namespace n ON n.oid = e.extnamespace \
+                         WHERE e.extname = 'pg_stat_statements'";
+
+struct SlowStatements {
+    databases: Vec<String>,
+    thresholds: Thresholds,
+    exclude_user: Option<RegexSet>,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(SlowStatements {
+        databases: databases(options),
+        thresholds: Thresholds::new(options, &["seconds"], Some("1s"), Some("5s"))?,
+        exclude_user: patterns(options, "exclude-user")?,
+        top: top(options)?,
+    }))
+}
+
+// Seconds with millisecond precision for the output
+fn seconds(seconds: f64) -> f64 {
+    (seconds * 1000.0).round() / 1000.0
+}
+
+impl Check for SlowStatements {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let extension = session.query(EXTENSION, &[]).await?;
+            let (schema, exec_time) = match extension.first() {
+                Some(row) => (column::<String>(row, 0)?, column::<bool>(row, 1)?),
+                None => return Err(Status::new(StatusType::UNKNOWN, "Extension pg_stat_statements is not installed".to_string())),
+            };
+            let (mean, total) = if exec_time { ("mean_exec_time", "total_exec_time") } else { ("mean_time", "total_time") };
+            let query = format!("SELECT coalesce(r.rolname::text, ''), coalesce(d.datname::text, ''), s.calls, \
+                                        (s.{} / 1000)::float8, (s.{} / 1000)::float8, coalesce(s.query, '') \
+                                 FROM {}.pg_stat_statements s \
+                                   LEFT JOIN pg_roles r ON r.oid = s.userid \
+                                   LEFT JOIN pg_database d ON d.oid = s.dbid \
+                                 WHERE cardinality($1::text[]) = 0 OR d.datname = ANY($1) \
+                                 ORDER BY 4 DESC", mean, total, schema);
+
+            let mut statements = vec![];
+            for row in &session.query(&query, &[&self.databases]).await? {
+                let user: String = column(row, 0)?;
+                if self.exclude_user.as_ref().is_some_and(|exclude| exclude.is_match(&user)) {
+                    continue;
+                }
+                statements.push((user, column::<String>(row, 1)?, column::<i64>(row, 2)?, column::<f64>(row, 3)?, column::<f64>(row, 4)?, column::<String>(row, 5)?));
+            }
+
+            // statements are ordered by their mean time, so the first one is the worst
+            let worst = statements.first().map(|statement| statement.3).unwrap_or(0.0);
+            let total = statements.iter().map(|statement| statement.4).fold(0.0, f64::max);
+            let mut status = Status::new(self.thresholds.status(0, worst), match statements.first() {
+                Some(&(_, _, calls, mean, _, ref query)) =>
+                    format!("Worst mean execution time {}s over {} calls: {}", seconds(mean), calls, one_line(query, 60)),
+                None => "No statements recorded".to_string(),
+            });
+            for &(ref user, ref database, calls, mean, total, ref query) in statements.iter().take(self.top) {
+                status.long_output.push(format!("mean {}s, total {}s, {} calls by {} in {}: {}", seconds(mean), seconds(total), calls, user, database, one_line(query, 100)));
+            }
+            status.perfdata.push(self.thresholds.perfdata(0, "mean", seconds(worst)).uom("s").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("total", seconds(total)).uom("s").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("statements", statements.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,85 @@
+// Logical replication lag on a subscriber, for every enabled subscription in `pg_subscription`: the WAL received from
+// the publisher but not applied yet, by the subscription's replication origin, and the time since the publisher last
+// reported its WAL position. The publisher reports it with every message and keepalive, so the time grows when the
+// connection stalls even if nothing is replicated. An enabled subscription without an apply worker is CRITICAL.
+//
+// The origin only advances with replicated transactions, while the received position also follows WAL the publisher
+// skipped, e.g. of other databases. The bytes have no default thresholds for that reason.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+struct SubscriptionLag {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(SubscriptionLag { thresholds: Thresholds::new(options, &["bytes", "seconds"], Some(",1m"), Some(",5m"))? }))
+}
+
+impl Check for SubscriptionLag {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let version: i32 = column(&session.query_one("SELECT current_setting('server_version_num')::int", &[]).await?, 0)?;
+            // parallel apply workers are new in PostgreSQL 16, only the leader reports the subscription's progress
+            let leader = if version >= 160000 { " AND w.leader_pid IS NULL" } else { "" };
+            let query = format!("SELECT s.subname::text, s.subenabled, w.pid IS NOT NULL, \
+                                        pg_wal_lsn_diff(w.received_lsn, o.remote_lsn)::float8, \
+                                        extract(epoch FROM now() - w.latest_end_time)::float8 \
+                                 FROM pg_subscription s \
+                                   LEFT JOIN pg_stat_subscription w ON w.subid = s.oid AND w.relid IS NULL{} \
+                                   LEFT JOIN pg_replication_origin_status o ON o.external_id = 'pg_' || s.oid \
+                                 ORDER BY 1", leader);
+            let rows = session.query(&query, &[]).await?;
+            if rows.is_empty() {
+                return Err(Status::new(StatusType::UNKNOWN, "No subscription found".to_string()));
+            }
+
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut lags = vec![];
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let enabled: bool = column(row, 1)?;
+                let running: bool = column(row, 2)?;
+                if !enabled {
+                    status.long_output.push(format!("{} is disabled", name));
+                    continue;
+                }
+                if !running {
+                    status.t = status.t.worst(StatusType::CRITICAL);
+                    lags.push(format!("{} has no apply worker", name));
+                    continue;
+                }
+
+                let mut lag = vec![];
+                let bytes: Option<f64> = column(row, 3)?;
+                if let Some(bytes) = bytes {
+                    status.t = status.t.worst(self.thresholds.status(0, bytes));
+                    status.perfdata.push(self.thresholds.perfdata(0, &format!("{}_bytes", name), bytes).uom("B"));
+                    lag.push(format_bytes(bytes));
+                }
+                let seconds: Option<f64> = column(row, 4)?;
+                if let Some(seconds) = seconds {
+                    let seconds = (seconds * 1000.0).round() / 1000.0;
+                    status.t = status.t.worst(self.thresholds.status(1, seconds));
+                    status.perfdata.push(self.thresholds.perfdata(1, &format!("{}_seconds", name), seconds).uom("s"));
+                    lag.push(format!("{}s", seconds));
+                }
+                if lag.is_empty() {
+                    lag.push("unknown".to_string());
+                }
+                lags.push(format!("{} lag {}", name, lag.join(", ")));
+            }
+
+            status.description = if lags.is_empty() { "All subscriptions are disabled".to_string() } else { lags.join("; ") };
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,108 @@
+// Table bloat, estimated from the statistics in `pg_stats` like the well-known query of ioguix/pgsql-bloat-estimation:
+// the expected number of pages is derived from the row count and the average row width, everything above it is
+// considered wasted. Tables without statistics or with columns of type `name` cannot be estimated and are skipped.
+//
+// A table alerts only if it is bloated both relatively and absolutely, so small tables do not alert for a high
+// percentage of a few kilobytes.
+
+use super::{patterns, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const QUERY: &str = "
+SELECT schemaname || '.' || tblname,
+       (CASE WHEN tblpages > est_tblpages THEN (tblpages - est_tblpages) * bs ELSE 0 END)::float8,
+       (CASE WHEN tblpages > est_tblpages THEN 100 * (tblpages - est_tblpages) / tblpages ELSE 0 END)::float8
+FROM (
+  SELECT ceil(reltuples / ((bs - page_hdr) / tpl_size)) + ceil(toasttuples / 4) AS est_tblpages,
+         tblpages, bs, schemaname, tblname, is_na
+  FROM (
+    SELECT (4 + tpl_hdr_size + tpl_data_size + (2 * ma)
+            - CASE WHEN tpl_hdr_size % ma = 0 THEN ma ELSE tpl_hdr_size % ma END
+            - CASE WHEN ceil(tpl_data_size)::int % ma = 0 THEN ma ELSE ceil(tpl_data_size)::int % ma END
+           ) AS tpl_size,
+           heappages + toastpages AS tblpages, reltuples, toasttuples, bs, page_hdr, schemaname, tblname, is_na
+    FROM (
+      SELECT ns.nspname AS schemaname, tbl.relname AS tblname, tbl.reltuples,
+             tbl.relpages AS heappages, coalesce(toast.relpages, 0) AS toastpages,
+             coalesce(toast.reltuples, 0) AS toasttuples,
+             current_setting('block_size')::numeric AS bs,
+             CASE WHEN version() ~ 'mingw32|64-bit|x86_64|ppc64|ia64|amd64' THEN 8 ELSE 4 END AS ma,
+             24 AS page_hdr,
+             23 + CASE WHEN max(coalesce(s.null_frac, 0)) > 0 THEN (7 + count(s.attname)) / 8 ELSE 0::int END
+               AS tpl_hdr_size,
+             sum((1 - coalesce(s.null_frac, 0)) * coalesce(s.avg_width, 0)) AS tpl_data_size,
+             bool_or(att.atttypid = 'pg_catalog.name'::regtype)
+               OR sum(CASE WHEN att.attnum > 0 THEN 1 ELSE 0 END) <> count(s.attname) AS is_na
+      FROM pg_attribute AS att
+        JOIN pg_class AS tbl ON att.attrelid = tbl.oid
+        JOIN pg_namespace AS ns ON ns.oid = tbl.relnamespace
+        LEFT JOIN pg_stats AS s ON s.schemaname = ns.nspname AND s.tablename = tbl.relname
+                                AND s.inherited = false AND s.attname = att.attname
+        LEFT JOIN pg_class AS toast ON tbl.reltoastrelid = toast.oid
+      WHERE NOT att.attisdropped AND att.attnum > 0 AND tbl.relkind IN ('r', 'm') AND tbl.reltuples >= 0
+        AND ns.nspname NOT IN ('pg_catalog', 'information_schema') AND ns.nspname !~ '^pg_toast'
+      GROUP BY 1, 2, 3, 4, 5, 6
+    ) AS s
+  ) AS s2
+) AS s3
+WHERE NOT is_na";
+
+struct TableBloat {
+    thresholds: Thresholds,
+    exclude: Option<RegexSet>,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(TableBloat {
+        thresholds: Thresholds::new(options, &["percent", "bytes"], Some("50,100MB"), Some("80,1GB"))?,
+        exclude: patterns(options, "exclude-table")?,
+        top: top(options)?,
+    }))
+}
+
+impl Check for TableBloat {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut total = 0.0;
+            let mut bloated = vec![];
+            for row in &session.query(QUERY, &[]).await? {
+                let name: String = column(row, 0)?;
+                if self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&name)) {
+                    continue;
+                }
+                let bytes: f64 = column(row, 1)?;
+                let percent: f64 = column(row, 2)?;
+                total += bytes;
+                let table_status = self.thresholds.status(0, percent).best(self.thresholds.status(1, bytes));
+                if table_status != StatusType::OK {
+                    status.t = status.t.worst(table_status);
+                    bloated.push((name, bytes, percent));
+                }
+            }
+
+            // the worst offenders by wasted bytes
+            bloated.sort_by(|a, b| b.1.total_cmp(&a.1));
+            status.description = format!("{} wasted in total", format_bytes(total));
+            if !bloated.is_empty() {
+                let offenders: Vec<String> = bloated.iter().take(self.top)
+                    .map(|&(ref name, bytes, percent)| format!("{} {} ({}%)", name, format_bytes(bytes), percent.round()))
+                    .collect();
+                status.description += &format!(", {} bloated tables: {}", bloated.len(), offenders.join(", "));
+            }
+            status.perfdata.push(PerfData::new("wasted", total).uom("B").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("bloated_tables", bloated.len() as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
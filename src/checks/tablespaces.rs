@@ -0,0 +1,72 @@
+// Size of every tablespace, by `pg_tablespace_size()`, with its location. pg_default and pg_global are in the data
+// directory, a tablespace created inside it is WARNING: it is not a separate file system then and is mishandled by
+// base backups. The data directory is only known to roles allowed to read it from `pg_settings`, e.g. members of
+// pg_read_all_settings.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const QUERY: &str = "SELECT spcname::text, pg_tablespace_size(oid)::float8, nullif(pg_tablespace_location(oid), ''), \
+                            (SELECT setting FROM pg_settings WHERE name = 'data_directory') \
+                     FROM pg_tablespace ORDER BY 2 DESC, 1";
+
+struct Tablespaces {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Tablespaces { thresholds: Thresholds::new(options, &["bytes"], None, None)? }))
+}
+
+impl Check for Tablespaces {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(QUERY, &[]).await?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut alerting = vec![];
+            let mut largest: Option<(String, f64)> = None;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let size: f64 = column(row, 1)?;
+                let location: Option<String> = column(row, 2)?;
+                let data_directory: Option<String> = column(row, 3)?;
+                let t = self.thresholds.status(0, size);
+                if t != StatusType::OK {
+                    alerting.push(format!("{} above the thresholds", name));
+                }
+                status.t = status.t.worst(t);
+                let line = match (location, data_directory) {
+                    (None, Some(data_directory)) => format!("{}: {} in the data directory {}", name, format_bytes(size), data_directory),
+                    (None, None) => format!("{}: {} in the data directory", name, format_bytes(size)),
+                    (Some(location), Some(data_directory)) if location.starts_with(&format!("{}/", data_directory.trim_end_matches('/'))) => {
+                        status.t = status.t.worst(StatusType::WARNING);
+                        alerting.push(format!("{} inside the data directory", name));
+                        format!("{}: {} at {}, inside the data directory", name, format_bytes(size), location)
+                    }
+                    (Some(location), _) => format!("{}: {} at {}", name, format_bytes(size), location),
+                };
+                status.long_output.push(line);
+                status.perfdata.push(self.thresholds.perfdata(0, &name, size).uom("B").min(Some(0.0)));
+                // rows are ordered by size, so the first one is the largest
+                if largest.is_none() {
+                    largest = Some((name, size));
+                }
+            }
+            status.description = match largest {
+                Some((ref name, size)) => format!("{} tablespaces, largest {} {}", rows.len(), name, format_bytes(size)),
+                None => "No tablespaces".to_string(),
+            };
+            if !alerting.is_empty() {
+                status.description += &format!(", {}", alerting.join(", "));
+            }
+            Ok(status)
+        })
+    }
+}
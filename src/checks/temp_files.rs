@@ -0,0 +1,60 @@
+// Temporary files written by queries whose sorts or hashes exceed `work_mem`, by the number of files and bytes per
+// second of every database since the previous run. The counters of `pg_stat_database` are kept in the state
+// directory, so the first run only records them.
+
+use super::{databases, require_databases, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const QUERY: &str = "SELECT datname::text, temp_files::float8, temp_bytes::float8 FROM pg_stat_database \
+                     WHERE datname IS NOT NULL AND (cardinality($1::text[]) = 0 OR datname = ANY($1)) ORDER BY 1";
+
+struct TempFiles {
+    databases: Vec<String>,
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(TempFiles { databases: databases(options), thresholds: Thresholds::new(options, &["files", "bytes"], None, None)? }))
+}
+
+impl Check for TempFiles {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let rows = session.query(QUERY, &[&self.databases]).await?;
+            let mut state = session.state("temp-files")?;
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut names = vec![];
+            let mut total: Option<(f64, f64)> = None;
+            for row in &rows {
+                let name: String = column(row, 0)?;
+                let files = state.rate(&format!("{}.files", name), column(row, 1)?);
+                let bytes = state.rate(&format!("{}.bytes", name), column(row, 2)?);
+                if let (Some(files), Some(bytes)) = (files, bytes) {
+                    status.t = status.t.worst(self.thresholds.status(0, files)).worst(self.thresholds.status(1, bytes));
+                    status.perfdata.push(self.thresholds.perfdata(0, &format!("{}_files", name), (files * 1000.0).round() / 1000.0).min(Some(0.0)));
+                    status.perfdata.push(self.thresholds.perfdata(1, &format!("{}_bytes", name), bytes.round()).uom("B").min(Some(0.0)));
+                    status.long_output.push(format!("{} {} files/s, {}/s", name, (files * 1000.0).round() / 1000.0, format_bytes(bytes)));
+                    let sum = total.unwrap_or((0.0, 0.0));
+                    total = Some((sum.0 + files, sum.1 + bytes));
+                }
+                names.push(name);
+            }
+            state.save()?;
+            require_databases(&self.databases, &names)?;
+
+            status.description = match total {
+                Some((files, bytes)) =>
+                    format!("{} temporary files/s, {}/s in {} databases since the previous run", (files * 1000.0).round() / 1000.0, format_bytes(bytes), names.len()),
+                None => "Temporary file counters recorded (first run)".to_string(),
+            };
+            Ok(status)
+        })
+    }
+}
@@ -0,0 +1,80 @@
+// Indexes of the current database that were never scanned since the statistics were reset, by `idx_scan` of
+// `pg_stat_user_indexes`. They cost space and slow every write, so an unused index larger than the thresholds (by
+// default WARNING from 10MB) is worth dropping. Unique indexes and those of constraints are needed even if no query
+// uses them and are skipped. Only meaningful on a server whose statistics cover the usual workload, a standby's
+// queries are not counted on the primary.
+
+use super::{patterns, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_bytes;
+
+const QUERY: &str = "SELECT s.schemaname || '.' || s.relname, s.schemaname || '.' || s.indexrelname, \
+                            pg_relation_size(s.indexrelid)::float8 \
+                     FROM pg_stat_user_indexes s JOIN pg_index i ON i.indexrelid = s.indexrelid \
+                     WHERE s.idx_scan = 0 AND NOT i.indisunique \
+                       AND NOT EXISTS (SELECT FROM pg_constraint c WHERE c.conindid = s.indexrelid) \
+                     ORDER BY 3 DESC, 2";
+
+struct UnusedIndexes {
+    thresholds: Thresholds,
+    include: Option<RegexSet>,
+    exclude: Option<RegexSet>,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(UnusedIndexes {
+        thresholds: Thresholds::new(options, &["bytes"], Some("10MB"), None)?,
+        include: patterns(options, "include-table")?,
+        exclude: patterns(options, "exclude-table")?,
+        top: top(options)?,
+    }))
+}
+
+impl Check for UnusedIndexes {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut unused = 0;
+            let mut total = 0.0;
+            let mut largest: f64 = 0.0;
+            let mut alerting = vec![];
+            for row in &session.query(QUERY, &[]).await? {
+                let table: String = column(row, 0)?;
+                if self.include.as_ref().is_some_and(|include| !include.is_match(&table))
+                    || self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&table)) {
+                    continue;
+                }
+                let index: String = column(row, 1)?;
+                let size: f64 = column(row, 2)?;
+                unused += 1;
+                total += size;
+                largest = largest.max(size);
+                let index_status = self.thresholds.status(0, size);
+                if index_status != StatusType::OK {
+                    status.t = status.t.worst(index_status);
+                    alerting.push(format!("{} on {} {}", index, table, format_bytes(size)));
+                }
+            }
+
+            status.description = format!("{} unused indexes, {}", unused, format_bytes(total));
+            if !alerting.is_empty() {
+                status.description += &format!(", {} above the thresholds", alerting.len());
+            }
+            // rows are ordered by size, so the largest are listed
+            status.long_output = alerting.into_iter().take(self.top).collect();
+            status.perfdata.push(self.thresholds.perfdata(0, "largest", largest).uom("B").min(Some(0.0)));
+            status.perfdata.push(PerfData::new("unused", unused as f64).min(Some(0.0)));
+            status.perfdata.push(PerfData::new("unused_bytes", total).uom("B").min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
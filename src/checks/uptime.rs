@@ -0,0 +1,38 @@
+// Time since the server started, by `pg_postmaster_start_time()`. A lower bound like the default `-w 10m:` flags a
+// restart if the check runs more often than that, e.g. after a crash at night.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::Status;
+
+const QUERY: &str = "SELECT extract(epoch FROM now() - pg_postmaster_start_time())::float8, \
+                            pg_postmaster_start_time()::text, extract(epoch FROM now() - pg_conf_load_time())::float8";
+
+struct Uptime {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Uptime { thresholds: Thresholds::new(options, &["seconds"], Some("10m:"), None)? }))
+}
+
+impl Check for Uptime {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let row = session.query_one(QUERY, &[]).await?;
+            let uptime: f64 = column(&row, 0)?;
+            let started: String = column(&row, 1)?;
+            let config_age: f64 = column(&row, 2)?;
+
+            let mut status = Status::new(self.thresholds.status(0, uptime), format!("Server up for {}s, started {}", uptime.round(), started));
+            status.long_output.push(format!("Configuration loaded {}s ago", config_age.round()));
+            status.perfdata.push(self.thresholds.perfdata(0, "uptime", uptime.round()).uom("s").min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
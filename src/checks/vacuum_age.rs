@@ -0,0 +1,93 @@
+// Time since the tables of the current database were last vacuumed and analyzed, manually or by autovacuum, from
+// `pg_stat_user_tables`. Tables that were never vacuumed or analyzed, e.g. small ones autovacuum has no reason to
+// process, are not compared for that metric.
+
+use super::{patterns, top, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use regex::RegexSet;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+use crate::units::format_age;
+
+const QUERY: &str = "SELECT schemaname || '.' || relname, \
+                            extract(epoch FROM now() - greatest(last_vacuum, last_autovacuum))::float8, \
+                            extract(epoch FROM now() - greatest(last_analyze, last_autoanalyze))::float8 \
+                     FROM pg_stat_user_tables ORDER BY 1";
+
+struct VacuumAge {
+    thresholds: Thresholds,
+    include: Option<RegexSet>,
+    exclude: Option<RegexSet>,
+    top: usize,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(VacuumAge {
+        thresholds: Thresholds::new(options, &["vacuum", "analyze"], Some("1d,1d"), Some("7d,7d"))?,
+        include: patterns(options, "include-table")?,
+        exclude: patterns(options, "exclude-table")?,
+        top: top(options)?,
+    }))
+}
+
+impl Check for VacuumAge {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut oldest: [Option<(String, f64)>; 2] = [None, None];
+            let mut overdue = vec![];
+            let mut tables = 0;
+            for row in &session.query(QUERY, &[]).await? {
+                let name: String = column(row, 0)?;
+                if self.include.as_ref().is_some_and(|include| !include.is_match(&name))
+                    || self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(&name)) {
+                    continue;
+                }
+                tables += 1;
+
+                let mut ages = vec![];
+                let mut table_status = StatusType::OK;
+                for (metric, done) in ["vacuumed", "analyzed"].iter().enumerate() {
+                    let age: Option<f64> = column(row, metric + 1)?;
+                    if let Some(age) = age {
+                        table_status = table_status.worst(self.thresholds.status(metric, age));
+                        if oldest[metric].as_ref().is_none_or(|oldest| age > oldest.1) {
+                            oldest[metric] = Some((name.clone(), age));
+                        }
+                    }
+                    ages.push(match age {
+                        Some(age) => format!("{} {} ago", done, format_age(age)),
+                        None => format!("never {}", done),
+                    });
+                }
+                if table_status != StatusType::OK {
+                    status.t = status.t.worst(table_status);
+                    overdue.push(format!("{} {}", name, ages.join(", ")));
+                }
+            }
+            if (self.include.is_some() || self.exclude.is_some()) && tables == 0 {
+                return Err(Status::new(StatusType::UNKNOWN, "No table matches --include-table and --exclude-table".to_string()));
+            }
+
+            let mut parts = vec![format!("{} tables", tables)];
+            for (metric, label) in ["vacuum", "analyze"].iter().enumerate() {
+                if let Some((ref name, age)) = oldest[metric] {
+                    parts.push(format!("oldest {} {} ago on {}", label, format_age(age), name));
+                    status.perfdata.push(self.thresholds.perfdata(metric, &format!("{}_age", label), age.round()).uom("s").min(Some(0.0)));
+                }
+            }
+            if !overdue.is_empty() {
+                parts.push(format!("{} overdue", overdue.len()));
+            }
+            status.description = parts.join(", ");
+            status.long_output = overdue.into_iter().take(self.top).collect();
+            status.perfdata.push(PerfData::new("tables", tables as f64).min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
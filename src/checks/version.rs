@@ -0,0 +1,71 @@
+// The server's version, by `server_version_num`. A server older than `--min`, e.g. `14.5` for the oldest supported
+// minor release, is CRITICAL, one newer than `--max` WARNING, e.g. `--max 16` after an upgrade nobody planned. A bound
+// compares only as many parts as it has, so `--max 16` allows every 16.x.
+
+use super::{Check, Run};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT current_setting('server_version_num')::int, current_setting('server_version'), version()";
+
+struct Version {
+    min: Option<(String, Vec<u32>)>,
+    max: Option<(String, Vec<u32>)>,
+}
+
+// A version like `14.5` or `9.6.24` as its numeric parts
+fn parse_bound(options: &Options, name: &str) -> Result<Option<(String, Vec<u32>)>, String> {
+    match options.value_of(name) {
+        None => Ok(None),
+        Some(version) => version.split('.').map(|part| part.parse::<u32>()).collect::<Result<Vec<u32>, _>>()
+            .map(|parts| Some((version.to_string(), parts)))
+            .map_err(|_| format!("Invalid --{} '{}', expected a version like 14.5", name, version)),
+    }
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Version { min: parse_bound(options, "min")?, max: parse_bound(options, "max")? }))
+}
+
+// The parts of `server_version_num`: major and minor since 10, before that the first two parts make the major version
+fn parts(version_num: i32) -> Vec<u32> {
+    let version_num = version_num as u32;
+    if version_num >= 100000 {
+        vec![version_num / 10000, version_num % 10000]
+    } else {
+        vec![version_num / 10000, version_num / 100 % 100, version_num % 100]
+    }
+}
+
+impl Check for Version {
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let row = session.query_one(QUERY, &[]).await?;
+            let version_num: i32 = column(&row, 0)?;
+            let server_version: String = column(&row, 1)?;
+            let version: String = column(&row, 2)?;
+            let parts = parts(version_num);
+            // the server's version cut to the parts of the bound
+            let prefix = |bound: &[u32]| parts.iter().copied().take(bound.len()).collect::<Vec<u32>>();
+
+            let mut status = Status::new(StatusType::OK, format!("PostgreSQL {}", server_version));
+            if let Some((ref text, ref min)) = self.min {
+                if prefix(min) < *min {
+                    status.t = StatusType::CRITICAL;
+                    status.description += &format!(", older than {}", text);
+                }
+            }
+            if let Some((ref text, ref max)) = self.max {
+                if prefix(max) > *max {
+                    status.t = status.t.worst(StatusType::WARNING);
+                    status.description += &format!(", newer than {}", text);
+                }
+            }
+            status.long_output.push(version);
+            status.perfdata.push(PerfData::new("version", version_num as f64));
+            Ok(status)
+        })
+    }
+}
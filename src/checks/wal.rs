@@ -0,0 +1,41 @@
+// Number of WAL segments and total size of the files in `pg_wal`, as listed by `pg_ls_waldir()`. WAL piling up beyond
+// `max_wal_size` usually means archiving fails or a replication slot is abandoned.
+
+use super::{Check, Run, Thresholds};
+use crate::options::Options;
+use crate::session::{column, Session};
+use crate::status::Status;
+use crate::units::format_bytes;
+
+const QUERY: &str = "SELECT count(*) FILTER (WHERE name ~ '^[0-9A-F]{24}$'), coalesce(sum(size), 0)::float8, \
+                            current_setting('max_wal_size') \
+                     FROM pg_ls_waldir()";
+
+struct Wal {
+    thresholds: Thresholds,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    Ok(Box::new(Wal { thresholds: Thresholds::new(options, &["segments", "bytes"], None, None)? }))
+}
+
+impl Check for Wal {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let row = session.query_one(QUERY, &[]).await?;
+            let segments = column::<i64>(&row, 0)? as f64;
+            let bytes: f64 = column(&row, 1)?;
+            let max_wal_size: String = column(&row, 2)?;
+
+            let t = self.thresholds.status(0, segments).worst(self.thresholds.status(1, bytes));
+            let mut status = Status::new(t, format!("{} WAL segments, {} in total (max_wal_size {})", segments, format_bytes(bytes), max_wal_size));
+            status.perfdata.push(self.thresholds.perfdata(0, "segments", segments).min(Some(0.0)));
+            status.perfdata.push(self.thresholds.perfdata(1, "size", bytes).uom("B").min(Some(0.0)));
+            Ok(status)
+        })
+    }
+}
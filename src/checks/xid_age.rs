@@ -0,0 +1,70 @@
+// Transaction ID wraparound: the age of `datfrozenxid` of every database, which autovacuum keeps below
+// `autovacuum_freeze_max_age`. The server stops accepting writes when the age approaches 2^31.
+//
+// Thresholds are absolute XIDs, or percentages of `autovacuum_freeze_max_age` if they end in `%`, e.g. `-w 150%`.
+
+use super::{databases, require_databases, Check, Run, Thresholds};
+use crate::options::Options;
+use crate::perfdata::PerfData;
+use crate::session::{column, Session};
+use crate::status::{Status, StatusType};
+
+const QUERY: &str = "SELECT datname::text, age(datfrozenxid)::int8, current_setting('autovacuum_freeze_max_age')::int8 \
+                     FROM pg_database WHERE cardinality($1::text[]) = 0 OR datname = ANY($1) ORDER BY 1";
+
+struct XidAge {
+    databases: Vec<String>,
+    thresholds: Thresholds,
+    percent: bool,
+}
+
+pub fn new(options: &Options) -> Result<Box<dyn Check>, String> {
+    let warn = options.value_of("warn").unwrap_or("1000000000");
+    let crit = options.value_of("critical").unwrap_or("1500000000");
+    let percent = warn.ends_with('%');
+    if crit.ends_with('%') != percent {
+        return Err("--warn and --critical need to be both percentages or both XIDs".to_string());
+    }
+    let thresholds = Thresholds::parse(options, &["age"], Some(warn.trim_end_matches('%')), Some(crit.trim_end_matches('%')))?;
+    Ok(Box::new(XidAge { databases: databases(options), thresholds, percent }))
+}
+
+impl Check for XidAge {
+    fn describe(&self) -> Vec<String> {
+        self.thresholds.describe()
+    }
+
+    fn run<'a>(&'a self, session: &'a mut Session) -> Run<'a> {
+        Box::pin(async move {
+            let mut status = Status::new(StatusType::OK, String::new());
+            let mut names = vec![];
+            let mut oldest: Option<(String, i64, f64)> = None;
+            for row in &session.query(QUERY, &[&self.databases]).await? {
+                let name: String = column(row, 0)?;
+                let age: i64 = column(row, 1)?;
+                let freeze_max_age: i64 = column(row, 2)?;
+                let percent = age as f64 * 100.0 / freeze_max_age as f64;
+                let value = if self.percent { percent } else { age as f64 };
+                let percent = (percent * 10.0).round() / 10.0;
+
+                status.t = status.t.worst(self.thresholds.status(0, value));
+                status.perfdata.push(self.thresholds.perfdata(0, &name, value).uom(if self.percent { "%" } else { "" }).min(Some(0.0)));
+                status.long_output.push(format!("{} {} ({}% of autovacuum_freeze_max_age)", name, age, percent));
+                if oldest.as_ref().is_none_or(|oldest| age > oldest.1) {
+                    oldest = Some((name.clone(), age, percent));
+                }
+                names.push(name);
+            }
+            require_databases(&self.databases, &names)?;
+
+            match oldest {
+                Some((name, age, percent)) => {
+                    status.description = format!("Oldest unfrozen XID in {} is {} transactions old ({}% of autovacuum_freeze_max_age)", name, age, percent);
+                    status.perfdata.insert(0, PerfData::new("max", age as f64).min(Some(0.0)));
+                    Ok(status)
+                }
+                None => Err(Status::new(StatusType::UNKNOWN, "No database found".to_string())),
+            }
+        })
+    }
+}
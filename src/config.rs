@@ -0,0 +1,102 @@
+// Configuration file with connection defaults and named checks, e.g.
+//
+//   [defaults]
+//   db-connection-string = "host=db1 user=nagios"
+//   connect-timeout = 5
+//
+//   [checks.waiting-locks]
+//   query = "SELECT count(*) FROM pg_locks WHERE NOT granted"
+//   warn = "5"
+//   critical = "20"
+//   labels = "waiting"
+//
+// Keys are the long command line options. Lists can also be given as arrays, e.g. `uom = ["s", "%"]`, and flags as
+// booleans. The tables are turned into command line arguments, so they are validated exactly like the command line.
+
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+// Options whose arrays are given as repeated options instead of a comma separated list
+const REPEATED: &[&str] = &["param", "param-type"];
+
+pub struct Config {
+    table: Table,
+}
+
+// Formats a single value like it would be given on the command line
+fn scalar(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref s) => Some(s.clone()),
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Float(f) => Some(f.to_string()),
+        _ => None,
+    }
+}
+
+// Turns a table into `--key=value` arguments
+fn arguments(table: &Table, section: &str) -> Result<Vec<String>, String> {
+    let mut arguments = vec![];
+    for (key, value) in table {
+        let value = match *value {
+            Value::Boolean(true) => {
+                arguments.push(format!("--{}", key));
+                continue;
+            }
+            Value::Boolean(false) => continue,
+            // options given several times on the command line, their values may contain commas
+            Value::Array(ref values) if REPEATED.contains(&key.as_str()) => {
+                for value in values {
+                    match scalar(value) {
+                        Some(value) => arguments.push(format!("--{}={}", key, value)),
+                        None => return Err(format!("Invalid value for '{}' in [{}]", key, section)),
+                    }
+                }
+                continue;
+            }
+            Value::Array(ref values) => values.iter().map(scalar).collect::<Option<Vec<_>>>().map(|values| values.join(",")),
+            ref value => scalar(value),
+        };
+        match value {
+            Some(value) => arguments.push(format!("--{}={}", key, value)),
+            None => return Err(format!("Invalid value for '{}' in [{}]", key, section)),
+        }
+    }
+    Ok(arguments)
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read configuration file '{}': {}", path.display(), err))?;
+        let table = content.parse::<Table>()
+            .map_err(|err| format!("Invalid configuration file '{}': {}", path.display(), err.message()))?;
+        Ok(Config { table })
+    }
+
+    fn section(&self, name: &str) -> Result<Option<&Table>, String> {
+        match self.table.get(name) {
+            None => Ok(None),
+            Some(Value::Table(table)) => Ok(Some(table)),
+            Some(_) => Err(format!("Invalid configuration: '{}' needs to be a table", name)),
+        }
+    }
+
+    // Arguments of the `[defaults]` table
+    pub fn defaults(&self) -> Result<Vec<String>, String> {
+        match self.section("defaults")? {
+            Some(table) => arguments(table, "defaults"),
+            None => Ok(vec![]),
+        }
+    }
+
+    // Arguments of the `[checks.<name>]` table, `None` if there is no such check
+    pub fn check(&self, name: &str) -> Result<Option<Vec<String>>, String> {
+        let check = match self.section("checks")?.and_then(|checks| checks.get(name)) {
+            None => return Ok(None),
+            Some(Value::Table(check)) => check,
+            Some(_) => return Err(format!("Invalid configuration: check '{}' needs to be a table", name)),
+        };
+        arguments(check, &format!("checks.{}", name)).map(Some)
+    }
+}
@@ -0,0 +1,582 @@
+// The connection to the server the checks run on, configured by the connection options. Every run connects anew,
+// retrying a connection that failed to reach the server, and runs the checks in order on the same session. Runs are
+// futures on the caller's tokio runtime, so runs against several servers can be awaited together. A long-running
+// program can keep the sessions in a `Pool` instead, so later runs reuse them.
+
+use crate::aws;
+use crate::azure;
+use crate::checks::{self, Check};
+use crate::conninfo::ConnInfo;
+use crate::encoding::{self, Encoding};
+use crate::gss;
+use crate::http;
+use crate::options::Options;
+use crate::output;
+use crate::perfdata::PerfData;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Client;
+use postgres_openssl::MakeTlsConnector;
+use crate::session::{column, describe, Session};
+use crate::state::StateDir;
+use crate::tls;
+use crate::status::{Status, StatusType};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use regex::RegexSet;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::vault;
+use crate::verbose;
+use crate::watchdog;
+
+pub struct Connection {
+    // the hosts in the order they are tried, with their configuration
+    targets: Vec<(String, tokio_postgres::Config)>,
+    tls: MakeTlsConnector,
+    connect_timeout: Option<Duration>,
+    target: Target,
+    // the Kerberos service name of the servers, for GSSAPI
+    krbsrvname: String,
+    connection_status: StatusType,
+    timeout_status: StatusType,
+    retries: u32,
+    retry_delay: Duration,
+    statement_timeout: Option<(Duration, StatusType)>,
+    encoding: Encoding,
+    state_dir: PathBuf,
+    identity: String,
+    dbname: String,
+    // the connection parameters without passwords, for the debug output
+    redacted: String,
+    // everything a session depends on, sessions of a pool are only reused for the same
+    key: String,
+    // the parameters the connection was made from, for the connections to other databases
+    conninfo: ConnInfo,
+    // with `--all-databases`, the checks run in every database but those matching the patterns
+    all_databases: Option<Option<RegexSet>>,
+    // the login whose password expires, fetched for every connect attempt within `credentials_timeout`
+    login: Option<Login>,
+    credentials_timeout: Option<Duration>,
+}
+
+// Logins with a password that expires. It is fetched when connecting, the modules reuse it until it is about to expire,
+// so a long-running program does not connect with an expired one.
+#[derive(Clone, Debug)]
+enum Login {
+    // the user and password of a Vault secret
+    Vault(vault::Source),
+    // an RDS IAM authentication token for the endpoint, user and region
+    Aws(String, String, String, String),
+    // an Entra ID access token of the identity
+    Azure(azure::Identity),
+}
+
+impl Login {
+    // The user to log in as, if the login has one of its own, and the password
+    fn credentials(&self) -> Result<(Option<String>, String), String> {
+        match *self {
+            Login::Vault(ref source) => source.credentials().map(|credentials| (credentials.user, credentials.password)),
+            Login::Aws(ref host, ref port, ref user, ref region) => Ok((None, aws::auth_token(host, port, user, region)?)),
+            Login::Azure(ref identity) => Ok((None, identity.access_token()?)),
+        }
+    }
+}
+
+// The doubled wait between retries stops growing here
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+// Sessions idle for longer are closed, e.g. those made with credentials that were rotated since
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+// Sessions kept open between runs, by the connection they were made with, and since when they are idle
+#[derive(Default)]
+pub struct Pool {
+    sessions: Mutex<HashMap<String, Vec<(Session, Instant)>>>,
+}
+
+impl Pool {
+    // An idle session for `key`, unless the server closed it meanwhile or it has been idle for too long
+    fn take(&self, key: &str) -> Option<Session> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|err| err.into_inner());
+        let idle = sessions.get_mut(key)?;
+        std::iter::from_fn(|| idle.pop())
+            .filter(|(_, since)| since.elapsed() < IDLE_TIMEOUT)
+            .map(|(session, _)| session)
+            .find(|session| !session.is_closed())
+    }
+
+    fn put(&self, key: &str, session: Session) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|err| err.into_inner());
+        for idle in sessions.values_mut() {
+            idle.retain(|(_, since)| since.elapsed() < IDLE_TIMEOUT);
+        }
+        sessions.retain(|_, idle| !idle.is_empty());
+        sessions.entry(key.to_string()).or_default().push((session, Instant::now()));
+    }
+}
+
+// The kind of server a session is for, like libpq's target_session_attrs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Any,
+    ReadWrite,
+    ReadOnly,
+    Primary,
+    Standby,
+}
+
+impl Target {
+    // Why the server connected to is not one to use, if it is not
+    async fn mismatch(self, client: &Client) -> Result<Option<&'static str>, tokio_postgres::Error> {
+        if self == Target::Any {
+            return Ok(None);
+        }
+        let row = client.query_one("SELECT pg_catalog.pg_is_in_recovery(), \
+                                    pg_catalog.current_setting('transaction_read_only') = 'on'", &[]).await?;
+        let (recovery, read_only): (bool, bool) = (row.get(0), row.get(1));
+        Ok(match self {
+            Target::ReadWrite if read_only => Some("Session is read-only"),
+            Target::ReadOnly if !read_only => Some("Session is not read-only"),
+            Target::Primary if recovery => Some("Server is in hot standby mode"),
+            Target::Standby if !recovery => Some("Server is not in hot standby mode"),
+            _ => None,
+        })
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Target, String> {
+        match s {
+            "any" => Ok(Target::Any),
+            "read-write" => Ok(Target::ReadWrite),
+            "read-only" => Ok(Target::ReadOnly),
+            "primary" => Ok(Target::Primary),
+            "standby" => Ok(Target::Standby),
+            _ => Err(format!("Invalid target_session_attrs '{}'", s)),
+        }
+    }
+}
+
+enum ConnectError {
+    Timeout(Duration),
+    Postgres(tokio_postgres::Error),
+    // the server is not of the target's kind
+    Target(&'static str),
+    // the plugin's own GSSAPI login failed
+    Gss(String),
+}
+
+// Connects with DNS resolution, TCP connect, the startup handshake and checking the kind of server all bounded by
+// `timeout`. The connection is driven by a task of its own until the client is dropped.
+async fn connect(config: &tokio_postgres::Config, tls: MakeTlsConnector, timeout: Option<Duration>, target: Target,
+                 krbsrvname: &str) -> Result<Client, ConnectError> {
+    let connect = async {
+        let client = match config.connect(tls.clone()).await {
+            Ok((client, connection)) => {
+                tokio::spawn(connection);
+                client
+            }
+            // the driver cannot log in with GSSAPI or SSPI, the plugin connects again and does it itself
+            Err(err) if describe(&err).ends_with("unsupported authentication method") => {
+                verbose::log(1, || "Logging in with GSSAPI".to_string());
+                gss::connect(config, tls, krbsrvname).await.map_err(ConnectError::Gss)?
+            }
+            Err(err) => return Err(ConnectError::Postgres(err)),
+        };
+        match target.mismatch(&client).await.map_err(ConnectError::Postgres)? {
+            Some(reason) => Err(ConnectError::Target(reason)),
+            None => Ok(client),
+        }
+    };
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connect).await.unwrap_or(Err(ConnectError::Timeout(timeout))),
+        None => connect.await,
+    }
+}
+
+// Raises sslmode to at least `require` for a login that needs TLS
+fn require_tls(conninfo: &mut ConnInfo, option: &str) -> Result<(), String> {
+    match conninfo.get("sslmode") {
+        Some("disable") => Err(format!("{} needs TLS, it cannot be used with sslmode disable", option)),
+        Some("require") | Some("verify-ca") | Some("verify-full") => Ok(()),
+        _ => conninfo.set("sslmode", "require"),
+    }
+}
+
+// Everything a session depends on, sessions of a pool are only reused for the same
+fn key(conninfo: &ConnInfo, login: Option<&Login>, statement_timeout: Option<(Duration, StatusType)>, state_dir: &Path) -> String {
+    format!("{}\0{:?}\0{:?}\0{:?}\0{:?}\0{}", conninfo.redacted(), conninfo.get("password"), conninfo.get("sslpassword"),
+            login, statement_timeout, state_dir.display())
+}
+
+impl Connection {
+    pub fn new(options: &Options) -> Result<Connection, String> {
+        let connection_string = options.value_of("db-connection-string").unwrap_or("");
+
+        // possible values are restricted by clap, a timeout is a connection error unless it has a status of its own
+        let connection_status: StatusType = options.value_of("on-connection-error").unwrap_or("unknown").parse().unwrap();
+        let timeout_status: StatusType = options.value_of("on-connect-timeout").map(|t| t.parse().unwrap()).unwrap_or(connection_status);
+
+        // a failed connection is retried after `--retry-delay`, doubled for every further attempt
+        let retries: u32 = match options.value_of("retries").map(|r| r.parse::<u32>()) {
+            None => 0,
+            Some(Ok(retries)) => retries,
+            Some(Err(_)) => return Err("Retries need to be a non-negative integer".to_string()),
+        };
+        let retry_delay = match options.value_of("retry-delay").map(|d| d.parse::<u64>()) {
+            None => Duration::from_millis(1000),
+            Some(Ok(delay)) => Duration::from_millis(delay),
+            Some(Err(_)) => return Err("Retry delay needs to be a non-negative number of milliseconds".to_string()),
+        };
+
+        let statement_timeout: Option<Duration> = match options.value_of("statement-timeout").map(|t| t.parse::<f64>()) {
+            None => None,
+            Some(Ok(t)) if t > 0.0 => Some(Duration::from_secs_f64(t)),
+            Some(_) => return Err("Statement timeout needs to be a positive number of seconds".to_string()),
+        };
+        // possible values are restricted by clap
+        let statement_timeout_status: StatusType = options.value_of("on-statement-timeout").unwrap_or("unknown").parse().unwrap();
+
+        // Options take precedence over the connection string, which takes precedence over the environment
+        let mut conninfo = ConnInfo::parse(connection_string)?;
+        let hosts = options.values_of("host");
+        if !hosts.is_empty() {
+            conninfo.set("host", &hosts.join(",")).unwrap();
+        }
+        for &(arg, key) in &[("sslmode", "sslmode"), ("sslcert", "sslcert"), ("sslkey", "sslkey"), ("sslpassword", "sslpassword"),
+                             ("sslrootcert", "sslrootcert"), ("sslcrl", "sslcrl"), ("connect-timeout", "connect_timeout"),
+                             ("client-encoding", "client_encoding"), ("target", "target_session_attrs"),
+                             ("krb-srvname", "krbsrvname")] {
+            if let Some(value) = options.value_of(arg) {
+                conninfo.set(key, value).unwrap();
+            }
+        }
+        // Passwords in the connection string are visible in the process list, so they can also come from a file
+        if let Some(path) = options.value_of("password-file") {
+            match std::fs::read_to_string(path) {
+                Ok(password) => conninfo.set_default("password", password.trim_end_matches(['\r', '\n'])).unwrap(),
+                Err(err) => return Err(format!("Could not read password file '{}': {}", path, err)),
+            }
+        }
+        // The credentials of Vault or a cloud provider are fetched within `--timeout`, which bounds the whole run
+        let credentials_timeout = match options.value_of("timeout").map(|t| t.parse::<f64>()) {
+            Some(Ok(t)) if t > 0.0 => Some(Duration::from_secs_f64(t)),
+            _ => None,
+        };
+        // Credentials from Vault replace those of the connection string, they are the point of giving `--vault-path`
+        let mut login = vault::source(options)?.map(Login::Vault);
+        conninfo.apply_environment()?;
+        // RDS IAM and Entra ID authentication replace the password by a token, which is only accepted over TLS
+        if options.is_present("aws-iam-auth") {
+            require_tls(&mut conninfo, "--aws-iam-auth")?;
+            // a token is only valid for the endpoint it was made for, the first one is used
+            let host = conninfo.host().split(',').next().unwrap_or("").to_string();
+            let port = conninfo.get("port").and_then(|ports| ports.split(',').next()).unwrap_or("5432").to_string();
+            let user = conninfo.get("user").ok_or("--aws-iam-auth needs a user")?.to_string();
+            let region = aws::region(options.value_of("aws-region"), &host)?;
+            login = Some(Login::Aws(host, port, user, region));
+        }
+        if options.is_present("azure-ad-auth") {
+            require_tls(&mut conninfo, "--azure-ad-auth")?;
+            login = Some(Login::Azure(azure::identity(options)?));
+        }
+        conninfo.apply_passfile()?;
+        let state_dir = PathBuf::from(options.value_of("state-dir").unwrap_or("/var/tmp/check_postgresql"));
+        let statement_timeout = statement_timeout.map(|timeout| (timeout, statement_timeout_status));
+        let key = key(&conninfo, login.as_ref(), statement_timeout, &state_dir);
+
+        let all_databases = match options.is_present("all-databases") {
+            true => Some(checks::patterns(options, "exclude-database")?),
+            false => None,
+        };
+
+        let mut tls = conninfo.tls_config()?;
+        tls.hostname = options.value_of("ssl-hostname").map(|hostname| hostname.to_string());
+
+        Ok(Connection {
+            targets: conninfo.configs()?,
+            tls: tls.connector()?,
+            connect_timeout: conninfo.connect_timeout()?,
+            target: conninfo.target()?,
+            krbsrvname: conninfo.krbsrvname().to_string(),
+            connection_status,
+            timeout_status,
+            retries,
+            retry_delay,
+            statement_timeout,
+            encoding: conninfo.client_encoding()?,
+            state_dir,
+            identity: conninfo.identity(),
+            dbname: conninfo.dbname().to_string(),
+            redacted: conninfo.redacted(),
+            key,
+            conninfo,
+            all_databases,
+            login,
+            credentials_timeout,
+        })
+    }
+
+    // The connection parameters, with passwords redacted
+    pub fn redacted(&self) -> &str {
+        &self.redacted
+    }
+
+    // The hosts connected to, separated by commas if there are several
+    pub fn host(&self) -> &str {
+        self.conninfo.host()
+    }
+
+    pub fn dbname(&self) -> &str {
+        &self.dbname
+    }
+
+    // Revokes the leased credentials fetched so far, before the end of a run that does not connect again. Revoking
+    // stops at `deadline`.
+    pub fn revoke(&self, deadline: Option<Instant>) {
+        if let Some(Login::Vault(_)) = self.login {
+            http::deadline(deadline, vault::revoke);
+        }
+    }
+
+    // The hosts to try with their configuration, with the current password of the login. Fetching it blocks, so it
+    // runs on a thread of its own.
+    async fn targets(&self) -> Result<Vec<(String, tokio_postgres::Config)>, Status> {
+        let login = match self.login {
+            Some(ref login) => login.clone(),
+            None => return Ok(self.targets.clone()),
+        };
+        watchdog::phase("credentials");
+        let deadline = self.credentials_timeout.map(|timeout| Instant::now() + timeout);
+        let (user, password) = tokio::task::spawn_blocking(move || http::deadline(deadline, || login.credentials())).await
+            .unwrap_or_else(|err| Err(format!("Could not fetch the credentials: {}", err)))
+            .map_err(|err| Status::new(StatusType::UNKNOWN, err))?;
+        let mut targets = self.targets.clone();
+        for (_, config) in targets.iter_mut() {
+            if let Some(ref user) = user {
+                config.user(user);
+            }
+            config.password(&password);
+        }
+        Ok(targets)
+    }
+
+    // Connects to the first host that accepts the connection, returns the session and the number of retries it took
+    pub async fn session(&self) -> Result<(Session, u32), Status> {
+        let mut delay = self.retry_delay;
+        let mut attempt = 0;
+        let (conn, host) = 'attempts: loop {
+            let targets = self.targets().await?;
+            watchdog::phase("connect");
+            verbose::log(1, || format!("Connecting to {}", self.redacted));
+            // why each host failed and whether that was a timeout
+            let mut failures: Vec<(&str, bool, String)> = vec![];
+            let mut transient = false;
+            for (host, config) in &targets {
+                if self.targets.len() > 1 {
+                    verbose::log(1, || format!("Trying {}", host));
+                }
+                let start = Instant::now();
+                let (err, rejected) = match tls::verifying(connect(config, self.tls.clone(), self.connect_timeout, self.target, &self.krbsrvname)).await {
+                    (Ok(conn), _) => {
+                        verbose::log(1, || format!("Connected to {} in {:.3}s", host, start.elapsed().as_secs_f64()));
+                        break 'attempts (conn, host.clone())
+                    }
+                    (Err(err), rejected) => (err, rejected),
+                };
+                // the server rejecting the login or its certificate being rejected will be so again, only failures to
+                // reach it and finding a standby where a primary is needed, e.g. during a failover, are retried
+                transient |= match err {
+                    ConnectError::Timeout(_) | ConnectError::Target(_) => true,
+                    ConnectError::Postgres(_) if rejected.is_some() => false,
+                    ConnectError::Gss(_) => false,
+                    ConnectError::Postgres(ref err) => err.as_db_error().is_none() || err.code() == Some(&SqlState::CANNOT_CONNECT_NOW),
+                };
+                failures.push(match err {
+                    ConnectError::Timeout(timeout) => (host, true, format!("Connection timed out after {}s", timeout.as_secs_f64())),
+                    ConnectError::Postgres(err) => (host, false, rejected.unwrap_or_else(|| describe(&err))),
+                    ConnectError::Target(reason) => (host, false, reason.to_string()),
+                    ConnectError::Gss(description) => (host, false, rejected.unwrap_or(description)),
+                });
+            }
+            if attempt >= self.retries || !transient {
+                let retried = if attempt > 0 { format!(" (after {} retries)", attempt) } else { String::new() };
+                let t = if failures.iter().all(|&(_, timeout, _)| timeout) { self.timeout_status } else { self.connection_status };
+                let description = match failures.as_slice() {
+                    [(_, _, description)] => description.clone(),
+                    _ => failures.iter().map(|(host, _, description)| format!("{}: {}", host, description)).collect::<Vec<_>>().join("; "),
+                };
+                return Err(Status::new(t, format!("{}{}", description, retried)));
+            }
+            verbose::log(1, || format!("Connection failed, retrying in {}ms", delay.as_millis()));
+            watchdog::phase("retry delay");
+            tokio::time::sleep(delay).await;
+            delay = delay.saturating_mul(2).min(MAX_RETRY_DELAY);
+            attempt += 1;
+        };
+        watchdog::phase("session setup");
+        let session = Session::new(conn, self.statement_timeout, self.encoding, StateDir::new(&self.state_dir, &self.identity), &host, &self.dbname).await?;
+        Ok((session, attempt))
+    }
+
+    // The same connection to another database of the server, for `--all-databases`
+    fn database(&self, dbname: &str) -> Result<Connection, String> {
+        let mut conninfo = self.conninfo.clone();
+        conninfo.set("dbname", dbname)?;
+        Ok(Connection {
+            targets: conninfo.configs()?,
+            tls: self.tls.clone(),
+            connect_timeout: self.connect_timeout,
+            target: self.target,
+            krbsrvname: self.krbsrvname.clone(),
+            connection_status: self.connection_status,
+            timeout_status: self.timeout_status,
+            retries: self.retries,
+            retry_delay: self.retry_delay,
+            statement_timeout: self.statement_timeout,
+            encoding: self.encoding,
+            state_dir: self.state_dir.clone(),
+            identity: conninfo.identity(),
+            dbname: conninfo.dbname().to_string(),
+            redacted: conninfo.redacted(),
+            key: key(&conninfo, self.login.as_ref(), self.statement_timeout, &self.state_dir),
+            conninfo,
+            all_databases: None,
+            login: self.login.clone(),
+            credentials_timeout: self.credentials_timeout,
+        })
+    }
+
+    // A session of `pool` if there is an idle one, otherwise a new one, with the number of retries it took
+    async fn take(&self, pool: Option<&Pool>) -> Result<(Session, u32), Status> {
+        match pool.and_then(|pool| pool.take(&self.key)) {
+            Some(session) => {
+                verbose::log(1, || format!("Reusing the session to {}", self.redacted));
+                Ok((session, 0))
+            }
+            None => self.session().await,
+        }
+    }
+
+    // Connects and runs every check on the same session. A check that could not be evaluated has its error as result,
+    // only failing to connect is an error of the run.
+    pub async fn run(&self, checks: &[(String, Box<dyn Check>)]) -> Result<Vec<(String, Status)>, Status> {
+        self.run_on(None, checks).await
+    }
+
+    // Like `run`, but on a session of `pool` if there is an idle one, which is returned to it afterwards. A run that is
+    // abandoned, e.g. because it timed out, closes its session.
+    pub async fn run_pooled(&self, pool: &Pool, checks: &[(String, Box<dyn Check>)]) -> Result<Vec<(String, Status)>, Status> {
+        self.run_on(Some(pool), checks).await
+    }
+
+    async fn run_on(&self, pool: Option<&Pool>, checks: &[(String, Box<dyn Check>)]) -> Result<Vec<(String, Status)>, Status> {
+        let start = Instant::now();
+        let (mut session, retries) = self.take(pool).await?;
+        let connect_time = start.elapsed();
+        let exclude = match self.all_databases {
+            Some(ref exclude) => exclude,
+            None => {
+                let mut results = run_checks(&mut session, retries, self.targets.len() > 1, checks).await;
+                if let Some(pool) = pool {
+                    pool.put(&self.key, session);
+                }
+                push_connect_time(&mut results, connect_time);
+                return Ok(results);
+            }
+        };
+
+        // every check's results of all databases are combined like those of several checks
+        let databases = list_databases(&mut session, exclude.as_ref()).await?;
+        let mut first = Some((session, retries));
+        let mut connect_time = connect_time;
+        let mut by_check: Vec<Vec<(String, Status)>> = checks.iter().map(|_| vec![]).collect();
+        for database in databases {
+            let connection = self.database(&database).map_err(|err| Status::new(StatusType::UNKNOWN, err))?;
+            let start = Instant::now();
+            let session = match first.take_if(|_| database == self.dbname) {
+                Some(first) => Ok(first),
+                None => connection.take(pool).await,
+            };
+            connect_time += start.elapsed();
+            // a database that cannot be connected to has the error as the result of every check
+            let results: Vec<Status> = match session {
+                Ok((mut session, retries)) => {
+                    let results = run_checks(&mut session, retries, self.targets.len() > 1, checks).await;
+                    if let Some(pool) = pool {
+                        pool.put(&connection.key, session);
+                    }
+                    results.into_iter().map(|(_, status)| status).collect()
+                }
+                Err(status) => checks.iter().map(|_| Status::new(status.t, status.description.clone())).collect(),
+            };
+            for (results_of_check, status) in by_check.iter_mut().zip(results) {
+                results_of_check.push((database.clone(), status));
+            }
+        }
+        if let (Some(pool), Some((session, _))) = (pool, first) {
+            pool.put(&self.key, session);
+        }
+        let mut results: Vec<(String, Status)> = checks.iter().zip(by_check).map(|((name, _), results)| (name.clone(), output::combine(results))).collect();
+        push_connect_time(&mut results, connect_time);
+        Ok(results)
+    }
+}
+
+// The databases of `--all-databases`: all that accept connections but templates and those matching `exclude`
+async fn list_databases(session: &mut Session, exclude: Option<&RegexSet>) -> Result<Vec<String>, Status> {
+    let rows = session.query("SELECT datname::text FROM pg_database WHERE datallowconn AND NOT datistemplate ORDER BY 1", &[]).await?;
+    let mut databases = vec![];
+    for row in &rows {
+        let database: String = column(row, 0)?;
+        if !exclude.is_some_and(|exclude| exclude.is_match(&database)) {
+            databases.push(database);
+        }
+    }
+    if databases.is_empty() {
+        return Err(Status::new(StatusType::UNKNOWN, "No database to check, all are excluded".to_string()));
+    }
+    Ok(databases)
+}
+
+// The first check has the time it took to connect
+fn push_connect_time(results: &mut [(String, Status)], connect_time: Duration) {
+    if let Some((_, status)) = results.first_mut() {
+        status.perfdata.push(PerfData::new("connect_time", output::seconds(connect_time)).uom("s").min(Some(0.0)));
+    }
+}
+
+// Runs every check in order, a check's failure is its result. With several hosts, the output tells which one it was.
+// Every check has its run time as `query_time`, or the one it reports itself. The timings' labels are the plugin's, a
+// check's perfdata of the same label is suffixed.
+async fn run_checks(session: &mut Session, retries: u32, failover: bool, checks: &[(String, Box<dyn Check>)]) -> Vec<(String, Status)> {
+    let mut results: Vec<(String, Status)> = vec![];
+    for (name, check) in checks {
+        let start = Instant::now();
+        watchdog::phase(&format!("check {}", name));
+        let mut status = encoding::scope(session.encoding(), check.run(session)).await.unwrap_or_else(|status| status);
+        verbose::log(1, || format!("Check {}: {} - {} in {:.3}s", name, status.t, status.description, start.elapsed().as_secs_f64()));
+        let elapsed = start.elapsed();
+        let labels = output::unique_names(status.perfdata.iter().map(|p| p.label.clone()), &["query_time", "connect_time", "total_time"]);
+        for (p, label) in status.perfdata.iter_mut().zip(labels) {
+            p.label = label;
+        }
+        let query_time = status.query_time.take().map(|p| *p).unwrap_or_else(|| PerfData::new("query_time", output::seconds(elapsed)).uom("s").min(Some(0.0)));
+        status.perfdata.push(query_time);
+        results.push((name.clone(), status));
+    }
+    if (retries > 0 || failover) && !results.is_empty() {
+        let mut note = "Connected".to_string();
+        if failover {
+            note += &format!(" to {}", session.host());
+        }
+        if retries > 0 {
+            note += &format!(" after {} retries", retries);
+        }
+        results[0].1.long_output.push(note);
+    }
+    results
+}
@@ -0,0 +1,434 @@
+// Connection parameters in the formats libpq understands:
+//
+//   postgresql://[user[:password]@][host][:port][,...][/dbname][?param=value&...]   (also postgres://)
+//   host=localhost port=5432 dbname='my db' connect_timeout=10
+//
+// For backwards compatibility, a URI without the scheme (`user[:password]@host[:port][/database]`) is accepted too.
+// Parameters missing from the connection string are taken from libpq's environment variables and the password from
+// the password file.
+
+use crate::connection::Target;
+use crate::encoding::Encoding;
+use crate::pgpass;
+use tokio_postgres::config::{ChannelBinding, Config};
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::tls::{SslMode, TlsConfig};
+
+// Supported parameters and the environment variables they default to
+const PARAMETERS : &[(&str, &str)] = &[
+    ("host", "PGHOST"),
+    ("hostaddr", "PGHOSTADDR"),
+    ("port", "PGPORT"),
+    ("dbname", "PGDATABASE"),
+    ("user", "PGUSER"),
+    ("password", "PGPASSWORD"),
+    ("passfile", "PGPASSFILE"),
+    ("connect_timeout", "PGCONNECT_TIMEOUT"),
+    ("application_name", "PGAPPNAME"),
+    ("options", "PGOPTIONS"),
+    ("client_encoding", "PGCLIENTENCODING"),
+    ("target_session_attrs", "PGTARGETSESSIONATTRS"),
+    ("keepalives", ""),
+    ("keepalives_idle", ""),
+    ("sslmode", "PGSSLMODE"),
+    ("sslcert", "PGSSLCERT"),
+    ("sslkey", "PGSSLKEY"),
+    ("sslpassword", ""),
+    ("sslrootcert", "PGSSLROOTCERT"),
+    ("sslcrl", "PGSSLCRL"),
+    ("sslsni", "PGSSLSNI"),
+    ("channel_binding", "PGCHANNELBINDING"),
+    ("krbsrvname", "PGKRBSRVNAME"),
+];
+
+#[derive(Clone, Debug, Default)]
+pub struct ConnInfo {
+    params: BTreeMap<String, String>,
+}
+
+fn percent_decode(s: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid percent-encoding in '{}'", s);
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3).ok_or_else(invalid)?;
+            decoded.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| invalid())
+}
+
+// Splits `host[:port]` or `[v6addr][:port]`
+fn split_host_port(s: &str) -> Result<(String, Option<String>), String> {
+    let (host, port) = if let Some(rest) = s.strip_prefix('[') {
+        let end = rest.find(']').ok_or_else(|| format!("Invalid host '{}'", s))?;
+        let port = &rest[end + 1..];
+        (&rest[..end], port.strip_prefix(':'))
+    } else {
+        match s.rfind(':') {
+            Some(pos) => (&s[..pos], Some(&s[pos + 1..])),
+            None => (s, None),
+        }
+    };
+    Ok((percent_decode(host)?, port.filter(|p| !p.is_empty()).map(|p| p.to_string())))
+}
+
+impl ConnInfo {
+    pub fn parse(s: &str) -> Result<ConnInfo, String> {
+        let s = s.trim();
+        let mut conninfo = ConnInfo::default();
+        if s.is_empty() {
+            return Ok(conninfo);
+        }
+        for scheme in &["postgresql://", "postgres://"] {
+            if let Some(uri) = s.strip_prefix(scheme) {
+                conninfo.parse_uri(uri)?;
+                return Ok(conninfo);
+            }
+        }
+        match s.find('=') {
+            Some(pos) if s[..pos].trim().chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => conninfo.parse_keywords(s)?,
+            _ => conninfo.parse_uri(s)?,
+        }
+        Ok(conninfo)
+    }
+
+    fn parse_uri(&mut self, uri: &str) -> Result<(), String> {
+        let (uri, query) = match uri.find('?') {
+            Some(pos) => (&uri[..pos], Some(&uri[pos + 1..])),
+            None => (uri, None),
+        };
+        // passwords may contain unencoded `/`, the user info ends at the last `@`
+        let (userinfo, rest) = match uri.rfind('@') {
+            Some(pos) => (Some(&uri[..pos]), &uri[pos + 1..]),
+            None => (None, uri),
+        };
+        let (hosts, dbname) = match rest.find('/') {
+            Some(pos) => (&rest[..pos], Some(&rest[pos + 1..])),
+            None => (rest, None),
+        };
+
+        if let Some(userinfo) = userinfo {
+            let (user, password) = match userinfo.find(':') {
+                Some(pos) => (&userinfo[..pos], Some(&userinfo[pos + 1..])),
+                None => (userinfo, None),
+            };
+            if !user.is_empty() {
+                self.set("user", &percent_decode(user)?)?;
+            }
+            if let Some(password) = password {
+                self.set("password", &percent_decode(password)?)?;
+            }
+        }
+
+        if !hosts.is_empty() {
+            let mut host_list = vec![];
+            let mut port_list = vec![];
+            for host in hosts.split(',') {
+                let (host, port) = split_host_port(host)?;
+                host_list.push(host);
+                port_list.push(port);
+            }
+            if host_list.iter().any(|h| !h.is_empty()) {
+                self.set("host", &host_list.join(","))?;
+            }
+            if port_list.iter().any(|p| p.is_some()) {
+                let ports : Vec<String> = port_list.into_iter().map(|p| p.unwrap_or_else(|| "5432".to_string())).collect();
+                self.set("port", &ports.join(","))?;
+            }
+        }
+        if let Some(dbname) = dbname.filter(|d| !d.is_empty()) {
+            self.set("dbname", &percent_decode(dbname)?)?;
+        }
+
+        for pair in query.unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = match pair.find('=') {
+                Some(pos) => (&pair[..pos], &pair[pos + 1..]),
+                None => return Err(format!("Invalid connection parameter '{}'", pair)),
+            };
+            self.set(&percent_decode(key)?, &percent_decode(value)?)?;
+        }
+        Ok(())
+    }
+
+    // `key = value` pairs separated by whitespace. Values may be single quoted, backslash escapes `'` and `\`.
+    fn parse_keywords(&mut self, s: &str) -> Result<(), String> {
+        let mut chars = s.chars().peekable();
+        loop {
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+            if chars.peek().is_none() {
+                return Ok(());
+            }
+
+            let mut key = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == '=' || c.is_whitespace() {
+                    break;
+                }
+                key.push(c);
+                chars.next();
+            }
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+            if chars.next() != Some('=') {
+                return Err(format!("Missing '=' after '{}' in connection string", key));
+            }
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+
+            let mut value = String::new();
+            if chars.peek() == Some(&'\'') {
+                chars.next();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => value.extend(chars.next()),
+                        Some(c) => value.push(c),
+                        None => return Err(format!("Unterminated quoted value for '{}' in connection string", key)),
+                    }
+                }
+            } else {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    chars.next();
+                    match c {
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            }
+            self.set(&key, &value)?;
+        }
+    }
+
+    // The parameters as `key=value`, for the debug output. Passwords are left out.
+    pub fn redacted(&self) -> String {
+        let params: Vec<String> = self.params.iter()
+            .map(|(key, value)| if key == "password" || key == "sslpassword" { format!("{}=<redacted>", key) } else { format!("{}={}", key, value) })
+            .collect();
+        params.join(" ")
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params.get(key).map(|v| v.as_str())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if !PARAMETERS.iter().any(|&(name, _)| name == key) {
+            return Err(format!("Unsupported connection parameter '{}'", key));
+        }
+        self.params.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    // Sets `key` unless it is already set
+    pub fn set_default(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if self.get(key).is_none() {
+            self.set(key, value)?;
+        }
+        Ok(())
+    }
+
+    // Fills in missing parameters from libpq's environment variables
+    pub fn apply_environment(&mut self) -> Result<(), String> {
+        for &(key, var) in PARAMETERS.iter().filter(|&&(_, var)| !var.is_empty()) {
+            if let Some(value) = env::var(var).ok().filter(|v| !v.is_empty()) {
+                self.set_default(key, &value)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Looks up a missing password for the first host in the password file
+    pub fn apply_passfile(&mut self) -> Result<(), String> {
+        if self.get("password").is_some() {
+            return Ok(());
+        }
+        let path = match self.get("passfile") {
+            Some(path) => PathBuf::from(path),
+            None => match pgpass::default_path() {
+                Some(path) => path,
+                None => return Ok(()),
+            },
+        };
+        let host = match self.get("host").and_then(|h| h.split(',').next()) {
+            // libpq matches unix sockets as localhost
+            Some(host) if !host.starts_with('/') => host.to_string(),
+            _ => "localhost".to_string(),
+        };
+        let port = match self.get("port").and_then(|p| p.split(',').next()) {
+            Some(port) => port.parse().map_err(|_| format!("Invalid port '{}'", port))?,
+            None => 5432,
+        };
+        let user = self.get("user").unwrap_or("").to_string();
+        let target = pgpass::Target {
+            host: &host,
+            port,
+            database: self.get("dbname").unwrap_or(&user),
+            user: &user,
+        };
+        if let Some(password) = pgpass::lookup(&path, &target) {
+            self.set("password", &password)?;
+        }
+        Ok(())
+    }
+
+    // Identifies the server and database, e.g. `db1,db2:5432/app`
+    pub fn identity(&self) -> String {
+        format!("{}:{}/{}", self.host(), self.get("port").unwrap_or("5432"), self.dbname())
+    }
+
+    // The host connected to, like libpq without a socket directory
+    pub fn host(&self) -> &str {
+        self.get("host").unwrap_or("localhost")
+    }
+
+    // The database connected to, like libpq it defaults to the user name
+    pub fn dbname(&self) -> &str {
+        self.get("dbname").or_else(|| self.get("user")).unwrap_or("")
+    }
+
+    pub fn connect_timeout(&self) -> Result<Option<Duration>, String> {
+        match self.get("connect_timeout").map(|t| t.parse::<f64>()) {
+            None => Ok(None),
+            // like libpq, 0 means no timeout
+            Some(Ok(0.0)) => Ok(None),
+            Some(Ok(t)) if t > 0.0 => Ok(Some(Duration::from_secs_f64(t))),
+            Some(_) => Err("Connect timeout needs to be a positive number of seconds".to_string()),
+        }
+    }
+
+    // The kind of server to connect to, checked after connecting rather than by tokio-postgres, which only knows
+    // read-write and read-only
+    pub fn target(&self) -> Result<Target, String> {
+        self.get("target_session_attrs").map_or(Ok(Target::Any), |target| target.parse())
+    }
+
+    // The Kerberos service name of the server for GSSAPI, like libpq's default
+    pub fn krbsrvname(&self) -> &str {
+        self.get("krbsrvname").unwrap_or("postgres")
+    }
+
+    // The session's client encoding, which tokio-postgres always starts as UTF8
+    pub fn client_encoding(&self) -> Result<Encoding, String> {
+        self.get("client_encoding").map_or(Ok(Encoding::Utf8), |encoding| encoding.parse())
+    }
+
+    pub fn tls_config(&self) -> Result<TlsConfig, String> {
+        let system = self.get("sslrootcert") == Some("system");
+        Ok(TlsConfig {
+            mode: match self.get("sslmode") {
+                Some(mode) => match mode.parse()? {
+                    // like libpq, the system's CAs trust any certificate a public CA issued for the name
+                    SslMode::VerifyFull => SslMode::VerifyFull,
+                    _ if system => return Err(format!("sslrootcert=system needs sslmode verify-full, not {}", mode)),
+                    mode => mode,
+                },
+                None if system => SslMode::VerifyFull,
+                None => SslMode::Prefer,
+            },
+            cert: self.get("sslcert").map(PathBuf::from),
+            key: self.get("sslkey").map(PathBuf::from),
+            password: self.get("sslpassword").map(|p| p.to_string()),
+            root_cert: self.get("sslrootcert").map(|p| p.to_string()),
+            crl: self.get("sslcrl").map(PathBuf::from),
+            sni: match self.get("sslsni") {
+                None | Some("1") => true,
+                Some("0") => false,
+                Some(sni) => return Err(format!("Invalid sslsni '{}', needs to be 0 or 1", sni)),
+            },
+            hostname: None,
+        })
+    }
+
+    // The parameters every host is connected with
+    fn config(&self) -> Result<Config, String> {
+        let mut config = Config::new();
+        if let Some(user) = self.get("user") {
+            config.user(user);
+        }
+        if let Some(password) = self.get("password") {
+            config.password(password);
+        }
+        if let Some(dbname) = self.get("dbname") {
+            config.dbname(dbname);
+        }
+        if let Some(application_name) = self.get("application_name") {
+            config.application_name(application_name);
+        }
+        if let Some(options) = self.get("options") {
+            config.options(options);
+        }
+        if let Some(keepalives) = self.get("keepalives") {
+            config.keepalives(keepalives != "0");
+        }
+        if let Some(idle) = self.get("keepalives_idle") {
+            config.keepalives_idle(Duration::from_secs(idle.parse().map_err(|_| format!("Invalid keepalives_idle '{}'", idle))?));
+        }
+        // SCRAM binds to the TLS connection if the server offers it, `require` refuses servers that do not
+        if let Some(binding) = self.get("channel_binding") {
+            config.channel_binding(match binding {
+                "disable" => ChannelBinding::Disable,
+                "prefer" => ChannelBinding::Prefer,
+                "require" => ChannelBinding::Require,
+                _ => return Err(format!("Invalid channel_binding '{}'", binding)),
+            });
+        }
+        config.ssl_mode(self.tls_config()?.mode.negotiation());
+        Ok(config)
+    }
+
+    // A configuration per host, in the order they are tried, with the host's name for the output. Like libpq, hostaddr
+    // needs a value per host and port one per host or one for all.
+    pub fn configs(&self) -> Result<Vec<(String, Config)>, String> {
+        let list = |key: &str| self.get(key).map(|values| values.split(',').map(|value| value.trim()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let (hosts, hostaddrs, ports) = (list("host"), list("hostaddr"), list("port"));
+        if !hosts.is_empty() && !hostaddrs.is_empty() && hosts.len() != hostaddrs.len() {
+            return Err(format!("Could not match {} host names to {} hostaddr values", hosts.len(), hostaddrs.len()));
+        }
+        let count = hosts.len().max(hostaddrs.len()).max(1);
+        if ports.len() > 1 && ports.len() != count {
+            return Err(format!("Could not match {} port numbers to {} hosts", ports.len(), count));
+        }
+        let base = self.config()?;
+        (0..count).map(|i| {
+            let mut config = base.clone();
+            let host = hosts.get(i).copied().filter(|host| !host.is_empty());
+            let hostaddr = hostaddrs.get(i).copied().filter(|hostaddr| !hostaddr.is_empty());
+            if let Some(host) = host {
+                config.host(host);
+            } else if hostaddr.is_none() {
+                config.host("localhost");
+            }
+            if let Some(hostaddr) = hostaddr {
+                config.hostaddr(hostaddr.parse().map_err(|_| format!("Invalid hostaddr '{}'", hostaddr))?);
+            }
+            let port = ports.get(if ports.len() == 1 { 0 } else { i }).filter(|port| !port.is_empty());
+            if let Some(port) = port {
+                config.port(port.parse().map_err(|_| format!("Invalid port '{}'", port))?);
+            }
+            // hosts with ports of their own are told apart by them
+            let name = host.or(hostaddr).unwrap_or("localhost");
+            Ok((match port {
+                Some(port) if ports.len() > 1 => format!("{}:{}", name, port),
+                _ => name.to_string(),
+            }, config))
+        }).collect()
+    }
+}
@@ -0,0 +1,89 @@
+// The client encoding of the session. The server converts text to it, so a database in another encoding is still read
+// correctly; the default of UTF8 suits all but legacy setups. `--client-encoding` selects one of the single byte
+// encodings instead, e.g. for a SQL_ASCII database holding latin1 text, which the server does not convert. Text that is
+// not valid in the encoding is shown with replacement characters instead of failing the check.
+
+use std::future::Future;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Latin1,
+    Latin9,
+    Win1252,
+}
+
+// The characters of WIN1252 that differ from latin1, for 0x80 to 0x9F. The unassigned ones are replacement characters.
+const WIN1252: [char; 32] = [
+    '\u{20AC}', '\u{FFFD}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{FFFD}', '\u{017D}', '\u{FFFD}',
+    '\u{FFFD}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{FFFD}', '\u{017E}', '\u{0178}',
+];
+
+impl Encoding {
+    // The name postgres knows the encoding by
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF8",
+            Encoding::Latin1 => "LATIN1",
+            Encoding::Latin9 => "LATIN9",
+            Encoding::Win1252 => "WIN1252",
+        }
+    }
+
+    pub fn decode(self, raw: &[u8]) -> String {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(raw).into_owned(),
+            Encoding::Latin1 => raw.iter().map(|&b| b as char).collect(),
+            // latin9 replaces eight characters of latin1, e.g. the currency sign by the euro sign
+            Encoding::Latin9 => raw.iter().map(|&b| match b {
+                0xA4 => '\u{20AC}',
+                0xA6 => '\u{0160}',
+                0xA8 => '\u{0161}',
+                0xB4 => '\u{017D}',
+                0xB8 => '\u{017E}',
+                0xBC => '\u{0152}',
+                0xBD => '\u{0153}',
+                0xBE => '\u{0178}',
+                b => b as char,
+            }).collect(),
+            Encoding::Win1252 => raw.iter().map(|&b| match b {
+                0x80..=0x9F => WIN1252[(b - 0x80) as usize],
+                b => b as char,
+            }).collect(),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    // Like postgres, names are compared without case and punctuation, so `ISO-8859-1` is LATIN1
+    fn from_str(s: &str) -> Result<Encoding, String> {
+        let name: String = s.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_lowercase();
+        match name.as_str() {
+            "utf8" | "unicode" => Ok(Encoding::Utf8),
+            "latin1" | "iso88591" => Ok(Encoding::Latin1),
+            "latin9" | "iso885915" => Ok(Encoding::Latin9),
+            "win1252" | "windows1252" => Ok(Encoding::Win1252),
+            _ => Err(format!("Unsupported client encoding '{}', expected UTF8, LATIN1, LATIN9 or WIN1252", s)),
+        }
+    }
+}
+
+tokio::task_local! {
+    static ENCODING: Encoding;
+}
+
+// Runs `future` with text decoded in `encoding`
+pub async fn scope<F: Future>(encoding: Encoding, future: F) -> F::Output {
+    ENCODING.scope(encoding, future).await
+}
+
+// The encoding of the session the current check runs on, UTF8 outside of a check
+pub fn current() -> Encoding {
+    ENCODING.try_with(|encoding| *encoding).unwrap_or_default()
+}
@@ -0,0 +1,24 @@
+// Expectations for text columns, which cannot be compared against threshold ranges
+
+use regex::Regex;
+
+pub enum TextExpectation {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl TextExpectation {
+    pub fn regex(pattern: &str) -> Result<TextExpectation, String> {
+        Regex::new(pattern)
+            .map(TextExpectation::Regex)
+            // regex errors span multiple lines, but the status line must not
+            .map_err(|err| format!("Invalid regex '{}': {}", pattern, err.to_string().split_whitespace().collect::<Vec<_>>().join(" ")))
+    }
+
+    pub fn is_met(&self, text: &str) -> bool {
+        match *self {
+            TextExpectation::Literal(ref literal) => text == literal,
+            TextExpectation::Regex(ref regex) => regex.is_match(text),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+// GSSAPI logins, e.g. with Kerberos, which tokio-postgres does not implement. A server asking for GSSAPI or SSPI gets
+// a connection of the plugin's own: TLS like the driver would do it, the startup message and the exchange of tokens of
+// a security context for `<krbsrvname>@<host>` with the ticket cache's or keytab's credentials (`$KRB5CCNAME`,
+// `$KRB5_CLIENT_KTNAME`). The driver then takes the logged in session over as if it had made it itself.
+// libgssapi_krb5 is loaded when it is first needed, the plugin runs without it as long as no server asks for GSSAPI.
+
+use crate::session::describe;
+use postgres_openssl::MakeTlsConnector;
+use std::ffi::{c_void, CStr};
+use std::io;
+use std::pin::Pin;
+use std::ptr;
+use std::sync::OnceLock;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_postgres::config::{Host, SslMode};
+use tokio_postgres::tls::{MakeTlsConnect, NoTls, TlsConnect};
+use tokio_postgres::{Client, Config};
+
+#[repr(C)]
+struct Buffer {
+    length: usize,
+    value: *mut c_void,
+}
+
+#[repr(C)]
+struct Oid {
+    length: u32,
+    elements: *mut c_void,
+}
+
+type ImportName = unsafe extern "C" fn(*mut u32, *const Buffer, *const Oid, *mut *mut c_void) -> u32;
+type InitSecContext = unsafe extern "C" fn(*mut u32, *mut c_void, *mut *mut c_void, *mut c_void, *const Oid, u32, u32,
+                                           *const c_void, *const Buffer, *mut *const Oid, *mut Buffer, *mut u32, *mut u32) -> u32;
+type ReleaseBuffer = unsafe extern "C" fn(*mut u32, *mut Buffer) -> u32;
+type ReleaseName = unsafe extern "C" fn(*mut u32, *mut *mut c_void) -> u32;
+type DeleteSecContext = unsafe extern "C" fn(*mut u32, *mut *mut c_void, *mut Buffer) -> u32;
+type DisplayStatus = unsafe extern "C" fn(*mut u32, u32, i32, *const Oid, *mut u32, *mut Buffer) -> u32;
+
+// The functions of libgssapi_krb5 the login needs
+struct Library {
+    import_name: ImportName,
+    init_sec_context: InitSecContext,
+    release_buffer: ReleaseBuffer,
+    release_name: ReleaseName,
+    delete_sec_context: DeleteSecContext,
+    display_status: DisplayStatus,
+}
+
+static LIBRARY: OnceLock<Result<Library, String>> = OnceLock::new();
+
+// GSS_C_NT_HOSTBASED_SERVICE, 1.2.840.113554.1.2.1.4, names like `postgres@db1.example.com`
+const HOSTBASED_SERVICE: [u8; 10] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x12, 0x01, 0x02, 0x01, 0x04];
+const MUTUAL_FLAG: u32 = 2;
+const CONTINUE_NEEDED: u32 = 1;
+const GSS_CODE: i32 = 1;
+const MECH_CODE: i32 = 2;
+
+// The message the driver reads instead of the server's answer to its startup message
+const AUTHENTICATION_OK: [u8; 9] = [b'R', 0, 0, 0, 8, 0, 0, 0, 0];
+
+fn library() -> Result<&'static Library, String> {
+    LIBRARY.get_or_init(|| unsafe {
+        let handle = libc::dlopen(c"libgssapi_krb5.so.2".as_ptr(), libc::RTLD_NOW);
+        if handle.is_null() {
+            let error = libc::dlerror();
+            let error = if error.is_null() { String::new() } else { CStr::from_ptr(error).to_string_lossy().into_owned() };
+            return Err(format!("GSSAPI needs libgssapi_krb5.so.2: {}", error));
+        }
+        let symbol = |name: &CStr| match libc::dlsym(handle, name.as_ptr()) {
+            symbol if symbol.is_null() => Err(format!("libgssapi_krb5.so.2 has no {}", name.to_string_lossy())),
+            symbol => Ok(symbol),
+        };
+        Ok(Library {
+            import_name: std::mem::transmute::<*mut c_void, ImportName>(symbol(c"gss_import_name")?),
+            init_sec_context: std::mem::transmute::<*mut c_void, InitSecContext>(symbol(c"gss_init_sec_context")?),
+            release_buffer: std::mem::transmute::<*mut c_void, ReleaseBuffer>(symbol(c"gss_release_buffer")?),
+            release_name: std::mem::transmute::<*mut c_void, ReleaseName>(symbol(c"gss_release_name")?),
+            delete_sec_context: std::mem::transmute::<*mut c_void, DeleteSecContext>(symbol(c"gss_delete_sec_context")?),
+            display_status: std::mem::transmute::<*mut c_void, DisplayStatus>(symbol(c"gss_display_status")?),
+        })
+    }).as_ref().map_err(|err| err.clone())
+}
+
+impl Library {
+    // The messages of a major status and the mechanism's minor one, e.g. that there is no ticket
+    fn error(&self, what: &str, major: u32, minor: u32) -> String {
+        let mut messages = vec![];
+        for (status, kind) in [(major, GSS_CODE), (minor, MECH_CODE)] {
+            let mut context = 0;
+            loop {
+                let (mut ignored, mut buffer) = (0, Buffer { length: 0, value: ptr::null_mut() });
+                let result = unsafe { (self.display_status)(&mut ignored, status, kind, ptr::null(), &mut context, &mut buffer) };
+                if result != 0 {
+                    break;
+                }
+                if !buffer.value.is_null() {
+                    let message = unsafe { std::slice::from_raw_parts(buffer.value as *const u8, buffer.length) };
+                    let message = String::from_utf8_lossy(message).trim().to_string();
+                    if !message.is_empty() && !messages.contains(&message) {
+                        messages.push(message);
+                    }
+                    unsafe { (self.release_buffer)(&mut ignored, &mut buffer) };
+                }
+                if context == 0 {
+                    break;
+                }
+            }
+        }
+        format!("GSSAPI {}: {}", what, messages.join(": "))
+    }
+}
+
+// A security context with the server's service, while it is established
+struct SecurityContext {
+    library: &'static Library,
+    name: *mut c_void,
+    context: *mut c_void,
+}
+
+// the context is only ever used by one thread at a time
+unsafe impl Send for SecurityContext {}
+
+impl SecurityContext {
+    fn new(service: &str) -> Result<SecurityContext, String> {
+        let library = library()?;
+        let buffer = Buffer { length: service.len(), value: service.as_ptr() as *mut c_void };
+        let oid = Oid { length: HOSTBASED_SERVICE.len() as u32, elements: HOSTBASED_SERVICE.as_ptr() as *mut c_void };
+        let (mut minor, mut name) = (0, ptr::null_mut());
+        let major = unsafe { (library.import_name)(&mut minor, &buffer, &oid, &mut name) };
+        if major != 0 {
+            return Err(library.error(&format!("could not use the service name {}", service), major, minor));
+        }
+        Ok(SecurityContext { library, name, context: ptr::null_mut() })
+    }
+
+    // The token for the server from the one it sent, empty for the first, and whether the context is established.
+    // Getting a ticket for the service may ask the KDC, so this blocks.
+    fn step(&mut self, input: &[u8]) -> Result<(Vec<u8>, bool), String> {
+        let input = Buffer { length: input.len(), value: input.as_ptr() as *mut c_void };
+        let (mut minor, mut output) = (0, Buffer { length: 0, value: ptr::null_mut() });
+        let major = unsafe {
+            (self.library.init_sec_context)(&mut minor, ptr::null_mut(), &mut self.context, self.name, ptr::null(), MUTUAL_FLAG, 0,
+                                            ptr::null(), if input.length > 0 { &input } else { ptr::null() }, ptr::null_mut(),
+                                            &mut output, ptr::null_mut(), ptr::null_mut())
+        };
+        let token = match output.value.is_null() {
+            true => vec![],
+            false => unsafe { std::slice::from_raw_parts(output.value as *const u8, output.length).to_vec() },
+        };
+        let mut ignored = 0;
+        unsafe { (self.library.release_buffer)(&mut ignored, &mut output) };
+        // the routine errors are in the upper half of the status, the lower half are informational bits
+        if major & 0xffff_0000 != 0 {
+            return Err(self.library.error("could not establish the security context", major, minor));
+        }
+        Ok((token, major & CONTINUE_NEEDED == 0))
+    }
+}
+
+impl Drop for SecurityContext {
+    fn drop(&mut self) {
+        let mut ignored = 0;
+        unsafe {
+            if !self.context.is_null() {
+                (self.library.delete_sec_context)(&mut ignored, &mut self.context, ptr::null_mut());
+            }
+            (self.library.release_name)(&mut ignored, &mut self.name);
+        }
+    }
+}
+
+// The connection to the server, with or without TLS
+trait Stream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Stream for S {}
+
+// A logged in session for the driver: the startup message it sends is dropped, and the answer it gets is that it is
+// logged in. Everything after goes to the server and back.
+struct LoggedIn {
+    stream: Box<dyn Stream>,
+    startup: Vec<u8>,
+    answered: usize,
+}
+
+impl LoggedIn {
+    // How much of the driver's startup message is still to be dropped, its length comes first
+    fn startup_left(&self) -> usize {
+        match self.startup.get(..4) {
+            Some(length) => (u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize).saturating_sub(self.startup.len()),
+            None => 4 - self.startup.len(),
+        }
+    }
+}
+
+impl AsyncRead for LoggedIn {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if self.answered < AUTHENTICATION_OK.len() {
+            let n = buf.remaining().min(AUTHENTICATION_OK.len() - self.answered);
+            buf.put_slice(&AUTHENTICATION_OK[self.answered..self.answered + n]);
+            self.answered += n;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for LoggedIn {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let left = self.startup_left();
+        if left > 0 {
+            let n = left.min(buf.len());
+            self.startup.extend_from_slice(&buf[..n]);
+            return Poll::Ready(Ok(n));
+        }
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+fn io_error(err: io::Error) -> String {
+    format!("Connection to the server failed during GSSAPI authentication: {}", err)
+}
+
+// A message of the server, its type and body
+async fn read_message(stream: &mut Box<dyn Stream>) -> Result<(u8, Vec<u8>), String> {
+    let mut header = [0; 5];
+    stream.read_exact(&mut header).await.map_err(io_error)?;
+    let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if !(4..=1 << 20).contains(&length) {
+        return Err(format!("Invalid message of length {} from the server", length));
+    }
+    let mut body = vec![0; length - 4];
+    stream.read_exact(&mut body).await.map_err(io_error)?;
+    Ok((header[0], body))
+}
+
+// An ErrorResponse like the driver describes it, e.g. `db error: FATAL: GSSAPI authentication failed for user "nagios"`
+fn error_response(body: &[u8]) -> String {
+    let mut severity = "ERROR".to_string();
+    let mut message = String::new();
+    for field in body.split(|&b| b == 0).filter(|field| !field.is_empty()) {
+        let value = String::from_utf8_lossy(&field[1..]).into_owned();
+        match field[0] {
+            b'S' => severity = value,
+            b'M' => message = value,
+            _ => {}
+        }
+    }
+    format!("db error: {}: {}", severity, message)
+}
+
+// The next step of the security context, on another thread since it blocks
+async fn step(context: SecurityContext, input: Vec<u8>) -> Result<(SecurityContext, Vec<u8>), String> {
+    let (context, result) = tokio::task::spawn_blocking(move || {
+        let mut context = context;
+        let result = context.step(&input);
+        (context, result)
+    }).await.map_err(|err| format!("GSSAPI failed: {}", err))?;
+    result.map(|(token, _)| (context, token))
+}
+
+// Connects to the host of `config` and logs in with GSSAPI to the service `krbsrvname` as the configuration's user.
+// The connection is driven by a task of its own until the client is dropped.
+pub async fn connect(config: &Config, mut tls: MakeTlsConnector, krbsrvname: &str) -> Result<Client, String> {
+    let port = config.get_ports().first().copied().unwrap_or(5432);
+    let hostaddr = config.get_hostaddrs().first().copied();
+    let host = match config.get_hosts().first() {
+        Some(Host::Tcp(host)) => host.clone(),
+        Some(Host::Unix(_)) => return Err("GSSAPI needs a TCP connection, a Unix socket cannot be used".to_string()),
+        None => hostaddr.map(|hostaddr| hostaddr.to_string()).unwrap_or_else(|| "localhost".to_string()),
+    };
+    let stream = match hostaddr {
+        Some(hostaddr) => TcpStream::connect((hostaddr, port)).await,
+        None => TcpStream::connect((host.as_str(), port)).await,
+    }.map_err(|err| format!("Could not connect to {}:{}: {}", host, port, err))?;
+    let _ = stream.set_nodelay(true);
+
+    // like the driver, TLS is negotiated with an SSLRequest first
+    let mut stream: Box<dyn Stream> = match config.get_ssl_mode() {
+        SslMode::Disable => Box::new(stream),
+        mode => {
+            let mut stream = stream;
+            stream.write_all(&[0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f]).await.map_err(io_error)?;
+            let mut answer = [0];
+            stream.read_exact(&mut answer).await.map_err(io_error)?;
+            match answer[0] {
+                b'S' => {
+                    let connector = MakeTlsConnect::<TcpStream>::make_tls_connect(&mut tls, &host)
+                        .map_err(|err| format!("error performing TLS handshake: {}", err))?;
+                    Box::new(connector.connect(stream).await.map_err(|err| format!("error performing TLS handshake: {}", describe(&*err)))?)
+                }
+                _ if matches!(mode, SslMode::Require) => return Err("error performing TLS handshake: server does not support TLS".to_string()),
+                _ => Box::new(stream),
+            }
+        }
+    };
+
+    // the startup message of the driver, which does not send a password either
+    let user = config.get_user().map(|user| user.to_string()).or_else(|| std::env::var("USER").ok()).ok_or("GSSAPI needs a user")?;
+    let mut parameters = vec![("user", user.as_str()), ("client_encoding", "UTF8")];
+    for (name, value) in [("database", config.get_dbname()), ("application_name", config.get_application_name()), ("options", config.get_options())] {
+        if let Some(value) = value {
+            parameters.push((name, value));
+        }
+    }
+    let mut startup = 196608u32.to_be_bytes().to_vec();
+    for (name, value) in parameters {
+        startup.extend_from_slice(name.as_bytes());
+        startup.push(0);
+        startup.extend_from_slice(value.as_bytes());
+        startup.push(0);
+    }
+    startup.push(0);
+    let mut message = ((startup.len() + 4) as u32).to_be_bytes().to_vec();
+    message.extend_from_slice(&startup);
+    stream.write_all(&message).await.map_err(io_error)?;
+
+    let mut context: Option<SecurityContext> = None;
+    loop {
+        let (tag, body) = read_message(&mut stream).await?;
+        let token = match (tag, body.get(..4).map(|code| u32::from_be_bytes([code[0], code[1], code[2], code[3]]))) {
+            (b'R', Some(0)) => break,
+            // SSPI is GSSAPI with SPNEGO, which libgssapi_krb5 negotiates as well
+            (b'R', Some(7)) | (b'R', Some(9)) => {
+                let (established, token) = step(SecurityContext::new(&format!("{}@{}", krbsrvname, host))?, vec![]).await?;
+                context = Some(established);
+                token
+            }
+            (b'R', Some(8)) => {
+                let (established, token) = step(context.take().ok_or("GSSAPI token from the server before the exchange started")?, body[4..].to_vec()).await?;
+                context = Some(established);
+                token
+            }
+            (b'R', Some(code)) => return Err(format!("Server asked for authentication method {} instead of GSSAPI", code)),
+            (b'E', _) => return Err(error_response(&body)),
+            _ => return Err("Unexpected message from the server during GSSAPI authentication".to_string()),
+        };
+        if !token.is_empty() {
+            let mut message = vec![b'p'];
+            message.extend_from_slice(&((token.len() + 4) as u32).to_be_bytes());
+            message.extend_from_slice(&token);
+            stream.write_all(&message).await.map_err(io_error)?;
+        }
+    }
+    drop(context);
+
+    let mut config = config.clone();
+    config.ssl_mode(SslMode::Disable);
+    let (client, connection) = config.connect_raw(LoggedIn { stream, startup: vec![], answered: 0 }, NoTls).await
+        .map_err(|err| describe(&err))?;
+    tokio::spawn(connection);
+    Ok(client)
+}
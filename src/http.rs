@@ -0,0 +1,189 @@
+// A minimal HTTP/1.1 client for the services credentials are fetched from, like Vault or a cloud's metadata service.
+// Every request has a connection of its own, closed after the response. `https` URLs verify the server with the
+// system's trusted CAs and those of `cacert`, if given. Within `deadline`, the requests of the thread end by then at
+// the latest.
+
+use openssl::ssl::{HandshakeError, SslConnector, SslMethod};
+use std::cell::Cell;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+thread_local! {
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+// Runs `f` with the requests it makes ending by `deadline`, if given
+pub fn deadline<T, F: FnOnce() -> T>(deadline: Option<Instant>, f: F) -> T {
+    let previous = DEADLINE.with(|current| current.replace(deadline));
+    let result = f();
+    DEADLINE.with(|current| current.set(previous));
+    result
+}
+
+// The time a step of a request may take, `TimedOut` once the deadline has passed
+fn timeout() -> std::io::Result<Duration> {
+    match DEADLINE.with(|deadline| deadline.get()) {
+        None => Ok(TIMEOUT),
+        Some(deadline) => match deadline.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) {
+            Some(left) => Ok(left.min(TIMEOUT)),
+            None => Err(ErrorKind::TimedOut.into()),
+        },
+    }
+}
+
+// A socket's timeout is `WouldBlock` on some systems, it is reported as what it is
+fn io_error(err: std::io::Error) -> String {
+    match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => "timed out".to_string(),
+        _ => err.to_string(),
+    }
+}
+
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+struct Url<'a> {
+    tls: bool,
+    // host and port as given, for the Host header
+    authority: &'a str,
+    host: &'a str,
+    port: u16,
+    // the path including the query, at least `/`
+    path: &'a str,
+}
+
+fn parse_url(url: &str) -> Result<Url<'_>, String> {
+    let invalid = || format!("Invalid URL '{}'", url);
+    let (tls, rest) = match url.split_once("://") {
+        Some(("https", rest)) => (true, rest),
+        Some(("http", rest)) => (false, rest),
+        _ => return Err(invalid()),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, "/"),
+    };
+    // an IPv6 address is in brackets, its colons are not the port's
+    let (host, port) = match authority.rfind(':').filter(|&pos| !authority[pos..].contains(']')) {
+        Some(pos) => (&authority[..pos], authority[pos + 1..].parse().map_err(|_| invalid())?),
+        None => (authority, if tls { 443 } else { 80 }),
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Url { tls, authority, host, port, path })
+}
+
+fn connect(url: &Url) -> std::io::Result<TcpStream> {
+    let mut last = None;
+    for address in (url.host, url.port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, timeout()?) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last = Some(err),
+        }
+    }
+    Err(last.unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "host has no address")))
+}
+
+// Reads until the server closes the connection. Servers that close a TLS connection without notifying the client are
+// common, the response is complete then anyway. `socket` is that of the stream, a server sending slowly must not keep
+// the request past the deadline.
+fn read_response<S: Read>(stream: &mut S, socket: &TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut response = vec![];
+    let mut buffer = [0u8; 8192];
+    loop {
+        socket.set_read_timeout(Some(timeout()?))?;
+        match stream.read(&mut buffer) {
+            Ok(0) => return Ok(response),
+            Ok(n) => response.extend_from_slice(&buffer[..n]),
+            Err(_) if !response.is_empty() => return Ok(response),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// The body of a response with `Transfer-Encoding: chunked`
+fn dechunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut out = vec![];
+    loop {
+        let end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..end]).ok()?.split(';').next()?.trim();
+        let size = usize::from_str_radix(size, 16).ok()?;
+        if size == 0 {
+            return Some(out);
+        }
+        let chunk = body.get(end + 2..end + 2 + size)?;
+        out.extend_from_slice(chunk);
+        body = body.get(end + 4 + size..)?;
+    }
+}
+
+fn parse_response(response: &[u8]) -> Option<Response> {
+    let end = response.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&response[..end]).ok()?;
+    let mut lines = head.split("\r\n");
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let mut body = response[end + 4..].to_vec();
+    for line in lines {
+        let (name, value) = line.split_once(':')?;
+        match name.trim().to_lowercase().as_str() {
+            "transfer-encoding" if value.trim().eq_ignore_ascii_case("chunked") => body = dechunk(&body)?,
+            "content-length" => body.truncate(value.trim().parse().ok()?),
+            _ => {}
+        }
+    }
+    Some(Response { status, body: String::from_utf8_lossy(&body).into_owned() })
+}
+
+// Percent-encodes everything but the unreserved characters, for query strings and form bodies
+pub fn encode(s: &str) -> String {
+    s.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{:02X}", b),
+    }).collect()
+}
+
+// Sends a request and returns the response, whatever its status
+pub fn request(method: &str, url: &str, headers: &[(&str, &str)], body: Option<&str>, cacert: Option<&str>) -> Result<Response, String> {
+    let parsed = parse_url(url)?;
+    let error = |err: &dyn std::fmt::Display| format!("Request to {} failed: {}", url, err);
+    let io = |err: std::io::Error| error(&io_error(err));
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", method, parsed.path, parsed.authority);
+    for (name, value) in headers {
+        request += &format!("{}: {}\r\n", name, value);
+    }
+    if let Some(body) = body {
+        request += &format!("Content-Length: {}\r\n", body.len());
+    }
+    request += "\r\n";
+    request += body.unwrap_or("");
+
+    let stream = connect(&parsed).map_err(io)?;
+    let timeout = timeout().map_err(io)?;
+    stream.set_read_timeout(Some(timeout)).and_then(|_| stream.set_write_timeout(Some(timeout))).map_err(io)?;
+    let socket = stream.try_clone().map_err(io)?;
+    let response = if parsed.tls {
+        let mut builder = SslConnector::builder(SslMethod::tls()).map_err(|err| error(&err))?;
+        if let Some(cacert) = cacert {
+            builder.set_ca_file(cacert).map_err(|err| format!("Could not read CA file '{}': {}", cacert, err))?;
+        }
+        let mut stream = builder.build().connect(parsed.host, stream).map_err(|err| match err {
+            HandshakeError::WouldBlock(_) => error(&"timed out"),
+            HandshakeError::Failure(ref mid) if mid.error().io_error().is_some_and(|err| err.kind() == ErrorKind::TimedOut) => error(&"timed out"),
+            err => error(&err),
+        })?;
+        stream.write_all(request.as_bytes()).map_err(io)?;
+        read_response(&mut stream, &socket).map_err(io)?
+    } else {
+        let mut stream = stream;
+        stream.write_all(request.as_bytes()).map_err(io)?;
+        read_response(&mut stream, &socket).map_err(io)?
+    };
+    parse_response(&response).ok_or_else(|| error(&"invalid HTTP response"))
+}
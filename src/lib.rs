@@ -0,0 +1,55 @@
+//! The checks of the `check_postgresql` Nagios plugin as a library, for programs that run them without starting the
+//! plugin. The options of a check are given like its command line arguments, the connection is configured by the same
+//! options. A check's result is a [`Status`](status/struct.Status.html) with the plugin's description, perfdata and
+//! long output, which [`output::render`](output/fn.render.html) turns into any of the plugin's output formats.
+//!
+//! Runs are futures on a tokio runtime, so runs against several servers can be awaited together:
+//!
+//! ```no_run
+//! use check_postgresql::checks;
+//! use check_postgresql::connection::Connection;
+//! use check_postgresql::options::Options;
+//!
+//! let options = Options::parse(&["-d", "host=db1 user=nagios", "-w", "10,60", "-c", "20,300"]).unwrap();
+//! let locks = checks::builtin("locks").unwrap()(&options).unwrap();
+//! let connection = Connection::new(&options).unwrap();
+//! let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+//! for (name, status) in runtime.block_on(connection.run(&[("locks".to_string(), locks)])).unwrap() {
+//!     println!("{}: {}", name, status);
+//! }
+//! ```
+//!
+//! Queries are checks, too, see [`Query`](checks/struct.Query.html). Own checks implement
+//! [`Check`](checks/trait.Check.html) and use the [`Session`](session/struct.Session.html) to query the server.
+
+pub mod agent;
+pub mod arguments;
+mod aws;
+mod azure;
+pub mod checks;
+pub mod config;
+pub mod connection;
+pub mod conninfo;
+pub mod encoding;
+mod expect;
+mod gss;
+mod http;
+mod json;
+pub mod options;
+pub mod otlp;
+pub mod nrpe;
+pub mod output;
+pub mod perfdata;
+mod pgpass;
+pub mod prometheus;
+pub mod session;
+pub mod state;
+pub mod statsd;
+pub mod status;
+pub mod threshold;
+mod tls;
+pub mod units;
+pub mod value;
+mod vault;
+pub mod verbose;
+pub mod watchdog;
@@ -0,0 +1,203 @@
+// An NRPE listener: with `--nrpe-listen`, the program stays resident and answers the requests of `check_nrpe` itself,
+// so database hosts need no NRPE daemon. Requests name the check to run, there are no command definitions. Packets
+// are those of NRPE versions 2 and 3, in network byte order with a CRC32 of the whole packet:
+//
+//   v2   version (2), type (1 request, 2 response), crc32, result code, 1024 bytes buffer, 2 bytes padding
+//   v3   version (3), type, crc32, result code, 2 bytes alignment, buffer length (4 bytes), buffer
+//
+// The buffer holds the command, terminated by a NUL byte. Like the NRPE daemon, the connection uses TLS with
+// anonymous Diffie-Hellman unless a certificate is given, or no TLS with `--nrpe-no-ssl` for `check_nrpe -n`.
+
+use crate::verbose;
+use openssl::dh::Dh;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslAcceptor, SslMethod, SslVersion};
+use openssl::x509::X509;
+use std::io::{Read, Write};
+use std::net::{IpAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+const QUERY: u16 = 1;
+const RESPONSE: u16 = 2;
+
+// The size of a version 2 packet's buffer, longer output is cut off
+const V2_BUFFER: usize = 1024;
+
+// The largest version 3 request accepted, check_nrpe's commands are far shorter
+const V3_MAX_BUFFER: usize = 65536;
+
+// The CRC32 NRPE uses, the one of zlib and ethernet
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+// Whether the packet's CRC matches, computed with the CRC field zeroed
+fn valid_crc(packet: &[u8]) -> bool {
+    let expected = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+    let mut zeroed = packet.to_vec();
+    zeroed[4..8].copy_from_slice(&[0; 4]);
+    crc32(&zeroed) == expected
+}
+
+// The command of the buffer, up to the first NUL byte
+fn command(buffer: &[u8]) -> String {
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..end]).into_owned()
+}
+
+// Reads a request, returns the packet version and the command
+fn read_request<S: Read>(stream: &mut S) -> std::io::Result<(u16, String)> {
+    let mut header = [0u8; 10];
+    stream.read_exact(&mut header)?;
+    let version = u16::from_be_bytes([header[0], header[1]]);
+    if u16::from_be_bytes([header[2], header[3]]) != QUERY {
+        return Err(invalid("not a query packet"));
+    }
+    match version {
+        2 => {
+            let mut packet = header.to_vec();
+            packet.resize(10 + V2_BUFFER + 2, 0);
+            stream.read_exact(&mut packet[10..])?;
+            if !valid_crc(&packet) {
+                return Err(invalid("CRC mismatch"));
+            }
+            Ok((2, command(&packet[10..10 + V2_BUFFER])))
+        }
+        3 => {
+            let mut packet = header.to_vec();
+            packet.resize(16, 0);
+            stream.read_exact(&mut packet[10..])?;
+            let length = u32::from_be_bytes([packet[12], packet[13], packet[14], packet[15]]) as usize;
+            if length > V3_MAX_BUFFER {
+                return Err(invalid("request too long"));
+            }
+            packet.resize(16 + length, 0);
+            stream.read_exact(&mut packet[16..])?;
+            // check_nrpe sends the padding of its packet struct too and includes it in the CRC
+            if !valid_crc(&packet) {
+                let mut padding = [0u8; 3];
+                stream.read_exact(&mut padding)?;
+                packet.extend_from_slice(&padding);
+                if !valid_crc(&packet) {
+                    return Err(invalid("CRC mismatch"));
+                }
+            }
+            Ok((3, command(&packet[16..16 + length])))
+        }
+        _ => Err(invalid("unsupported packet version")),
+    }
+}
+
+// A response packet of `version` with the plugin's exit code and output
+fn response(version: u16, code: i32, output: &str) -> Vec<u8> {
+    let mut packet = vec![];
+    packet.extend_from_slice(&version.to_be_bytes());
+    packet.extend_from_slice(&RESPONSE.to_be_bytes());
+    packet.extend_from_slice(&[0; 4]);
+    packet.extend_from_slice(&(code as i16).to_be_bytes());
+    if version == 2 {
+        // cut at a character boundary, leaving room for the NUL byte
+        let mut end = output.len().min(V2_BUFFER - 1);
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        packet.extend_from_slice(&output.as_bytes()[..end]);
+        packet.resize(10 + V2_BUFFER + 2, 0);
+    } else {
+        packet.extend_from_slice(&[0; 2]);
+        packet.extend_from_slice(&(output.len() as u32 + 1).to_be_bytes());
+        packet.extend_from_slice(output.as_bytes());
+        packet.push(0);
+    }
+    let crc = crc32(&packet);
+    packet[4..8].copy_from_slice(&crc.to_be_bytes());
+    packet
+}
+
+// The TLS setup of the listener. Without a certificate, only anonymous Diffie-Hellman is possible, which TLS 1.3
+// does not offer.
+pub fn acceptor(cert: Option<&str>, key: Option<&str>) -> Result<SslAcceptor, String> {
+    let error = |err: openssl::error::ErrorStack| format!("Could not set up TLS for NRPE: {}", err);
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).map_err(error)?;
+    match (cert, key) {
+        (Some(cert), Some(key)) => {
+            let read = |path: &str| std::fs::read(path).map_err(|err| format!("Could not read '{}': {}", path, err));
+            let cert = X509::from_pem(&read(cert)?).map_err(error)?;
+            let key = PKey::private_key_from_pem(&read(key)?).map_err(error)?;
+            builder.set_certificate(&cert).map_err(error)?;
+            builder.set_private_key(&key).map_err(error)?;
+        }
+        // the anonymous suites need the lowest security level, a listener with a certificate keeps the default suites
+        (None, None) => {
+            builder.set_cipher_list("ADH:!eNULL:!EXPORT:!MD5:@STRENGTH:@SECLEVEL=0").map_err(error)?;
+            let dh = Dh::get_2048_256().map_err(error)?;
+            builder.set_tmp_dh(&dh).map_err(error)?;
+            builder.set_max_proto_version(Some(SslVersion::TLS1_2)).map_err(error)?;
+        }
+        _ => return Err("--nrpe-ssl-cert and --nrpe-ssl-key need to be given together".to_string()),
+    }
+    Ok(builder.build())
+}
+
+// Answers a request on the stream, after the TLS handshake if any
+fn answer<S: Read + Write>(stream: &mut S, handle: &(dyn Fn(&str) -> (String, i32) + Sync)) -> std::io::Result<()> {
+    let (version, command) = read_request(stream)?;
+    verbose::log(1, || format!("NRPE request for '{}'", command));
+    let (output, code) = handle(&command);
+    stream.write_all(&response(version, code, &output))?;
+    stream.flush()
+}
+
+fn connection(stream: TcpStream, tls: Option<&SslAcceptor>, allowed: &[IpAddr], handle: &(dyn Fn(&str) -> (String, i32) + Sync)) {
+    let peer = match stream.peer_addr() {
+        Ok(peer) => peer.ip(),
+        Err(_) => return,
+    };
+    // an IPv4 client of an IPv6 socket is allowed by its IPv4 address
+    let ip = match peer {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(peer),
+        v4 => v4,
+    };
+    if !allowed.is_empty() && !allowed.contains(&ip) {
+        verbose::log(1, || format!("NRPE connection from {} refused, it is not an allowed host", ip));
+        return;
+    }
+    // a client that does not send its request must not keep a thread
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+    let result = match tls {
+        Some(tls) => match tls.accept(&stream) {
+            Ok(mut stream) => answer(&mut stream, handle).map(|_| {
+                let _ = stream.shutdown();
+            }),
+            Err(err) => Err(invalid(&err.to_string())),
+        },
+        None => answer(&mut &stream, handle),
+    };
+    if let Err(err) = result {
+        verbose::log(1, || format!("NRPE request from {} failed: {}", ip, err));
+    }
+}
+
+// Answers requests until the program is stopped, each on a thread of its own. Connections from hosts not in
+// `allowed` are closed, unless it is empty. Only failing to listen returns.
+pub fn serve(address: &str, tls: Option<SslAcceptor>, allowed: &[IpAddr], handle: &(dyn Fn(&str) -> (String, i32) + Sync)) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|err| format!("Could not listen on {}: {}", address, err))?;
+    std::thread::scope(|scope| {
+        for stream in listener.incoming().flatten() {
+            let tls = tls.as_ref();
+            scope.spawn(move || connection(stream, tls, allowed, handle));
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,51 @@
+// Option lookup across several sources. The first source giving an option wins, so the command line takes precedence
+// over the selected check, which takes precedence over the configuration's defaults.
+
+use crate::arguments;
+use clap::ArgMatches;
+
+pub struct Options<'a> {
+    layers: Vec<ArgMatches<'a>>,
+}
+
+impl<'a> Options<'a> {
+    pub fn new(layers: Vec<ArgMatches<'a>>) -> Options<'a> {
+        Options { layers }
+    }
+
+    // Options given as arguments like those of the command line, e.g. by a program running checks itself
+    pub fn parse(arguments: &[&str]) -> Result<Options<'static>, String> {
+        Ok(Options::new(vec![arguments::parse(arguments.iter().map(|argument| argument.to_string()).collect())?]))
+    }
+
+    // The options with `name` set to `value`, e.g. to connect to another server like to the first
+    pub fn with(&self, name: &str, value: &str) -> Result<Options<'a>, String> {
+        let layer = arguments::parse(vec![format!("--{}", name), value.to_string()])?;
+        Ok(Options::new(std::iter::once(layer).chain(self.layers.iter().cloned()).collect()))
+    }
+
+    fn layer(&self, name: &str) -> Option<&ArgMatches<'a>> {
+        self.layers.iter().find(|matches| matches.occurrences_of(name) > 0)
+    }
+
+    pub fn value_of(&self, name: &str) -> Option<&str> {
+        self.layer(name).and_then(|matches| matches.value_of(name))
+    }
+
+    // All values of an option that may be given several times, from the first source giving it
+    pub fn values_of(&self, name: &str) -> Vec<&str> {
+        self.layer(name).and_then(|matches| matches.values_of(name)).map(|values| values.collect()).unwrap_or_default()
+    }
+
+    pub fn is_present(&self, name: &str) -> bool {
+        self.layer(name).is_some()
+    }
+
+    // For mutually exclusive options, returns the name and value of the one given by the first source
+    pub fn one_of(&self, names: &[&'static str]) -> Option<(&'static str, &str)> {
+        self.layers.iter()
+            .filter_map(|matches| names.iter().find(|&&name| matches.occurrences_of(name) > 0).map(|&name| (matches, name)))
+            .next()
+            .and_then(|(matches, name)| matches.value_of(name).map(|value| (name, value)))
+    }
+}
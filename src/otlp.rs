@@ -0,0 +1,178 @@
+// OpenTelemetry export with `--otlp-endpoint`: every run is sent to an OTLP/HTTP collector as a trace, a span over the
+// whole run with a child span for every phase of the run, like connecting and running a check, and its results as
+// gauges, `check_postgresql.status` with the exit code and `check_postgresql.<label>` for every perfdata value, the
+// check being an attribute of the data point. Like any OTLP exporter, the service name is `$OTEL_SERVICE_NAME` and
+// `$OTEL_EXPORTER_OTLP_HEADERS` are sent with the requests. The export runs on a thread of its own and ends by a
+// deadline, a failure is only logged.
+
+use crate::http;
+use crate::json::string;
+use crate::output::unique_names;
+use crate::perfdata::PerfData;
+use crate::status::{Status, StatusType};
+use crate::verbose;
+use crate::watchdog::Phase;
+use openssl::rand::rand_bytes;
+use std::env;
+use std::thread::JoinHandle;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const SCOPE: &str = "check_postgresql";
+
+#[derive(Clone)]
+pub struct Exporter {
+    // the base URL, the signals' paths are appended
+    endpoint: String,
+    cacert: Option<String>,
+    headers: Vec<(String, String)>,
+    resource: Vec<(String, String)>,
+}
+
+// A random trace or span ID of `bytes` bytes, in hex
+fn id(bytes: usize) -> String {
+    let mut id = vec![0u8; bytes];
+    // a failure leaves zeros, an invalid ID the collector refuses
+    let _ = rand_bytes(&mut id);
+    id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn nanos(time: SystemTime) -> String {
+    string(&time.duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0).to_string())
+}
+
+fn attributes(attributes: &[(&str, &str)]) -> String {
+    let attributes: Vec<String> = attributes.iter()
+        .map(|(key, value)| format!("{{\"key\":{},\"value\":{{\"stringValue\":{}}}}}", string(key), string(value)))
+        .collect();
+    format!("[{}]", attributes.join(","))
+}
+
+fn span(trace: &str, id: &str, parent: &str, name: &str, (start, end): (SystemTime, SystemTime), attrs: &[(&str, &str)], status: Option<&Status>) -> String {
+    // an OK check is an OK span, any other status an error
+    let status = match status {
+        None => "{}".to_string(),
+        Some(status) if status.t == StatusType::OK => "{\"code\":1}".to_string(),
+        Some(status) => format!("{{\"code\":2,\"message\":{}}}", string(&format!("{} - {}", status.t, status.description))),
+    };
+    // the run is internal, connecting and the checks are calls of the server
+    let kind = if parent.is_empty() { 1 } else { 3 };
+    format!("{{\"traceId\":{},\"spanId\":{},\"parentSpanId\":{},\"name\":{},\"kind\":{},\"startTimeUnixNano\":{},\"endTimeUnixNano\":{},\"attributes\":{},\"status\":{}}}",
+            string(trace), string(id), string(parent), string(name), kind, nanos(start), nanos(end), attributes(attrs), status)
+}
+
+// The worst status of the results, or that of the failure
+fn overall(results: &Result<Vec<(String, Status)>, Status>) -> Status {
+    match *results {
+        Ok(ref results) => {
+            let t = results.iter().fold(StatusType::OK, |t, (_, status)| t.worst(status.t));
+            let failed: Vec<&str> = results.iter().filter(|(_, status)| status.t == t).map(|(name, _)| name.as_str()).collect();
+            Status::new(t, failed.join(", "))
+        }
+        Err(ref status) => Status::new(status.t, status.description.clone()),
+    }
+}
+
+impl Exporter {
+    pub fn new(endpoint: &str, cacert: Option<&str>, host: &str, database: &str) -> Exporter {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let headers = var("OTEL_EXPORTER_OTLP_HEADERS").map(|headers| headers.split(',')
+            .filter_map(|header| header.split_once('='))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect()).unwrap_or_default();
+        let resource = vec![
+            ("service.name".to_string(), var("OTEL_SERVICE_NAME").unwrap_or_else(|| "check_postgresql".to_string())),
+            ("db.system".to_string(), "postgresql".to_string()),
+            ("server.address".to_string(), host.to_string()),
+            ("db.namespace".to_string(), database.to_string()),
+        ];
+        Exporter { endpoint: endpoint.trim_end_matches('/').to_string(), cacert: cacert.map(|cacert| cacert.to_string()), headers, resource }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    fn resource(&self) -> String {
+        let resource: Vec<(&str, &str)> = self.resource.iter().map(|(key, value)| (key.as_str(), value.as_str())).collect();
+        format!("{{\"attributes\":{}}}", attributes(&resource))
+    }
+
+    fn traces(&self, names: &[String], results: &Result<Vec<(String, Status)>, Status>, phases: &[Phase], (start, end): (SystemTime, SystemTime)) -> String {
+        let trace = id(16);
+        let root = id(8);
+        let checks = names.join(",");
+        let mut spans = vec![span(&trace, &root, "", "check_postgresql", (start, end), &[("checks", &checks)], Some(&overall(results)))];
+        for phase in phases {
+            // a check's span has its result, with all databases the combined one
+            let check = phase.name.strip_prefix("check ")
+                .and_then(|name| results.as_ref().ok()?.iter().find(|(check, _)| check == name));
+            let span = match check {
+                Some((name, status)) => {
+                    let t = status.t.to_string();
+                    span(&trace, &id(8), &root, &phase.name, (phase.start, phase.end),
+                         &[("check", name), ("status", &t), ("description", &status.description)], Some(status))
+                }
+                None => span(&trace, &id(8), &root, &phase.name, (phase.start, phase.end), &[], None),
+            };
+            spans.push(span);
+        }
+        format!("{{\"resourceSpans\":[{{\"resource\":{},\"scopeSpans\":[{{\"scope\":{{\"name\":{}}},\"spans\":[{}]}}]}}]}}",
+                self.resource(), string(SCOPE), spans.join(","))
+    }
+
+    fn metrics(&self, names: &[String], results: &Result<Vec<(String, Status)>, Status>, time: SystemTime) -> String {
+        let statuses: Vec<(&str, &Status)> = match *results {
+            Ok(ref results) => results.iter().map(|(name, status)| (name.as_str(), status)).collect(),
+            Err(ref status) => names.iter().map(|name| (name.as_str(), status)).collect(),
+        };
+        let time = nanos(time);
+        // the data points by metric, with its unit
+        let mut metrics: Vec<(String, String, Vec<String>)> = vec![];
+        let mut point = |metric: String, unit: &str, check: &str, value: f64| {
+            let point = format!("{{\"timeUnixNano\":{},\"asDouble\":{},\"attributes\":{}}}", time, value, attributes(&[("check", check)]));
+            match metrics.iter_mut().find(|(name, _, _)| *name == metric) {
+                Some((_, _, points)) => points.push(point),
+                None => metrics.push((metric, unit.to_string(), vec![point])),
+            }
+        };
+        for (name, status) in statuses {
+            point(format!("{}.status", SCOPE), "", name, status.t.exit_code() as f64);
+            let perfdata: Vec<&PerfData> = status.perfdata.iter().filter(|p| p.value.is_finite()).collect();
+            let labels = unique_names(perfdata.iter().map(|p| p.label.clone()), &["status"]);
+            for (perfdata, label) in perfdata.iter().zip(labels) {
+                point(format!("{}.{}", SCOPE, label), &perfdata.uom, name, perfdata.value);
+            }
+        }
+        let metrics: Vec<String> = metrics.iter().map(|(name, unit, points)| {
+            format!("{{\"name\":{},\"unit\":{},\"gauge\":{{\"dataPoints\":[{}]}}}}", string(name), string(unit), points.join(","))
+        }).collect();
+        format!("{{\"resourceMetrics\":[{{\"resource\":{},\"scopeMetrics\":[{{\"scope\":{{\"name\":{}}},\"metrics\":[{}]}}]}}]}}",
+                self.resource(), string(SCOPE), metrics.join(","))
+    }
+
+    fn post(&self, signal: &str, body: &str) -> Result<(), String> {
+        let url = format!("{}/v1/{}", self.endpoint, signal);
+        let mut headers: Vec<(&str, &str)> = vec![("Content-Type", "application/json")];
+        headers.extend(self.headers.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+        let response = http::request("POST", &url, &headers, Some(body), self.cacert.as_deref())?;
+        if response.status / 100 != 2 {
+            return Err(format!("HTTP status {} from {}: {}", response.status, url, response.body.lines().next().unwrap_or("")));
+        }
+        Ok(())
+    }
+
+    // Sends the results of the checks `names` of a run from `start` to `end` and the phases it went through. The
+    // requests are made on a thread of their own, which ends by `deadline`.
+    pub fn export(&self, names: &[String], results: &Result<Vec<(String, Status)>, Status>, phases: &[Phase], (start, end): (SystemTime, SystemTime), deadline: Instant) -> JoinHandle<()> {
+        let signals = [("traces", self.traces(names, results, phases, (start, end))), ("metrics", self.metrics(names, results, end))];
+        let exporter = self.clone();
+        std::thread::spawn(move || http::deadline(Some(deadline), || {
+            for (signal, body) in signals.iter() {
+                match exporter.post(signal, body) {
+                    Ok(()) => verbose::log(1, || format!("Exported the {} to {}", signal, exporter.endpoint)),
+                    Err(err) => verbose::log(1, || format!("Could not export the {}: {}", signal, err)),
+                }
+            }
+        }))
+    }
+}
@@ -0,0 +1,273 @@
+// The output formats of `--output`. `nagios` is the plugin output of the Nagios guidelines, the others present the
+// same results to other tools. The exit code is the Nagios one in every format.
+
+use crate::json::string;
+use crate::perfdata::PerfData;
+use crate::status::{Status, StatusType};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Format {
+    Nagios,
+    Json,
+    Checkmk,
+    // the host of the items, `-` for the one zabbix_sender is configured with
+    Zabbix(String),
+    // the host and database of the tags, empty until the connection is known
+    Influx(String, String),
+    // the prefix of the metric paths
+    Graphite(String),
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "nagios" => Ok(Format::Nagios),
+            "json" => Ok(Format::Json),
+            "checkmk" => Ok(Format::Checkmk),
+            "zabbix" => Ok(Format::Zabbix("-".to_string())),
+            "influx" => Ok(Format::Influx(String::new(), String::new())),
+            "graphite" => Ok(Format::Graphite("check_postgresql".to_string())),
+            _ => Err(format!("Invalid output format '{}'", s)),
+        }
+    }
+}
+
+// The timings of the whole run, which the first check carries. They are not prefixed when results are combined.
+pub const RUN_METRICS: [&str; 2] = ["connect_time", "total_time"];
+
+// Combines the results of several checks, the worst status wins. Descriptions are concatenated, perfdata labels and
+// lines of long output are prefixed with the name of their check. A single result is returned as is.
+pub fn combine(mut results: Vec<(String, Status)>) -> Status {
+    if results.len() == 1 {
+        return results.pop().unwrap().1;
+    }
+    let t = results.iter().fold(StatusType::OK, |t, (_, status)| t.worst(status.t));
+    let descriptions: Vec<String> = results.iter().map(|(name, status)| format!("{}: {} - {}", name, status.t, status.description)).collect();
+    let mut perfdata = vec![];
+    let mut long_output = vec![];
+    for (name, status) in results {
+        perfdata.extend(status.perfdata.into_iter().map(|p| if RUN_METRICS.contains(&p.label.as_str()) { p } else { p.prefix(&name) }));
+        long_output.extend(status.long_output.into_iter().map(|line| format!("{}: {}", name, line)));
+    }
+    Status { t, description: descriptions.join(", "), perfdata, long_output, query_time: None }
+}
+
+// A duration in seconds, rounded to milliseconds for the perfdata
+pub fn seconds(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0).round() / 1000.0
+}
+
+// JSON has no infinity or NaN, they are null
+fn number(value: Option<f64>) -> String {
+    match value {
+        Some(value) if value.is_finite() => value.to_string(),
+        _ => "null".to_string(),
+    }
+}
+
+fn optional<T: ToString>(value: Option<&T>) -> String {
+    value.map(|value| string(&value.to_string())).unwrap_or_else(|| "null".to_string())
+}
+
+fn perfdata(perfdata: &PerfData) -> String {
+    format!("{{\"label\":{},\"value\":{},\"uom\":{},\"warning\":{},\"critical\":{},\"min\":{},\"max\":{}}}",
+            string(&perfdata.label), number(Some(perfdata.value)), string(&perfdata.uom), optional(perfdata.warn.as_ref()),
+            optional(perfdata.crit.as_ref()), number(perfdata.min), number(perfdata.max))
+}
+
+fn check(name: &str, status: &Status) -> String {
+    let perfdata: Vec<String> = status.perfdata.iter().map(perfdata).collect();
+    let long_output: Vec<String> = status.long_output.iter().map(|line| string(line)).collect();
+    format!("{{\"name\":{},\"status\":{},\"exit_code\":{},\"description\":{},\"perfdata\":[{}],\"long_output\":[{}]}}",
+            string(name), string(&status.t.to_string()), status.t.exit_code(), string(&status.description),
+            perfdata.join(","), long_output.join(","))
+}
+
+// The overall status with every check's result. If the checks could not run, e.g. because the connection failed,
+// the list of checks is empty.
+fn json(results: &Result<Vec<(String, Status)>, Status>, duration: Duration) -> (String, StatusType) {
+    let (t, description, checks) = match *results {
+        Ok(ref results) => {
+            let t = results.iter().fold(StatusType::OK, |t, (_, status)| t.worst(status.t));
+            let descriptions: Vec<String> = results.iter().map(|(name, status)| format!("{}: {} - {}", name, status.t, status.description)).collect();
+            let description = if results.len() == 1 { results[0].1.description.clone() } else { descriptions.join(", ") };
+            (t, description, results.iter().map(|(name, status)| check(name, status)).collect())
+        }
+        Err(ref status) => (status.t, status.description.clone(), vec![]),
+    };
+    let document = format!("{{\"status\":{},\"exit_code\":{},\"description\":{},\"duration_seconds\":{},\"checks\":[{}]}}\n",
+                           string(&t.to_string()), t.exit_code(), string(&description), number(Some(duration.as_secs_f64())),
+                           checks.join(","));
+    (document, t)
+}
+
+// The metric names of a check's perfdata values, a name that repeats or is one of `taken` gets a suffix `_2`, `_3` and
+// so on, as a later value of the same name would replace the earlier one
+pub(crate) fn unique_names<I: IntoIterator<Item = String>>(names: I, taken: &[&str]) -> Vec<String> {
+    let mut unique: Vec<String> = taken.iter().map(|name| name.to_string()).collect();
+    for name in names {
+        let name = std::iter::once(name.clone()).chain((2..).map(|i| format!("{}_{}", name, i)))
+            .find(|candidate| !unique.contains(candidate)).unwrap();
+        unique.push(name);
+    }
+    unique.split_off(taken.len())
+}
+
+// Checkmk's service and metric names, anything but letters, digits, `-` and `_` is replaced
+fn checkmk_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect()
+}
+
+// A line of a Checkmk local check, `<status> <service> <metrics> <text>`. Checkmk's levels are upper bounds, so
+// thresholds that are not are left out. Long output follows the text with escaped line breaks.
+fn checkmk_line(name: &str, status: &Status) -> String {
+    let names = unique_names(status.perfdata.iter().map(|p| checkmk_name(&p.label)), &[]);
+    let metrics: Vec<String> = status.perfdata.iter().zip(names).map(|(p, name)| {
+        let levels = [p.warn.as_ref().and_then(|r| r.upper()), p.crit.as_ref().and_then(|r| r.upper()), p.min, p.max];
+        let mut fields: Vec<String> = levels.iter().map(|level| level.map(|l| l.to_string()).unwrap_or_default()).collect();
+        while fields.last().is_some_and(|field| field.is_empty()) {
+            fields.pop();
+        }
+        std::iter::once(format!("{}={}", name, p.value)).chain(fields).collect::<Vec<String>>().join(";")
+    }).collect();
+    let metrics = if metrics.is_empty() { "-".to_string() } else { metrics.join("|") };
+    let text = std::iter::once(status.description.as_str()).chain(status.long_output.iter().map(|line| line.as_str()))
+        .map(|line| line.replace('\\', "\\\\").replace('\n', "\\n")).collect::<Vec<String>>().join("\\n");
+    format!("{} {} {} {}\n", status.t.exit_code(), checkmk_name(name), metrics, text)
+}
+
+// One line per check. If the checks could not run, every check has the status of the failure.
+fn checkmk(names: &[String], results: &Result<Vec<(String, Status)>, Status>) -> (String, StatusType) {
+    match *results {
+        Ok(ref results) => (results.iter().map(|(name, status)| checkmk_line(name, status)).collect(),
+                            results.iter().fold(StatusType::OK, |t, (_, status)| t.worst(status.t))),
+        Err(ref status) => (names.iter().map(|name| checkmk_line(name, status)).collect(), status.t),
+    }
+}
+
+// A parameter of an item key, quoted if it contains characters of the key syntax
+fn zabbix_parameter(parameter: &str) -> String {
+    if parameter.contains(|c: char| c == ',' || c == ']' || c == '"' || c == '[' || c.is_whitespace()) || parameter.is_empty() {
+        format!("\"{}\"", parameter.replace('"', "\\\""))
+    } else {
+        parameter.to_string()
+    }
+}
+
+// A field of zabbix_sender's input, quoted if it contains spaces or quotes
+fn zabbix_field(field: &str) -> String {
+    if field.contains(|c: char| c == '"' || c == '\\' || c.is_whitespace()) || field.is_empty() {
+        format!("\"{}\"", field.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+    } else {
+        field.to_string()
+    }
+}
+
+// The input of `zabbix_sender -i -`, `<host> <key> <value>` per line. Every check results in the items
+// `check_postgresql.status[<check>]` with its exit code, `check_postgresql.text[<check>]` with its description and
+// `check_postgresql.metric[<check>,<label>]` for every perfdata value, a label that repeats gets a suffix. If the checks could not run, every check has
+// the status and description of the failure.
+fn zabbix(host: &str, names: &[String], results: &Result<Vec<(String, Status)>, Status>) -> (String, StatusType) {
+    let mut out = String::new();
+    let mut item = |key: String, value: String| out += &format!("{} {} {}\n", zabbix_field(host), zabbix_field(&key), zabbix_field(&value));
+    let statuses: Vec<(&str, &Status)> = match *results {
+        Ok(ref results) => results.iter().map(|(name, status)| (name.as_str(), status)).collect(),
+        Err(ref status) => names.iter().map(|name| (name.as_str(), status)).collect(),
+    };
+    let mut t = StatusType::OK;
+    for (name, status) in statuses {
+        t = t.worst(status.t);
+        item(format!("check_postgresql.status[{}]", zabbix_parameter(name)), status.t.exit_code().to_string());
+        item(format!("check_postgresql.text[{}]", zabbix_parameter(name)), status.description.clone());
+        let labels = unique_names(status.perfdata.iter().map(|p| p.label.clone()), &[]);
+        for (perfdata, label) in status.perfdata.iter().zip(labels) {
+            item(format!("check_postgresql.metric[{},{}]", zabbix_parameter(name), zabbix_parameter(&label)), perfdata.value.to_string());
+        }
+    }
+    (out, t)
+}
+
+// Escapes a tag key or value or a field key of the line protocol, which cannot have line breaks
+fn influx_escape(s: &str) -> String {
+    s.replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ").replace('\n', " ")
+}
+
+// InfluxDB's line protocol, a line of the measurement `check_postgresql` per check, tagged with the check, host and
+// database. Its fields are the exit code as `status` and every perfdata value by its label, a label that repeats gets
+// a suffix `_2`, `_3` and so on. If the checks could not run, every check has the status of the failure.
+fn influx(host: &str, database: &str, names: &[String], results: &Result<Vec<(String, Status)>, Status>) -> (String, StatusType) {
+    let statuses: Vec<(&str, &Status)> = match *results {
+        Ok(ref results) => results.iter().map(|(name, status)| (name.as_str(), status)).collect(),
+        Err(ref status) => names.iter().map(|name| (name.as_str(), status)).collect(),
+    };
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+    let mut tags = String::new();
+    for (key, value) in [("host", host), ("database", database)] {
+        if !value.is_empty() {
+            tags += &format!(",{}={}", key, influx_escape(value));
+        }
+    }
+    let mut out = String::new();
+    let mut t = StatusType::OK;
+    for (name, status) in statuses {
+        t = t.worst(status.t);
+        let mut fields = vec![format!("status={}i", status.t.exit_code())];
+        // the line protocol has no infinity or NaN
+        let perfdata: Vec<&PerfData> = status.perfdata.iter().filter(|p| p.value.is_finite()).collect();
+        let keys = unique_names(perfdata.iter().map(|p| p.label.clone()), &["status"]);
+        fields.extend(perfdata.iter().zip(keys).map(|(p, key)| format!("{}={}", influx_escape(&key), p.value)));
+        out += &format!("check_postgresql,check={}{} {} {}\n", influx_escape(name), tags, fields.join(","), timestamp);
+    }
+    (out, t)
+}
+
+// A node of a Graphite metric path, anything but letters, digits, `-` and `_` is replaced as dots separate the nodes
+pub(crate) fn graphite_node(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' }).collect()
+}
+
+// Graphite's plaintext protocol, `<path> <value> <timestamp>` per line. Every check results in `<prefix>.<check>.status`
+// with its exit code and `<prefix>.<check>.<label>` for every perfdata value, a label that repeats gets a suffix. If the
+// checks could not run, every check has the status of the failure.
+fn graphite(prefix: &str, names: &[String], results: &Result<Vec<(String, Status)>, Status>) -> (String, StatusType) {
+    let statuses: Vec<(&str, &Status)> = match *results {
+        Ok(ref results) => results.iter().map(|(name, status)| (name.as_str(), status)).collect(),
+        Err(ref status) => names.iter().map(|name| (name.as_str(), status)).collect(),
+    };
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let mut out = String::new();
+    let mut t = StatusType::OK;
+    for (name, status) in statuses {
+        t = t.worst(status.t);
+        let path = format!("{}.{}", prefix.trim_end_matches('.'), graphite_node(name));
+        out += &format!("{}.status {} {}\n", path, status.t.exit_code(), timestamp);
+        let perfdata: Vec<&PerfData> = status.perfdata.iter().filter(|p| p.value.is_finite()).collect();
+        let nodes = unique_names(perfdata.iter().map(|p| graphite_node(&p.label)), &["status"]);
+        for (perfdata, node) in perfdata.iter().zip(nodes) {
+            out += &format!("{}.{} {} {}\n", path, node, perfdata.value, timestamp);
+        }
+    }
+    (out, t)
+}
+
+// Renders the results of the checks `names` in `format`, returns the text and the status that determines the exit code
+pub fn render(format: &Format, names: &[String], mut results: Result<Vec<(String, Status)>, Status>, duration: Duration) -> (String, StatusType) {
+    if let Some((_, status)) = results.as_mut().ok().and_then(|results| results.first_mut()) {
+        status.perfdata.push(PerfData::new("total_time", seconds(duration)).uom("s").min(Some(0.0)));
+    }
+    match *format {
+        Format::Nagios => {
+            let status = results.map(combine).unwrap_or_else(|status| status);
+            (status.to_string(), status.t)
+        }
+        Format::Json => json(&results, duration),
+        Format::Checkmk => checkmk(names, &results),
+        Format::Zabbix(ref host) => zabbix(host, names, &results),
+        Format::Influx(ref host, ref database) => influx(host, database, names, &results),
+        Format::Graphite(ref prefix) => graphite(prefix, names, &results),
+    }
+}
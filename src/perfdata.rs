@@ -0,0 +1,103 @@
+// Performance data as defined by the Nagios plugin development guidelines:
+//
+//   'label'=value[UOM];[warn];[crit];[min];[max]
+//
+// Trailing empty fields are omitted. Labels containing spaces, `=` or quotes are put in single quotes,
+// with single quotes inside the label doubled.
+
+use std::fmt;
+use crate::threshold::Range;
+
+#[derive(Clone, Debug)]
+pub struct PerfData {
+    pub label: String,
+    pub value: f64,
+    pub uom: String,
+    pub warn: Option<Range>,
+    pub crit: Option<Range>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl PerfData {
+    pub fn new(label: &str, value: f64) -> PerfData {
+        PerfData {
+            label: label.to_string(),
+            value,
+            uom: String::new(),
+            warn: None,
+            crit: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    // Prepends `prefix_` to the label, to tell apart the metrics of several checks
+    pub fn prefix(mut self, prefix: &str) -> PerfData {
+        self.label = format!("{}_{}", prefix, self.label);
+        self
+    }
+
+    pub fn uom(mut self, uom: &str) -> PerfData {
+        self.uom = uom.to_string();
+        self
+    }
+
+    pub fn warn(mut self, warn: Option<&Range>) -> PerfData {
+        self.warn = warn.cloned();
+        self
+    }
+
+    pub fn crit(mut self, crit: Option<&Range>) -> PerfData {
+        self.crit = crit.cloned();
+        self
+    }
+
+    pub fn min(mut self, min: Option<f64>) -> PerfData {
+        self.min = min;
+        self
+    }
+
+    pub fn max(mut self, max: Option<f64>) -> PerfData {
+        self.max = max;
+        self
+    }
+}
+
+fn quote_label(label: &str) -> String {
+    if label.contains(|c: char| c.is_whitespace() || c == '=' || c == '\'' || c == '"') {
+        format!("'{}'", label.replace('\'', "''"))
+    } else {
+        label.to_string()
+    }
+}
+
+impl fmt::Display for PerfData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut fields = vec![
+            self.warn.as_ref().map(|r| r.to_string()).unwrap_or_default(),
+            self.crit.as_ref().map(|r| r.to_string()).unwrap_or_default(),
+            self.min.map(|m| m.to_string()).unwrap_or_default(),
+            self.max.map(|m| m.to_string()).unwrap_or_default(),
+        ];
+        while fields.last().is_some_and(|field| field.is_empty()) {
+            fields.pop();
+        }
+
+        write!(f, "{}={}{}", quote_label(&self.label), self.value, self.uom)?;
+        for field in fields {
+            write!(f, ";{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+// Parses an optional comma separated list of numbers, e.g. `0,,100`. Empty entries are `None`.
+pub fn parse_limits(s: &str) -> Result<Vec<Option<f64>>, String> {
+    s.split(',')
+        .map(|v| match v.trim() {
+            "" => Ok(None),
+            v => v.parse::<f64>().map(Some).map_err(|_| format!("Invalid perfdata limit '{}'", v)),
+        })
+        .collect()
+}
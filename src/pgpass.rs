@@ -0,0 +1,62 @@
+// Password lookup in a libpq password file, `~/.pgpass` by default. Each line has the format
+//
+//   hostname:port:database:username:password
+//
+// where the first four fields may be `*` to match anything, and `:` or `\` are escaped with a backslash. The first
+// matching line wins. Like libpq, the file is ignored unless it is only accessible by its owner.
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+pub struct Target<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub database: &'a str,
+    pub user: &'a str,
+}
+
+// `$PGPASSFILE` or `~/.pgpass`
+pub fn default_path() -> Option<PathBuf> {
+    match env::var_os("PGPASSFILE") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".pgpass")),
+    }
+}
+
+// Splits a line at unescaped colons, removing the escapes
+fn fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) => fields.last_mut().unwrap().push(c),
+                None => fields.last_mut().unwrap().push('\\'),
+            },
+            ':' => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    fields
+}
+
+pub fn lookup(path: &Path, target: &Target) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.permissions().mode() & 0o077 != 0 {
+        return None;
+    }
+    let content = fs::read_to_string(path).ok()?;
+
+    let port = target.port.to_string();
+    content.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(fields)
+        .filter(|fields| fields.len() >= 5)
+        .find(|fields| {
+            [target.host, &port, target.database, target.user].iter().zip(fields.iter())
+                .all(|(value, field)| field == "*" || field == value)
+        })
+        .map(|fields| fields[4..].join(":"))
+}
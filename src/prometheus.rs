@@ -0,0 +1,106 @@
+// A Prometheus exporter: with `--listen`, the program stays resident and runs its checks whenever `/metrics` is
+// scraped. Every scrape connects anew and renders the results in Prometheus' text exposition format, the status of
+// every check as its Nagios exit code and every perfdata value as a gauge labeled with the check and the metric.
+
+use crate::output::unique_names;
+use crate::status::{Status, StatusType};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+// Escapes a label value, backslashes, quotes and line breaks need to be escaped
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// A sample's value, Prometheus spells infinity and NaN its own way
+fn sample(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// Renders the results of a scrape. If the checks could not run, e.g. because the connection failed, every check has
+// the status of the failure and `check_postgresql_up` is 0.
+pub fn render(names: &[String], results: &Result<Vec<(String, Status)>, Status>, duration: f64) -> String {
+    let mut out = String::new();
+    out += "# HELP check_postgresql_up Whether the checks could run on the database.\n";
+    out += "# TYPE check_postgresql_up gauge\n";
+    out += &format!("check_postgresql_up {}\n", if results.is_ok() { 1 } else { 0 });
+    out += "# HELP check_postgresql_scrape_duration_seconds Time it took to connect and run all checks.\n";
+    out += "# TYPE check_postgresql_scrape_duration_seconds gauge\n";
+    out += &format!("check_postgresql_scrape_duration_seconds {}\n", sample(duration));
+
+    out += "# HELP check_postgresql_status Status of the check as Nagios exit code: 0 OK, 1 WARNING, 2 CRITICAL, 3 UNKNOWN.\n";
+    out += "# TYPE check_postgresql_status gauge\n";
+    let statuses: Vec<(&str, StatusType)> = match *results {
+        Ok(ref results) => results.iter().map(|(name, status)| (name.as_str(), status.t)).collect(),
+        Err(ref status) => names.iter().map(|name| (name.as_str(), status.t)).collect(),
+    };
+    for (name, t) in statuses {
+        out += &format!("check_postgresql_status{{check=\"{}\"}} {}\n", escape(name), t.exit_code());
+    }
+
+    if let Ok(ref results) = *results {
+        out += "# HELP check_postgresql_metric Performance data of the check.\n";
+        out += "# TYPE check_postgresql_metric gauge\n";
+        for (name, status) in results {
+            // a series must not repeat, a label that does gets a suffix
+            let metrics = unique_names(status.perfdata.iter().map(|p| p.label.clone()), &[]);
+            for (perfdata, metric) in status.perfdata.iter().zip(metrics) {
+                out += &format!("check_postgresql_metric{{check=\"{}\",metric=\"{}\",uom=\"{}\"}} {}\n",
+                                escape(name), escape(&metric), escape(&perfdata.uom), sample(perfdata.value));
+            }
+        }
+    }
+    out
+}
+
+fn respond(mut stream: &TcpStream, status: &str, content_type: &str, body: &str) -> std::io::Result<()> {
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           status, content_type, body.len(), body)?;
+    stream.flush()
+}
+
+// Answers one request, only `GET /metrics` runs the checks
+fn handle(stream: TcpStream, names: &[String], scrape: &dyn Fn() -> Result<Vec<(String, Status)>, Status>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // the headers are not needed, but are read so the client does not see a reset connection
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    match (method, path.split('?').next().unwrap_or("")) {
+        ("GET", "/metrics") => {
+            let start = Instant::now();
+            let results = scrape();
+            let body = render(names, &results, start.elapsed().as_secs_f64());
+            respond(&stream, "200 OK", "text/plain; version=0.0.4; charset=utf-8", &body)
+        }
+        ("GET", "/") => respond(&stream, "200 OK", "text/html; charset=utf-8",
+                                "<html><body><h1>check_postgresql</h1><a href=\"/metrics\">Metrics</a></body></html>\n"),
+        ("GET", _) => respond(&stream, "404 Not Found", "text/plain; charset=utf-8", "Not found\n"),
+        _ => respond(&stream, "405 Method Not Allowed", "text/plain; charset=utf-8", "Method not allowed\n"),
+    }
+}
+
+// Serves scrapes one after another until the program is stopped. Only failing to listen returns.
+pub fn serve(address: &str, names: &[String], scrape: &dyn Fn() -> Result<Vec<(String, Status)>, Status>) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|err| format!("Could not listen on {}: {}", address, err))?;
+    // a client that went away does not concern the next one
+    for stream in listener.incoming().flatten() {
+        // scrapes are answered one at a time, a client that does not send its request must not block the others
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+        let _ = handle(stream, names, scrape);
+    }
+    Ok(())
+}
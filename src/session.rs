@@ -0,0 +1,224 @@
+// A connection to the server together with the settings that apply to every query on it. Query errors are turned
+// into the status the plugin exits with.
+
+use tokio_postgres::error::SqlState;
+use tokio_postgres::types::{FromSql, ToSql, Type};
+use tokio_postgres::{Client, Row, SimpleQueryMessage, SimpleQueryRow};
+use crate::encoding::Encoding;
+use crate::state::{State, StateDir};
+use crate::status::{Status, StatusType};
+use std::error::Error;
+use std::time::{Duration, Instant};
+use crate::value::Value;
+use crate::verbose;
+
+// postgres' errors only describe their kind, the details are in the chain of sources. Server messages may span
+// multiple lines, but the status line must not.
+pub fn describe(err: &dyn Error) -> String {
+    let mut description = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        description = description + ": " + &err.to_string();
+        source = err.source();
+    }
+    description.lines().map(|l| l.trim()).collect::<Vec<_>>().join(" ")
+}
+
+pub struct Session {
+    client: Client,
+    // the session's statement_timeout and the status if it elapses
+    statement_timeout: Option<(Duration, StatusType)>,
+    encoding: Encoding,
+    state_dir: StateDir,
+    // the host and database as configured, for the output
+    host: String,
+    dbname: String,
+}
+
+impl Session {
+    // Sets the session's statement_timeout and client_encoding on the server
+    pub async fn new(client: Client, statement_timeout: Option<(Duration, StatusType)>, encoding: Encoding, state_dir: StateDir,
+                     host: &str, dbname: &str) -> Result<Session, Status> {
+        let mut session = Session { client, statement_timeout, encoding, state_dir, host: host.to_string(), dbname: dbname.to_string() };
+        if let Some((timeout, _)) = statement_timeout {
+            session.set_statement_timeout(Some(timeout)).await?;
+        }
+        if encoding != Encoding::Utf8 {
+            session.client.batch_execute(&format!("SET client_encoding = '{}'", encoding.name())).await
+                .map_err(|err| Status::new(StatusType::UNKNOWN, describe(&err)))?;
+        }
+        Ok(session)
+    }
+
+    // The encoding text of this session's results is in
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    // Whether the connection was closed, e.g. by a restart of the server
+    pub fn is_closed(&self) -> bool {
+        self.client.is_closed()
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn dbname(&self) -> &str {
+        &self.dbname
+    }
+
+    // The values `check` stored in the previous run against this server
+    pub fn state(&self, check: &str) -> Result<State, Status> {
+        Ok(self.state_dir.open(check)?)
+    }
+
+    fn error(&self, err: tokio_postgres::Error) -> Status {
+        match self.statement_timeout {
+            Some((timeout, status)) if err.code() == Some(&SqlState::QUERY_CANCELED) => Status::new(status,
+                format!("Query cancelled after {}s statement timeout", timeout.as_secs_f64())),
+            _ => Status::new(StatusType::UNKNOWN, describe(&err)),
+        }
+    }
+
+    pub async fn query(&mut self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Status> {
+        log_query(sql, params);
+        let start = Instant::now();
+        let rows = self.client.query(sql, params).await.map_err(|err| self.error(err))?;
+        log_rows(&rows, start);
+        Ok(rows)
+    }
+
+    // Like `query`, but cancelled after `timeout` unless the session's statement_timeout is shorter, e.g. for a query
+    // that may run long on a large table
+    pub async fn query_timeout(&mut self, sql: &str, params: &[&(dyn ToSql + Sync)], timeout: Duration) -> Result<Vec<Row>, Status> {
+        if self.statement_timeout.is_some_and(|(session, _)| session <= timeout) {
+            return self.query(sql, params).await;
+        }
+        self.set_statement_timeout(Some(timeout)).await?;
+        log_query(sql, params);
+        let start = Instant::now();
+        let result = self.client.query(sql, params).await;
+        if let Ok(ref rows) = result {
+            log_rows(rows, start);
+        }
+        self.set_statement_timeout(self.statement_timeout.map(|(session, _)| session)).await?;
+        result.map_err(|err| match err.code() {
+            Some(&SqlState::QUERY_CANCELED) => Status::new(StatusType::UNKNOWN,
+                format!("Query cancelled after {}s timeout", timeout.as_secs_f64())),
+            _ => Status::new(StatusType::UNKNOWN, describe(&err)),
+        })
+    }
+
+    async fn set_statement_timeout(&mut self, timeout: Option<Duration>) -> Result<(), Status> {
+        let sql = match timeout {
+            // statement_timeout is in milliseconds, 0 would disable it
+            Some(timeout) => format!("SET statement_timeout = {}", timeout.as_millis().max(1)),
+            None => "RESET statement_timeout".to_string(),
+        };
+        self.client.batch_execute(&sql).await.map_err(|err| Status::new(StatusType::UNKNOWN, describe(&err)))
+    }
+
+    // Runs `sql` with the simple query protocol, which returns every value as text. Unlike `query`, it works with
+    // servers that cannot prepare statements, like PgBouncer's admin console.
+    pub async fn simple_query(&mut self, sql: &str) -> Result<Vec<SimpleQueryRow>, Status> {
+        log_query(sql, &[]);
+        let start = Instant::now();
+        let messages = self.client.simple_query(sql).await.map_err(|err| self.error(err))?;
+        let rows: Vec<SimpleQueryRow> = messages.into_iter().filter_map(|message| match message {
+            SimpleQueryMessage::Row(row) => Some(row),
+            _ => None,
+        }).collect();
+        verbose::log(2, || format!("Returned {} rows in {:.3}s", rows.len(), start.elapsed().as_secs_f64()));
+        for row in &rows {
+            verbose::log(3, || format!("Row: ({})", (0..row.len()).map(|j| row.try_get(j).unwrap_or(Some("?")).unwrap_or("NULL")).collect::<Vec<&str>>().join(",")));
+        }
+        Ok(rows)
+    }
+
+    // Runs `sql` with parameters given as text, e.g. on the command line. Each is converted to the type given in
+    // `types` or, if missing, the one the server infers for it. Returns the names of the columns too, which an empty
+    // result has no rows to tell.
+    pub async fn query_params(&mut self, sql: &str, types: &[Type], params: &[String]) -> Result<(Vec<String>, Vec<Row>), Status> {
+        let statement = self.client.prepare_typed(sql, types).await.map_err(|err| self.error(err))?;
+        if statement.params().len() != params.len() {
+            return Err(Status::new(StatusType::UNKNOWN,
+                format!("Query has {} parameters, but {} were given", statement.params().len(), params.len())));
+        }
+        let values = statement.params().iter().zip(params).enumerate().map(|(i, (t, param))| text_param(param, t, i + 1))
+            .collect::<Result<Vec<_>, _>>()?;
+        let values: Vec<&(dyn ToSql + Sync)> = values.iter().map(|value| value.as_ref() as &(dyn ToSql + Sync)).collect();
+        log_query(sql, &values);
+        let start = Instant::now();
+        let rows = self.client.query(&statement, &values).await.map_err(|err| self.error(err))?;
+        log_rows(&rows, start);
+        Ok((statement.columns().iter().map(|column| column.name().to_string()).collect(), rows))
+    }
+
+    // Like `query`, for queries returning exactly one row
+    pub async fn query_one(&mut self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Row, Status> {
+        let mut rows = self.query(sql, params).await?;
+        match rows.len() {
+            1 => Ok(rows.remove(0)),
+            n => Err(Status::new(StatusType::UNKNOWN, format!("Query returned {} rows instead of one", n))),
+        }
+    }
+
+    // Returns `now()` and `localtimestamp` of the server as microseconds since 2000-01-01
+    pub async fn clock(&mut self) -> Result<(i64, i64), Status> {
+        let row = self.query_one("SELECT now(), localtimestamp", &[]).await?;
+        match (column::<Value>(&row, 0)?, column::<Value>(&row, 1)?) {
+            (Value::TimestampTz(now), Value::Timestamp(local_now)) => Ok((now, local_now)),
+            _ => Err(Status::new(StatusType::UNKNOWN, "Could not read the server's clock".to_string())),
+        }
+    }
+}
+
+// The `n`th parameter given as text as a value of type `t`. Types without a counterpart here need a cast in the query.
+fn text_param(param: &str, t: &Type, n: usize) -> Result<Box<dyn ToSql + Send + Sync>, Status> {
+    let invalid = || Status::new(StatusType::UNKNOWN, format!("Invalid value '{}' for parameter ${} of type {}", param, n, t));
+    Ok(match *t {
+        Type::BOOL => Box::new(match param.to_lowercase().as_str() {
+            "t" | "true" | "on" | "yes" | "y" | "1" => true,
+            "f" | "false" | "off" | "no" | "n" | "0" => false,
+            _ => return Err(invalid()),
+        }),
+        Type::INT2 => Box::new(param.parse::<i16>().map_err(|_| invalid())?),
+        Type::INT4 => Box::new(param.parse::<i32>().map_err(|_| invalid())?),
+        Type::INT8 => Box::new(param.parse::<i64>().map_err(|_| invalid())?),
+        Type::OID => Box::new(param.parse::<u32>().map_err(|_| invalid())?),
+        Type::FLOAT4 => Box::new(param.parse::<f32>().map_err(|_| invalid())?),
+        Type::FLOAT8 => Box::new(param.parse::<f64>().map_err(|_| invalid())?),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => Box::new(param.to_string()),
+        _ => return Err(Status::new(StatusType::UNKNOWN,
+            format!("Parameter ${} is of type {}, which needs a cast in the query, e.g. ${}::text::{}", n, t, n, t))),
+    })
+}
+
+fn log_query(sql: &str, params: &[&(dyn ToSql + Sync)]) {
+    match params.len() {
+        0 => verbose::log(2, || format!("Query: {}", sql)),
+        _ => verbose::log(2, || format!("Query: {} with parameters {:?}", sql, params)),
+    }
+}
+
+fn log_rows(rows: &[Row], start: Instant) {
+    verbose::log(2, || format!("Returned {} rows in {:.3}s", rows.len(), start.elapsed().as_secs_f64()));
+    for row in rows {
+        verbose::log(3, || {
+            let values: Vec<String> = (0..row.len()).map(|j| column::<Value>(row, j).map(|v| v.to_string()).unwrap_or_else(|_| "?".to_string())).collect();
+            format!("Row: ({})", values.join(","))
+        });
+    }
+}
+
+// Reads a column, a type mismatch results in UNKNOWN instead of a panic
+pub fn column<'a, T: FromSql<'a>>(row: &'a Row, idx: usize) -> Result<T, Status> {
+    row.try_get(idx).map_err(|err| Status::new(StatusType::UNKNOWN, format!("Column {}: {}", idx + 1, describe(&err))))
+}
+
+// Reads a column of the simple query protocol, which has no other encoding than UTF-8. Text in another one results in
+// UNKNOWN instead of a panic.
+pub fn simple_column(row: &SimpleQueryRow, idx: usize) -> Result<Option<&str>, Status> {
+    row.try_get(idx).map_err(|err| Status::new(StatusType::UNKNOWN, format!("Column {}: {}", idx + 1, describe(&err))))
+}
@@ -0,0 +1,109 @@
+// Values a check keeps between runs, e.g. counters to report their increase since the previous run instead of since
+// the server started. Every check of a server has its own file in the state directory, named after the server and
+// the check. Each line holds a key, its value and the time it was recorded:
+//
+//   failed_count 3 1700000000.25
+//
+// Spaces, line breaks and `%` in keys, e.g. database names, are percent-encoded. Keys not recorded by a run are kept,
+// so runs with different options do not lose each other's values. The file is replaced atomically, so concurrent runs
+// see either the old or the new state.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn escape(key: &str) -> String {
+    key.chars().map(|c| if c.is_ascii_whitespace() || c == '%' { format!("%{:02X}", c as u32) } else { c.to_string() }).collect()
+}
+
+fn unescape(key: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = key;
+    while let Some(start) = rest.find('%') {
+        unescaped.push_str(&rest[..start]);
+        match rest.get(start + 1..start + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[start + 3..];
+            }
+            None => {
+                unescaped.push('%');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    unescaped + rest
+}
+
+pub struct StateDir {
+    dir: PathBuf,
+    // identifies the server, e.g. `db1:5432/app`
+    server: String,
+}
+
+impl StateDir {
+    pub fn new(dir: &Path, server: &str) -> StateDir {
+        StateDir { dir: dir.to_path_buf(), server: server.to_string() }
+    }
+
+    // Loads the state of `check`, which is empty on the first run
+    pub fn open(&self, check: &str) -> Result<State, String> {
+        let name: String = format!("{}_{}", self.server, check).chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect();
+        let path = self.dir.join(name + ".state");
+        let mut values = BTreeMap::new();
+        match fs::read_to_string(&path) {
+            Ok(content) => for line in content.lines() {
+                let fields: Vec<&str> = line.rsplitn(3, ' ').collect();
+                if let [time, value, key] = fields[..] {
+                    if let (Ok(value), Ok(time)) = (value.parse(), time.parse()) {
+                        values.insert(unescape(key), (value, time));
+                    }
+                }
+            },
+            Err(ref err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(format!("Could not read state file '{}': {}", path.display(), err)),
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs_f64()).unwrap_or(0.0);
+        Ok(State { path, now, previous: values.clone(), values })
+    }
+}
+
+pub struct State {
+    path: PathBuf,
+    now: f64,
+    previous: BTreeMap<String, (f64, f64)>,
+    values: BTreeMap<String, (f64, f64)>,
+}
+
+impl State {
+    pub fn set(&mut self, key: &str, value: f64) {
+        self.values.insert(key.to_string(), (value, self.now));
+    }
+
+    // Records the counter `key` and returns its increase since the previous run, `None` on the first run. A counter
+    // that decreased was reset in between, so it increased by its current value.
+    pub fn delta(&mut self, key: &str, value: f64) -> Option<f64> {
+        self.set(key, value);
+        self.previous.get(key).map(|&(previous, _)| if value >= previous { value - previous } else { value })
+    }
+
+    // Like `delta`, but per second since the previous run, `None` if no time passed either
+    pub fn rate(&mut self, key: &str, value: f64) -> Option<f64> {
+        let seconds = self.previous.get(key).map(|&(_, time)| self.now - time).filter(|&seconds| seconds > 0.0);
+        self.delta(key, value).and_then(|increase| seconds.map(|seconds| increase / seconds))
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let describe = |err: std::io::Error| format!("Could not write state file '{}': {}", self.path.display(), err);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(describe)?;
+        }
+        let content: String = self.values.iter().map(|(key, &(value, time))| format!("{} {} {}\n", escape(key), value, time)).collect();
+        let temporary = self.path.with_extension(format!("state.{}", std::process::id()));
+        fs::write(&temporary, content).map_err(describe)?;
+        fs::rename(&temporary, &self.path).map_err(describe)
+    }
+}
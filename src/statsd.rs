@@ -0,0 +1,81 @@
+// StatsD emission alongside the plugin's output: with `--statsd-host`, every run also sends its perfdata as gauges over
+// UDP, `<prefix>.<check>.<label>:<value>|g`, and `<prefix>.<check>.status` with the exit code of every check. Sending
+// never changes the result of the checks, a failure is only logged.
+
+use crate::output::{graphite_node, seconds, unique_names};
+use crate::perfdata::PerfData;
+use crate::status::Status;
+use crate::verbose;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const PORT: u16 = 8125;
+// keeps a packet below the common MTU, so it is not fragmented
+const PACKET: usize = 1432;
+
+// The gauges of the results of the checks `names`
+fn gauges(prefix: &str, names: &[String], results: &Result<Vec<(String, Status)>, Status>, duration: Duration) -> Vec<String> {
+    let statuses: Vec<(&str, &Status)> = match *results {
+        Ok(ref results) => results.iter().map(|(name, status)| (name.as_str(), status)).collect(),
+        Err(ref status) => names.iter().map(|name| (name.as_str(), status)).collect(),
+    };
+    let prefix = prefix.trim_end_matches('.');
+    let mut gauges = vec![];
+    for (name, status) in statuses {
+        let path = format!("{}.{}", prefix, graphite_node(name));
+        gauges.push(format!("{}.status:{}|g", path, status.t.exit_code()));
+        let perfdata: Vec<&PerfData> = status.perfdata.iter().filter(|p| p.value.is_finite()).collect();
+        let nodes = unique_names(perfdata.iter().map(|p| graphite_node(&p.label)), &["status"]);
+        for (perfdata, node) in perfdata.iter().zip(nodes) {
+            // a signed value changes the gauge, a negative one is set by resetting it first, in the same packet
+            if perfdata.value < 0.0 {
+                gauges.push(format!("{0}.{1}:0|g\n{0}.{1}:{2}|g", path, node, perfdata.value));
+            } else {
+                gauges.push(format!("{}.{}:{}|g", path, node, perfdata.value));
+            }
+        }
+    }
+    gauges.push(format!("{}.total_time:{}|g", prefix, seconds(duration)));
+    gauges
+}
+
+// The gauges joined into as few packets as possible
+fn packets(gauges: Vec<String>) -> Vec<String> {
+    let mut packets: Vec<String> = vec![];
+    for gauge in gauges {
+        match packets.last_mut() {
+            Some(packet) if packet.len() + 1 + gauge.len() <= PACKET => {
+                packet.push('\n');
+                packet.push_str(&gauge);
+            }
+            _ => packets.push(gauge),
+        }
+    }
+    packets
+}
+
+fn try_send(address: &str, packets: &[String]) -> Result<(), String> {
+    let error = |err: std::io::Error| format!("Could not send to {}: {}", address, err);
+    // without a port, the default one of statsd
+    let target = match address.to_socket_addrs() {
+        Ok(mut addresses) => addresses.next(),
+        Err(_) => (address.trim_start_matches('[').trim_end_matches(']'), PORT).to_socket_addrs().map_err(error)?.next(),
+    }.ok_or_else(|| format!("Could not send to {}: host has no address", address))?;
+    let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).map_err(error)?;
+    for packet in packets {
+        socket.send_to(packet.as_bytes(), target).map_err(error)?;
+    }
+    Ok(())
+}
+
+// Sends the results of the checks `names` to the statsd at `address`, `host` or `host:port`. Resolving and sending
+// block, so they run on a thread of their own.
+pub fn send(address: &str, prefix: &str, names: &[String], results: &Result<Vec<(String, Status)>, Status>, duration: Duration) -> JoinHandle<()> {
+    let packets = packets(gauges(prefix, names, results, duration));
+    let address = address.to_string();
+    std::thread::spawn(move || match try_send(&address, &packets) {
+        Ok(()) => verbose::log(1, || format!("Sent {} packets of gauges to {}", packets.len(), address)),
+        Err(err) => verbose::log(1, || err),
+    })
+}
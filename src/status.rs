@@ -0,0 +1,126 @@
+// The status of a check as defined by Nagios' plugin specification: a status type, a one line description, the
+// performance data and optionally further lines of long output.
+
+use crate::perfdata::PerfData;
+use std::fmt;
+use std::str::FromStr;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusType {
+    OK,
+    WARNING,
+    CRITICAL,
+    UNKNOWN,
+}
+
+impl StatusType {
+    // Returns the more severe of both status, ordered OK < UNKNOWN < WARNING < CRITICAL
+    pub fn worst(self, other: StatusType) -> StatusType {
+        let severity = |t: StatusType| match t {
+            StatusType::OK => 0,
+            StatusType::UNKNOWN => 1,
+            StatusType::WARNING => 2,
+            StatusType::CRITICAL => 3,
+        };
+        if severity(other) > severity(self) { other } else { self }
+    }
+
+    // Returns the less severe of both status
+    pub fn best(self, other: StatusType) -> StatusType {
+        if self.worst(other) == self { other } else { self }
+    }
+
+    pub fn exit_code(self) -> i32 {
+        match self {
+            StatusType::OK => 0,
+            StatusType::WARNING => 1,
+            StatusType::CRITICAL => 2,
+            StatusType::UNKNOWN => 3,
+        }
+    }
+}
+
+impl fmt::Display for StatusType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StatusType::OK => write!(f, "OK"),
+            StatusType::WARNING => write!(f, "WARNING"),
+            StatusType::CRITICAL => write!(f, "CRITICAL"),
+            StatusType::UNKNOWN => write!(f, "UNKNOWN"),
+        }
+    }
+}
+
+impl FromStr for StatusType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StatusType, String> {
+        match s.to_lowercase().as_str() {
+            "ok" => Ok(StatusType::OK),
+            "warning" => Ok(StatusType::WARNING),
+            "critical" => Ok(StatusType::CRITICAL),
+            "unknown" => Ok(StatusType::UNKNOWN),
+            _ => Err(format!("Invalid status '{}'", s)),
+        }
+    }
+}
+
+// Replaces statuses by others before they become the exit code, given like `warning=ok,unknown=critical`. Every status
+// is replaced once, `warning=ok,ok=critical` leaves a WARNING OK.
+#[derive(Clone, Debug, Default)]
+pub struct StatusMap(Vec<(StatusType, StatusType)>);
+
+impl StatusMap {
+    pub fn apply(&self, t: StatusType) -> StatusType {
+        self.0.iter().find(|&&(from, _)| from == t).map_or(t, |&(_, to)| to)
+    }
+}
+
+impl FromStr for StatusMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<StatusMap, String> {
+        s.split(',').map(|pair| match pair.split_once('=') {
+            Some((from, to)) => Ok((from.trim().parse()?, to.trim().parse()?)),
+            None => Err(format!("Invalid status mapping '{}', expected e.g. warning=ok", pair)),
+        }).collect::<Result<_, String>>().map(StatusMap)
+    }
+}
+
+#[derive(Debug)]
+pub struct Status {
+    pub t: StatusType,
+    pub description: String,
+    pub perfdata: Vec<PerfData>,
+    pub long_output: Vec<String>,
+    // The check's run time with thresholds of its own, the plugin times it otherwise
+    pub query_time: Option<Box<PerfData>>,
+}
+
+impl Status {
+    pub fn new(t: StatusType, description: String) -> Status {
+        Status { t, description, perfdata: vec![], long_output: vec![], query_time: None }
+    }
+}
+
+// Errors without a status of their own, e.g. a state file that cannot be written, are UNKNOWN
+impl From<String> for Status {
+    fn from(description: String) -> Status {
+        Status::new(StatusType::UNKNOWN, description)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}", self.t, self.description)?;
+        if !self.perfdata.is_empty() {
+            let perfdata: Vec<String> = self.perfdata.iter().map(|p| p.to_string()).collect();
+            write!(f, " | {}", perfdata.join(" "))?;
+        }
+        for line in &self.long_output {
+            write!(f, "\n{}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+// Threshold ranges as defined by the Nagios plugin development guidelines:
+//
+//   10      alert if x < 0 or x > 10
+//   10:     alert if x < 10
+//   ~:10    alert if x > 10
+//   10:20   alert if x < 10 or x > 20
+//   @10:20  alert if 10 <= x <= 20
+//
+// A missing start means 0, `~` means negative infinity and a missing end means infinity.
+//
+// Bounds may carry a time unit (`ms`, `s`, `m`, `h`, `d`, `w`), they are converted to seconds, e.g. `~:5m` is `~:300`.
+// Likewise, size units (`B`, `kB`, `MB`, `GB`, `TB`, `PB`) are converted to bytes.
+
+use std::fmt;
+use std::str::FromStr;
+use crate::units::parse_number;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Range {
+    start: Option<f64>,
+    end: Option<f64>,
+    inside: bool,
+}
+
+impl Range {
+    // Returns true iff `value` should raise an alert for this range
+    pub fn alerts(&self, value: f64) -> bool {
+        let within = self.start.is_none_or(|start| value >= start) && self.end.is_none_or(|end| value <= end);
+        within == self.inside
+    }
+
+    // The bound of a range that alerts above it, like `10` or `~:10`, for formats that only know upper levels
+    pub fn upper(&self) -> Option<f64> {
+        match (self.start, self.end) {
+            (None, Some(end)) if !self.inside => Some(end),
+            (Some(start), Some(end)) if start == 0.0 && !self.inside => Some(end),
+            _ => None,
+        }
+    }
+}
+
+fn parse_bound(s: &str, range: &str) -> Result<f64, String> {
+    parse_number(s).ok_or_else(|| format!("Invalid threshold range '{}'", range))
+}
+
+impl FromStr for Range {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Range, String> {
+        let (inside, spec) = match s.strip_prefix('@') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        if spec.is_empty() {
+            return Err(format!("Invalid threshold range '{}'", s));
+        }
+
+        let (start, end) = match spec.find(':') {
+            Some(pos) => {
+                let start = match &spec[..pos] {
+                    "~" => None,
+                    "" => Some(0.0),
+                    bound => Some(parse_bound(bound, s)?),
+                };
+                let end = match &spec[pos + 1..] {
+                    "" => None,
+                    bound => Some(parse_bound(bound, s)?),
+                };
+                (start, end)
+            }
+            None => (Some(0.0), Some(parse_bound(spec, s)?)),
+        };
+
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > end {
+                return Err(format!("Invalid threshold range '{}': start is greater than end", s));
+            }
+        }
+        Ok(Range { start, end, inside })
+    }
+}
+
+// Formats the range in the same syntax it is parsed from, which is also what perfdata expects
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.inside {
+            write!(f, "@")?;
+        }
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if start == 0.0 && !self.inside => write!(f, "{}", end),
+            (start, end) => {
+                match start {
+                    Some(start) => write!(f, "{}:", start)?,
+                    None => write!(f, "~:")?,
+                }
+                match end {
+                    Some(end) => write!(f, "{}", end),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+// Parses a comma separated list of ranges, e.g. `10,~:5,@1:2`
+pub fn parse_list(s: &str) -> Result<Vec<Range>, String> {
+    s.split(',').map(Range::from_str).collect()
+}
+
+// A simple comparison of the result against a number, e.g. alert if x <= 10. Every comparison has an equivalent range.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Ge,
+    Le,
+    Gt,
+    Lt,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    // Returns the range that alerts iff `x <op> s`
+    pub fn range(self, s: &str) -> Result<Range, String> {
+        let n = parse_number(s).ok_or_else(|| format!("Invalid threshold '{}'", s))?;
+        let (start, end, inside) = match self {
+            Comparison::Ge => (Some(n), None, true),
+            Comparison::Le => (None, Some(n), true),
+            Comparison::Gt => (None, Some(n), false),
+            Comparison::Lt => (Some(n), None, false),
+            Comparison::Eq => (Some(n), Some(n), true),
+            Comparison::Ne => (Some(n), Some(n), false),
+        };
+        Ok(Range { start, end, inside })
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Comparison, String> {
+        match s {
+            "ge" => Ok(Comparison::Ge),
+            "le" => Ok(Comparison::Le),
+            "gt" => Ok(Comparison::Gt),
+            "lt" => Ok(Comparison::Lt),
+            "eq" => Ok(Comparison::Eq),
+            "ne" => Ok(Comparison::Ne),
+            _ => Err(format!("Invalid comparison operator '{}'", s)),
+        }
+    }
+}
+
+// Parses a comma separated list of numbers compared with the operators in `comparisons`. A single operator applies
+// to all numbers.
+pub fn parse_compare_list(s: &str, comparisons: &[Comparison]) -> Result<Vec<Range>, String> {
+    let numbers : Vec<&str> = s.split(',').collect();
+    if comparisons.len() != 1 && comparisons.len() != numbers.len() {
+        return Err("Size of comparison operators and thresholds need to match".to_string());
+    }
+    numbers.iter().enumerate()
+        .map(|(i, n)| comparisons[if comparisons.len() == 1 { 0 } else { i }].range(n))
+        .collect()
+}
@@ -0,0 +1,250 @@
+// TLS setup following libpq's sslmode semantics:
+//
+//   disable      never use TLS
+//   prefer       use TLS if the server supports it, without verifying the certificate
+//   require      always use TLS, without verifying the certificate
+//   verify-ca    always use TLS and verify the certificate is signed by a trusted CA
+//   verify-full  like verify-ca, additionally the certificate needs to match the host name
+//
+// Trusted CAs are the system's default ones plus `~/.postgresql/root.crt`, if it exists, or only those of sslrootcert.
+// Like libpq, `sslrootcert=system` means the system's CAs alone and requires verify-full, and a client certificate is
+// taken from `~/.postgresql/postgresql.crt` and `~/.postgresql/postgresql.key` unless given explicitly.
+
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::ssl::{SslConnector, SslFiletype, SslMethod, SslVerifyMode};
+use openssl::x509::store::{X509Lookup, X509StoreBuilder, X509StoreBuilderRef};
+use openssl::x509::verify::X509VerifyFlags;
+use openssl::x509::{X509Ref, X509StoreContextRef};
+use tokio_postgres::config;
+use postgres_openssl::MakeTlsConnector;
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::future::Future;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SslMode {
+    Disable,
+    Prefer,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl SslMode {
+    // The mode postgres negotiates TLS with, verification is done by the connector
+    pub fn negotiation(self) -> config::SslMode {
+        match self {
+            SslMode::Disable => config::SslMode::Disable,
+            SslMode::Prefer => config::SslMode::Prefer,
+            SslMode::Require | SslMode::VerifyCa | SslMode::VerifyFull => config::SslMode::Require,
+        }
+    }
+}
+
+impl FromStr for SslMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SslMode, String> {
+        match s {
+            "disable" => Ok(SslMode::Disable),
+            "prefer" => Ok(SslMode::Prefer),
+            "require" => Ok(SslMode::Require),
+            "verify-ca" => Ok(SslMode::VerifyCa),
+            "verify-full" => Ok(SslMode::VerifyFull),
+            _ => Err(format!("Invalid sslmode '{}'", s)),
+        }
+    }
+}
+
+pub struct TlsConfig {
+    pub mode: SslMode,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    // passphrase of an encrypted key
+    pub password: Option<String>,
+    // a file of CA certificates, or `system`
+    pub root_cert: Option<String>,
+    pub crl: Option<PathBuf>,
+    // whether to send the host name in the handshake
+    pub sni: bool,
+    // the name verify-full matches the certificate against instead of the host connected to
+    pub hostname: Option<String>,
+}
+
+tokio::task_local! {
+    // why the server's certificate was rejected in a connection attempt of the task
+    static FAILURE: RefCell<Option<String>>;
+}
+
+// Runs a connection attempt, returns its result and the reason the server's certificate failed verification, if it did.
+// The driver's error only has OpenSSL's error stack.
+pub async fn verifying<F: Future>(future: F) -> (F::Output, Option<String>) {
+    FAILURE.scope(RefCell::new(None), async move {
+        let output = future.await;
+        (output, FAILURE.with(|failure| failure.take()))
+    }).await
+}
+
+// OpenSSL's X509_V_ERR_* codes the reason is explained for
+const UNABLE_TO_GET_ISSUER_CERT: i32 = 2;
+const UNABLE_TO_GET_CRL: i32 = 3;
+const DEPTH_ZERO_SELF_SIGNED_CERT: i32 = 18;
+const SELF_SIGNED_CERT_IN_CHAIN: i32 = 19;
+const UNABLE_TO_GET_ISSUER_CERT_LOCALLY: i32 = 20;
+const UNABLE_TO_VERIFY_LEAF_SIGNATURE: i32 = 21;
+const HOSTNAME_MISMATCH: i32 = 62;
+const IP_ADDRESS_MISMATCH: i32 = 64;
+
+// The subject of a certificate, like `CN=db1, O=Example`
+fn subject(cert: &X509Ref) -> String {
+    cert.subject_name().entries()
+        .map(|entry| format!("{}={}", entry.object().nid().short_name().unwrap_or("?"),
+                             entry.data().to_string().unwrap_or_default()))
+        .collect::<Vec<_>>().join(", ")
+}
+
+// The names a certificate is valid for: those of its subjectAltName, else its common name
+fn names(cert: &X509Ref) -> Vec<String> {
+    let alt_names: Vec<String> = cert.subject_alt_names().map(|names| names.iter().filter_map(|name| {
+        name.dnsname().map(|dns| dns.to_string()).or_else(|| match name.ipaddress()? {
+            ip if ip.len() == 4 => Some(IpAddr::from(<[u8; 4]>::try_from(ip).ok()?).to_string()),
+            ip => Some(IpAddr::from(<[u8; 16]>::try_from(ip).ok()?).to_string()),
+        })
+    }).collect()).unwrap_or_default();
+    if !alt_names.is_empty() {
+        return alt_names;
+    }
+    cert.subject_name().entries_by_nid(Nid::COMMONNAME)
+        .filter_map(|cn| cn.data().to_string().ok()).collect()
+}
+
+// Describes why a certificate of the server's chain was rejected, with what to check
+fn rejection(ctx: &X509StoreContextRef, expected: &str, roots: &str, crl: Option<&Path>) -> String {
+    let error = ctx.error();
+    let cert = ctx.current_cert();
+    let subject = cert.map(subject).unwrap_or_default();
+    match error.as_raw() {
+        HOSTNAME_MISMATCH | IP_ADDRESS_MISMATCH => format!("Server certificate is for {}, not {}",
+                                                           cert.map(names).unwrap_or_default().join(", "), expected),
+        UNABLE_TO_GET_ISSUER_CERT | DEPTH_ZERO_SELF_SIGNED_CERT | SELF_SIGNED_CERT_IN_CHAIN
+        | UNABLE_TO_GET_ISSUER_CERT_LOCALLY | UNABLE_TO_VERIFY_LEAF_SIGNATURE =>
+            format!("Server certificate not issued by a trusted CA ({} for {}), {} do not include its CA",
+                    error.error_string(), subject, roots),
+        UNABLE_TO_GET_CRL => format!("Server certificate not checked for revocation, {} has no CRL of the CA of {}",
+                                     crl.map(|crl| crl.display().to_string()).unwrap_or_default(), subject),
+        _ => format!("Server certificate verification failed: {} for {}", error.error_string(), subject),
+    }
+}
+
+// Returns `~/.postgresql/<name>` if it exists
+fn default_file(name: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(env::var_os("HOME")?).join(".postgresql").join(name);
+    if path.is_file() { Some(path) } else { None }
+}
+
+fn describe(path: &Path, err: impl ToString) -> String {
+    format!("Could not set up TLS with '{}': {}", path.display(), err.to_string())
+}
+
+// Adds the CRLs of `path` to a store and has all certificates of the chain checked against them, like libpq
+fn load_crl(store: &mut X509StoreBuilderRef, path: &Path) -> Result<(), String> {
+    let lookup = store.add_lookup(X509Lookup::file()).map_err(|err| describe(path, err))?;
+    lookup.load_crl_file(path, SslFiletype::PEM).map_err(|err| describe(path, err))?;
+    store.set_flags(X509VerifyFlags::CRL_CHECK | X509VerifyFlags::CRL_CHECK_ALL).map_err(|err| describe(path, err))
+}
+
+impl TlsConfig {
+    pub fn connector(&self) -> Result<MakeTlsConnector, String> {
+        let mut builder = SslConnector::builder(SslMethod::tls()).map_err(|err| format!("Could not set up TLS: {}", err))?;
+        let verify = matches!(self.mode, SslMode::VerifyCa | SslMode::VerifyFull);
+        if self.hostname.is_some() && self.mode != SslMode::VerifyFull {
+            return Err("--ssl-hostname needs sslmode verify-full".to_string());
+        }
+        // the CAs a rejected certificate is explained with
+        let mut roots = "the system's CAs and ~/.postgresql/root.crt".to_string();
+        match self.root_cert.as_deref() {
+            _ if !verify => builder.set_verify(SslVerifyMode::NONE),
+            Some("system") => {
+                if let Some(ref crl) = self.crl {
+                    load_crl(builder.cert_store_mut(), crl)?;
+                }
+                roots = "the system's CAs".to_string();
+            }
+            // the certificates of sslrootcert replace the system's CAs
+            Some(path) => {
+                let path = Path::new(path);
+                let mut store = X509StoreBuilder::new().map_err(|err| describe(path, err))?;
+                let lookup = store.add_lookup(X509Lookup::file()).map_err(|err| describe(path, err))?;
+                lookup.load_cert_file(path, SslFiletype::PEM).map_err(|err| describe(path, err))?;
+                if let Some(ref crl) = self.crl {
+                    load_crl(&mut store, crl)?;
+                }
+                builder.set_cert_store(store.build());
+                roots = format!("the certificates of '{}'", path.display());
+            }
+            None => {
+                if let Some(path) = default_file("root.crt") {
+                    builder.set_ca_file(&path).map_err(|err| describe(&path, err))?;
+                }
+                if let Some(ref crl) = self.crl {
+                    load_crl(builder.cert_store_mut(), crl)?;
+                }
+            }
+        }
+
+        let cert = self.cert.clone().or_else(|| default_file("postgresql.crt"));
+        let key = self.key.clone().or_else(|| default_file("postgresql.key"));
+        match (cert, key) {
+            (Some(cert), Some(key)) => {
+                builder.set_certificate_chain_file(&cert).map_err(|err| describe(&cert, err))?;
+                let pem = fs::read(&key).map_err(|err| describe(&key, err))?;
+                let pkey = match self.password {
+                    Some(ref password) => PKey::private_key_from_pem_passphrase(&pem, password.as_bytes()),
+                    None => PKey::private_key_from_pem(&pem),
+                }.map_err(|err| describe(&key, err))?;
+                builder.set_private_key(&pkey).map_err(|err| describe(&key, err))?;
+                builder.check_private_key().map_err(|err| describe(&key, err))?;
+            }
+            (Some(_), None) | (None, Some(_)) if self.cert.is_some() || self.key.is_some() => {
+                return Err("Client certificates need both --sslcert and --sslkey".to_string());
+            }
+            _ => {}
+        }
+
+        let mut connector = MakeTlsConnector::new(builder.build());
+        let (mode, sni, hostname, crl) = (self.mode, self.sni, self.hostname.clone(), self.crl.clone());
+        connector.set_callback(move |config, domain| {
+            config.set_use_server_name_indication(sni);
+            if mode != SslMode::VerifyFull {
+                config.set_verify_hostname(false);
+            } else if let Some(ref hostname) = hostname {
+                // the connector only verifies the host connected to
+                config.set_verify_hostname(false);
+                match hostname.parse::<IpAddr>() {
+                    Ok(ip) => config.param_mut().set_ip(ip)?,
+                    Err(_) => config.param_mut().set_host(hostname)?,
+                }
+            }
+            if verify {
+                let expected = hostname.clone().unwrap_or_else(|| domain.to_string());
+                let (roots, crl) = (roots.clone(), crl.clone());
+                config.set_verify_callback(SslVerifyMode::PEER, move |ok, ctx| {
+                    if !ok {
+                        let reason = rejection(ctx, &expected, &roots, crl.as_deref());
+                        let _ = FAILURE.try_with(|failure| {
+                            failure.borrow_mut().get_or_insert(reason);
+                        });
+                    }
+                    ok
+                });
+            }
+            Ok(())
+        });
+        Ok(connector)
+    }
+}
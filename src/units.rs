@@ -0,0 +1,51 @@
+// Units of measurement for thresholds and output. Times are converted to seconds and sizes to bytes, sizes use
+// postgres' units where `kB` is 1024 bytes.
+
+use std::str::FromStr;
+
+pub const TIME_UNITS: &[(&str, f64)] = &[
+    ("ms", 0.001),
+    ("s", 1.0),
+    ("m", 60.0),
+    ("h", 3600.0),
+    ("d", 86400.0),
+    ("w", 604800.0),
+];
+
+pub const BYTE_UNITS: &[(&str, f64)] = &[
+    ("B", 1.0),
+    ("kB", 1024.0),
+    ("MB", 1048576.0),
+    ("GB", 1073741824.0),
+    ("TB", 1099511627776.0),
+    ("PB", 1125899906842624.0),
+];
+
+// Parses a number with an optional time or size unit suffix
+pub fn parse_number(s: &str) -> Option<f64> {
+    if let Ok(number) = f64::from_str(s) {
+        return Some(number);
+    }
+    TIME_UNITS.iter().chain(BYTE_UNITS.iter())
+        .filter_map(|&(unit, factor)| s.strip_suffix(unit).map(|number| (number, factor)))
+        .filter_map(|(number, factor)| f64::from_str(number.trim_end()).ok().map(|number| number * factor))
+        .next()
+}
+
+// Formats a number of bytes with the largest unit it is at least one of, e.g. `1.5 MB`
+pub fn format_bytes(bytes: f64) -> String {
+    let &(unit, factor) = BYTE_UNITS.iter().rev()
+        .find(|&&(_, factor)| bytes.abs() >= factor)
+        .unwrap_or(&BYTE_UNITS[0]);
+    format!("{} {}", (bytes / factor * 10.0).round() / 10.0, unit)
+}
+
+// The age in whole days, hours, minutes or seconds for the output
+pub fn format_age(seconds: f64) -> String {
+    match seconds {
+        s if s >= 86400.0 => format!("{}d", (s / 86400.0).floor()),
+        s if s >= 3600.0 => format!("{}h", (s / 3600.0).floor()),
+        s if s >= 60.0 => format!("{}m", (s / 60.0).floor()),
+        s => format!("{}s", s.round()),
+    }
+}
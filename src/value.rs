@@ -0,0 +1,172 @@
+// A single value of the result set. We do not want to care about postgres type conversions, so all integer
+// types end up as `Int`, all floating point and numeric types end up as `Float` and all character types as `Text`.
+// Booleans are kept as `Bool`. Intervals end up as `Interval` in seconds, timestamps need to be turned into an
+// `Interval` by `Value::age` before they can be compared. NULL is `Null`.
+
+use byteorder::{BigEndian, ReadBytesExt};
+use crate::encoding;
+use tokio_postgres::types::{FromSql, Type};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::prelude::Read;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bool(bool),
+    Interval(f64),
+    // microseconds since 2000-01-01, in UTC for timestamptz and in the session's time zone for timestamp
+    Timestamp(i64),
+    TimestampTz(i64),
+    Null,
+}
+
+impl Value {
+    // The value thresholds are compared against, `None` for non-numeric values
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Int(i) => Some(i as f64),
+            Value::Float(f) | Value::Interval(f) => Some(f),
+            Value::Text(_) | Value::Bool(_) | Value::Timestamp(_) | Value::TimestampTz(_) | Value::Null => None,
+        }
+    }
+
+    // Rounds floating point values to `digits` decimal places, integers are left untouched
+    pub fn round(self, digits: usize) -> Value {
+        let factor = 10f64.powi(digits as i32);
+        match self {
+            Value::Float(f) => Value::Float((f * factor).round() / factor),
+            Value::Interval(f) => Value::Interval((f * factor).round() / factor),
+            value => value,
+        }
+    }
+
+    // Turns timestamps into their age in seconds relative to the given `now()` and `localtimestamp`
+    pub fn age(self, now: i64, local_now: i64) -> Value {
+        let seconds = |then: i64, now: i64| match then {
+            i64::MAX => f64::NEG_INFINITY,
+            i64::MIN => f64::INFINITY,
+            then => (now - then) as f64 / 1_000_000f64,
+        };
+        match self {
+            Value::TimestampTz(then) => Value::Interval(seconds(then, now)),
+            Value::Timestamp(then) => Value::Interval(seconds(then, local_now)),
+            value => value,
+        }
+    }
+
+    // A value of the simple query protocol, which sends everything as text. Numbers are recognized by their text,
+    // everything else stays `Text`.
+    pub fn from_text(text: Option<&str>) -> Value {
+        match text {
+            None => Value::Null,
+            Some(text) => match (text.parse::<i64>(), text.parse::<f64>()) {
+                (Ok(i), _) => Value::Int(i),
+                (_, Ok(f)) => Value::Float(f),
+                _ => Value::Text(text.to_string()),
+            },
+        }
+    }
+
+    pub fn is_timestamp(&self) -> bool {
+        matches!(*self, Value::Timestamp(_) | Value::TimestampTz(_))
+    }
+
+    // Unit of measurement for the performance data
+    pub fn uom(&self) -> &'static str {
+        match *self {
+            Value::Interval(_) => "s",
+            _ => "",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Text(ref t) => write!(f, "{}", t),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Interval(i) => write!(f, "{}s", i),
+            Value::Timestamp(t) | Value::TimestampTz(t) => write!(f, "{}", t),
+            Value::Null => write!(f, "NULL"),
+        }
+    }
+}
+
+// Numerics are sent as a list of base 10000 digits, see `numeric_send` in postgres' src/backend/utils/adt/numeric.c
+fn read_numeric<R: Read>(raw: &mut R) -> io::Result<f64> {
+    let ndigits = raw.read_i16::<BigEndian>()?;
+    let weight = raw.read_i16::<BigEndian>()? as i32;
+    let sign = raw.read_u16::<BigEndian>()?;
+    let _dscale = raw.read_u16::<BigEndian>()?;
+
+    let mut value = 0f64;
+    for i in 0..ndigits as i32 {
+        let digit = raw.read_i16::<BigEndian>()? as f64;
+        value += digit * 10000f64.powi(weight - i);
+    }
+    Ok(match sign {
+        0x4000 => -value,
+        0xC000 => f64::NAN,
+        0xD000 => f64::INFINITY,
+        0xF000 => f64::NEG_INFINITY,
+        _ => value,
+    })
+}
+
+// Intervals are sent as microseconds, days and months. Like postgres' `extract(epoch from ...)`, a month counts
+// as 30 days and a year as 365.25 days.
+fn read_interval<R: Read>(raw: &mut R) -> io::Result<f64> {
+    let micros = raw.read_i64::<BigEndian>()?;
+    let days = raw.read_i32::<BigEndian>()?;
+    let months = raw.read_i32::<BigEndian>()?;
+
+    let seconds_per_day = 86400f64;
+    Ok(micros as f64 / 1_000_000f64
+        + days as f64 * seconds_per_day
+        + (months / 12) as f64 * 365.25 * seconds_per_day
+        + (months % 12) as f64 * 30f64 * seconds_per_day)
+}
+
+// Converts via the shortest decimal representation, so 1.23::real is 1.23 and not 1.2300000190734863
+fn widen(f: f32) -> f64 {
+    f.to_string().parse().unwrap_or(f as f64)
+}
+
+impl<'a> FromSql<'a> for Value {
+    fn from_sql(ty: &Type, mut raw: &'a [u8]) -> Result<Value, Box<dyn Error + Sync + Send>> {
+        let raw = &mut raw;
+        let val = match *ty {
+            Type::BOOL => Value::Bool(raw.read_u8()? != 0),
+            Type::CHAR => Value::Int(raw.read_i8()? as i64),
+            Type::INT2 => Value::Int(raw.read_i16::<BigEndian>()? as i64),
+            Type::INT4 => Value::Int(raw.read_i32::<BigEndian>()? as i64),
+            Type::OID => Value::Int(raw.read_u32::<BigEndian>()? as i64),
+            Type::FLOAT4 => Value::Float(widen(raw.read_f32::<BigEndian>()?)),
+            Type::FLOAT8 => Value::Float(raw.read_f64::<BigEndian>()?),
+            Type::NUMERIC => Value::Float(read_numeric(raw)?),
+            Type::INTERVAL => Value::Interval(read_interval(raw)?),
+            Type::TIMESTAMP => Value::Timestamp(raw.read_i64::<BigEndian>()?),
+            Type::TIMESTAMPTZ => Value::TimestampTz(raw.read_i64::<BigEndian>()?),
+            Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => Value::Text(encoding::current().decode(raw)),
+            _ => Value::Int(raw.read_i64::<BigEndian>()?),
+        };
+        Ok(val)
+    }
+
+    fn from_sql_null(_: &Type) -> Result<Value, Box<dyn Error + Sync + Send>> {
+        Ok(Value::Null)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        matches!(*ty, Type::BOOL | Type::CHAR | Type::INT2 | Type::INT4 | Type::INT8 | Type::OID
+                 | Type::FLOAT4 | Type::FLOAT8 | Type::NUMERIC
+                 | Type::INTERVAL | Type::TIMESTAMP | Type::TIMESTAMPTZ
+                 | Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME)
+    }
+}
@@ -0,0 +1,264 @@
+// Credentials from HashiCorp Vault: with `--vault-path`, the user and password of the connection are read from a secret
+// when connecting. Both a KV secret with `username` and `password` fields (e.g. `secret/data/monitoring/pg` of the KV
+// version 2 engine) and the dynamic credentials of the database secrets engine (e.g. `database/creds/monitoring`)
+// work. The server is `--vault-addr` or `$VAULT_ADDR`, the token `$VAULT_TOKEN`, `~/.vault-token` or, with
+// `--vault-role-id` and `--vault-secret-id-file`, the one an AppRole login issues.
+//
+// Dynamic credentials and AppRole tokens are leased. They are kept until two thirds of their lease have passed and then
+// renewed, so a long-running program, like the agent, a listener or the exporter, does not create a new database user
+// for every run. A lease that cannot be renewed any more is replaced by a new one. A single run revokes its leases
+// when it ends.
+
+use crate::http;
+use crate::json::{self, Json};
+use crate::options::Options;
+use crate::verbose;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub struct Credentials {
+    pub user: Option<String>,
+    pub password: String,
+}
+
+// Leased secrets by what they were read with, renewed when two thirds of the lease have passed and kept until it
+// expires. Token logins are cached as a password.
+struct Lease {
+    key: String,
+    user: Option<String>,
+    password: String,
+    vault: Vault,
+    leased: Leased,
+    renewable: bool,
+    renew: Instant,
+    expires: Instant,
+}
+
+// What a lease is for, a secret has the lease ID and the token it was read with
+#[derive(Clone)]
+enum Leased {
+    Secret(String, String),
+    Token,
+}
+
+static LEASES: Mutex<Vec<Lease>> = Mutex::new(Vec::new());
+
+fn cached(key: &str) -> Option<Credentials> {
+    let (vault, leased, password) = {
+        let mut leases = LEASES.lock().unwrap_or_else(|err| err.into_inner());
+        leases.retain(|lease| lease.expires > Instant::now());
+        let lease = leases.iter().find(|lease| lease.key == key)?;
+        if lease.renew > Instant::now() {
+            return Some(Credentials { user: lease.user.clone(), password: lease.password.clone() });
+        }
+        if !lease.renewable {
+            leases.retain(|lease| lease.key != key);
+            return None;
+        }
+        (lease.vault.clone(), lease.leased.clone(), lease.password.clone())
+    };
+    let renewed = vault.renew(&leased, &password).map_err(|err| verbose::log(1, || format!("Could not renew the lease: {}", err)));
+    let mut leases = LEASES.lock().unwrap_or_else(|err| err.into_inner());
+    let position = leases.iter().position(|lease| lease.key == key)?;
+    match renewed {
+        Ok(seconds) if seconds >= 3.0 => {
+            let lease = &mut leases[position];
+            lease.renew = Instant::now() + Duration::from_secs_f64(seconds * 2.0 / 3.0);
+            lease.expires = Instant::now() + Duration::from_secs_f64(seconds);
+            Some(Credentials { user: lease.user.clone(), password: lease.password.clone() })
+        }
+        // too close to its maximum lifetime, or not renewable at all, the secret is read anew
+        _ => {
+            leases.remove(position);
+            None
+        }
+    }
+}
+
+// Keeps credentials leased for `seconds`, unless the lease is too short to be worth it
+fn cache(key: &str, credentials: &Credentials, vault: &Vault, leased: Leased, renewable: bool, seconds: f64) {
+    if seconds >= 3.0 {
+        LEASES.lock().unwrap_or_else(|err| err.into_inner()).push(Lease {
+            key: key.to_string(),
+            user: credentials.user.clone(),
+            password: credentials.password.clone(),
+            vault: vault.clone(),
+            leased,
+            renewable,
+            renew: Instant::now() + Duration::from_secs_f64(seconds * 2.0 / 3.0),
+            expires: Instant::now() + Duration::from_secs_f64(seconds),
+        });
+    }
+}
+
+// Revokes the leases taken, so a run does not leave database users behind. The secrets go first, the tokens they
+// were read with may be among the leases.
+pub fn revoke() {
+    let mut leases = std::mem::take(&mut *LEASES.lock().unwrap_or_else(|err| err.into_inner()));
+    leases.sort_by_key(|lease| matches!(lease.leased, Leased::Token));
+    for lease in leases {
+        let revoked = match lease.leased {
+            Leased::Secret(ref id, ref token) => {
+                lease.vault.call("PUT", "sys/leases/revoke", Some(token), Some(&format!("{{\"lease_id\":{}}}", json::string(id))))
+            }
+            Leased::Token => lease.vault.call("POST", "auth/token/revoke-self", Some(&lease.password), None),
+        };
+        match revoked {
+            Ok(_) => verbose::log(1, || "Revoked a Vault lease".to_string()),
+            Err(err) => verbose::log(1, || format!("Could not revoke a Vault lease: {}", err)),
+        }
+    }
+}
+
+// The Vault server and how requests are sent to it
+#[derive(Clone, Debug)]
+struct Vault {
+    addr: String,
+    cacert: Option<String>,
+    namespace: Option<String>,
+}
+
+impl Vault {
+    // Sends a request to the API, returns the response document or Vault's errors
+    fn call(&self, method: &str, path: &str, token: Option<&str>, body: Option<&str>) -> Result<Json, String> {
+        let mut headers = vec![];
+        if let Some(token) = token {
+            headers.push(("X-Vault-Token", token));
+        }
+        if let Some(ref namespace) = self.namespace {
+            headers.push(("X-Vault-Namespace", namespace.as_str()));
+        }
+        let url = format!("{}/v1/{}", self.addr, path);
+        let response = http::request(method, &url, &headers, body, self.cacert.as_deref())?;
+        // revoking answers without a document
+        if response.status == 204 {
+            return Ok(Json::Null);
+        }
+        let document = json::parse(&response.body);
+        if response.status != 200 {
+            let errors: Vec<&str> = document.as_ref().ok().and_then(|document| document.get("errors")).and_then(|errors| errors.as_array())
+                .map(|errors| errors.iter().filter_map(|error| error.as_str()).collect()).unwrap_or_default();
+            return Err(match errors.is_empty() {
+                true => format!("Vault answered {} with HTTP status {}", path, response.status),
+                false => format!("Vault answered {} with HTTP status {}: {}", path, response.status, errors.join(", ")),
+            });
+        }
+        document.map_err(|err| format!("Invalid answer from Vault for {}: {}", path, err))
+    }
+
+    // Renews a lease, returns for how many seconds it was extended
+    fn renew(&self, leased: &Leased, password: &str) -> Result<f64, String> {
+        verbose::log(1, || "Renewing a Vault lease".to_string());
+        let (response, lease) = match *leased {
+            Leased::Secret(ref id, ref token) => {
+                let response = self.call("PUT", "sys/leases/renew", Some(token), Some(&format!("{{\"lease_id\":{}}}", json::string(id))))?;
+                (response, None)
+            }
+            Leased::Token => (self.call("POST", "auth/token/renew-self", Some(password), Some("{}"))?, Some("auth")),
+        };
+        let lease = match lease {
+            Some(field) => response.get(field),
+            None => Some(&response),
+        };
+        lease.and_then(|lease| lease.get("lease_duration")).and_then(|d| d.as_f64()).ok_or_else(|| "Vault renewed no lease".to_string())
+    }
+
+    // The token of `$VAULT_TOKEN` or `~/.vault-token`, or one of an AppRole login
+    fn token(&self, approle: Option<&(String, String)>) -> Result<String, String> {
+        if let Some((role_id, path)) = approle {
+            let key = format!("approle\0{}\0{}", self.addr, role_id);
+            if let Some(token) = cached(&key) {
+                return Ok(token.password);
+            }
+            let secret_id = std::fs::read_to_string(path).map_err(|err| format!("Could not read secret ID file '{}': {}", path, err))?;
+            verbose::log(1, || format!("Logging in to Vault with role ID {}", role_id));
+            let body = format!("{{\"role_id\":{},\"secret_id\":{}}}", json::string(role_id), json::string(secret_id.trim()));
+            let response = self.call("POST", "auth/approle/login", None, Some(&body))?;
+            let auth = response.get("auth");
+            let token = auth.and_then(|auth| auth.get("client_token")).and_then(|token| token.as_str())
+                .ok_or("Vault's AppRole login returned no token")?;
+            let token = Credentials { user: None, password: token.to_string() };
+            let renewable = auth.and_then(|auth| auth.get("renewable")).and_then(|renewable| renewable.as_bool()).unwrap_or(false);
+            let seconds = auth.and_then(|auth| auth.get("lease_duration")).and_then(|d| d.as_f64()).unwrap_or(0.0);
+            cache(&key, &token, self, Leased::Token, renewable, seconds);
+            return Ok(token.password);
+        }
+        if let Some(token) = env::var("VAULT_TOKEN").ok().filter(|token| !token.is_empty()) {
+            return Ok(token);
+        }
+        let path = PathBuf::from(env::var_os("HOME").unwrap_or_default()).join(".vault-token");
+        match std::fs::read_to_string(&path) {
+            Ok(token) => Ok(token.trim().to_string()),
+            Err(_) => Err("No Vault token, set $VAULT_TOKEN, log in with `vault login` or use --vault-role-id".to_string()),
+        }
+    }
+}
+
+// Where the credentials of `--vault-path` are read from, and the AppRole's ID and secret ID file to log in with
+#[derive(Clone, Debug)]
+pub struct Source {
+    vault: Vault,
+    path: String,
+    approle: Option<(String, String)>,
+}
+
+// The source of the credentials of `--vault-path`, if given
+pub fn source(options: &Options) -> Result<Option<Source>, String> {
+    let path = match options.value_of("vault-path") {
+        Some(path) => path.trim_matches('/').to_string(),
+        None => return Ok(None),
+    };
+    let addr = match options.value_of("vault-addr").map(|addr| addr.to_string()).or_else(|| env::var("VAULT_ADDR").ok()) {
+        Some(addr) => addr.trim_end_matches('/').to_string(),
+        None => return Err("--vault-path needs --vault-addr or $VAULT_ADDR".to_string()),
+    };
+    let vault = Vault {
+        addr,
+        cacert: options.value_of("vault-cacert").map(|cacert| cacert.to_string()).or_else(|| env::var("VAULT_CACERT").ok()),
+        namespace: env::var("VAULT_NAMESPACE").ok().filter(|namespace| !namespace.is_empty()),
+    };
+    let approle = match options.value_of("vault-role-id") {
+        Some(role_id) => {
+            let path = options.value_of("vault-secret-id-file").ok_or("--vault-role-id needs --vault-secret-id-file")?;
+            Some((role_id.to_string(), path.to_string()))
+        }
+        None => None,
+    };
+    Ok(Some(Source { vault, path, approle }))
+}
+
+impl Source {
+    // The credentials of the secret, the leased ones while they last
+    pub fn credentials(&self) -> Result<Credentials, String> {
+        let (vault, path) = (&self.vault, self.path.as_str());
+        let token = vault.token(self.approle.as_ref())?;
+        let key = format!("secret\0{}\0{}\0{}", vault.addr, path, token);
+        if let Some(credentials) = cached(&key) {
+            verbose::log(1, || format!("Reusing the leased credentials of {}", path));
+            return Ok(credentials);
+        }
+
+        verbose::log(1, || format!("Reading {} from Vault", path));
+        let response = vault.call("GET", path, Some(&token), None)?;
+        let data = response.get("data").ok_or_else(|| format!("Vault secret {} has no data", path))?;
+        // the KV version 2 engine nests the fields in the secret's metadata
+        let fields = match data.get("data") {
+            Some(fields @ Json::Object(_)) if data.get("password").is_none() => fields,
+            _ => data,
+        };
+        let field = |name: &str| fields.get(name).and_then(|value| value.as_str()).map(|value| value.to_string());
+        let credentials = Credentials {
+            user: field("username").or_else(|| field("user")),
+            password: field("password").ok_or_else(|| format!("Vault secret {} has no password", path))?,
+        };
+        // only dynamic credentials have a lease, a KV secret is read again, so a changed password takes effect
+        if let Some(id) = response.get("lease_id").and_then(|id| id.as_str()).filter(|id| !id.is_empty()) {
+            let renewable = response.get("renewable").and_then(|renewable| renewable.as_bool()).unwrap_or(false);
+            let seconds = response.get("lease_duration").and_then(|d| d.as_f64()).unwrap_or(0.0);
+            cache(&key, &credentials, vault, Leased::Secret(id.to_string(), token.clone()), renewable, seconds);
+        }
+        Ok(credentials)
+    }
+}
@@ -0,0 +1,18 @@
+// Debug output on stderr for `-v`, `-vv` and `-vvv`, stdout is left to the plugin output. The first level logs the
+// connection and every check's status and run time, the second the queries and how thresholds were evaluated, the
+// third the values of every row returned.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static LEVEL: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_level(level: usize) {
+    LEVEL.store(level, Ordering::Relaxed);
+}
+
+// Prints the message if the verbosity is at least `level`, it is only formatted then
+pub fn log<F: FnOnce() -> String>(level: usize, message: F) {
+    if LEVEL.load(Ordering::Relaxed) >= level {
+        eprintln!("{}", message());
+    }
+}
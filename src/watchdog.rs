@@ -0,0 +1,52 @@
+// The overall timeout of `--timeout`: the run is abandoned when it elapses and the result tells what the program was
+// doing at the time, so the scheduler sees a proper result instead of killing the plugin. The phase is updated as the
+// run goes from connecting to running the checks, every run has its own. The phases are kept with the time they
+// started and ended, for tracing.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+// A phase of a run and when it started and ended
+pub struct Phase {
+    pub name: String,
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+tokio::task_local! {
+    static PHASES: Arc<Mutex<Vec<Phase>>>;
+}
+
+// Sets the phase of the current run, if it is limited. The previous phase ends.
+pub fn phase(phase: &str) {
+    let _ = PHASES.try_with(|phases| {
+        let mut phases = phases.lock().unwrap_or_else(|err| err.into_inner());
+        let now = SystemTime::now();
+        if let Some(last) = phases.last_mut() {
+            last.end = now;
+        }
+        phases.push(Phase { name: phase.to_string(), start: now, end: now });
+    });
+}
+
+// Runs `future` until it completes, or returns the phase it was in when `timeout` elapsed
+pub async fn limit<F: Future>(timeout: Option<Duration>, future: F) -> Result<F::Output, String> {
+    trace(timeout, future).await.0
+}
+
+// Like `limit`, with the phases the run went through
+pub async fn trace<F: Future>(timeout: Option<Duration>, future: F) -> (Result<F::Output, String>, Vec<Phase>) {
+    let phases = Arc::new(Mutex::new(vec![]));
+    let future = PHASES.scope(phases.clone(), future);
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future).await.map_err(|_| ()),
+        None => Ok(future.await),
+    };
+    let mut phases = std::mem::take(&mut *phases.lock().unwrap_or_else(|err| err.into_inner()));
+    if let Some(last) = phases.last_mut() {
+        last.end = SystemTime::now();
+    }
+    let result = result.map_err(|_| phases.last().map(|phase| phase.name.clone()).unwrap_or_default());
+    (result, phases)
+}